@@ -1,8 +1,22 @@
-use std::{path::PathBuf, time::Instant};
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+    path::PathBuf,
+    str::FromStr,
+    time::Instant,
+};
 
 use argh::FromArgs;
-use borld::preprocess::Object;
-use shpank::spatial::Spatial;
+use borld::preprocess::{split_multipoints, Object, Variant};
+use shpank::{
+    crs::Crs,
+    encoding::Encoding,
+    parse::Parser,
+    project::{CoordinateSystem, Transform},
+    shape::Shape,
+    simplify::Algorithm,
+    spatial::{self, RecordPairs},
+};
 
 #[derive(Debug, FromArgs)]
 /// Parse a .shp- and .dbf file pair then convert to objects.
@@ -18,30 +32,246 @@ struct Args {
     /// output file path, uses shp file stem with ".borld" ending if not given
     #[argh(option)]
     out: Option<PathBuf>,
+
+    /// encoding of the dBASE text, e.g. "1252" or "utf-8".
+    /// Overrides the .cpg file and the encoding given in the dBASE header
+    #[argh(option)]
+    encoding: Option<Encoding>,
+
+    /// coordinate system to project the shapes to, e.g. "utm33", "EPSG:25833", "web-mercator"
+    /// or "enu:10.75,59.91" for metres around an origin.
+    /// The shapes are taken to be in the system of the .prj file, see --from
+    #[argh(option)]
+    project: Option<CoordinateSystem>,
+
+    /// coordinate system the shapes are in, e.g. "wgs84" or "EPSG:25833".
+    /// Needed to project when there is no .prj file, and overrides it if there is
+    #[argh(option)]
+    from: Option<CoordinateSystem>,
+
+    /// check polygons and lines for problems such as unclosed or self-intersecting rings,
+    /// and repair them before projecting. Shapes with Z or M values are checked but not repaired
+    #[argh(switch)]
+    repair: bool,
+
+    /// tolerance for a simplified level of detail of lines and polygons, in units of the output
+    /// coordinate system. Repeat for more levels, from fine to coarse, e.g. "--simplify 10 --simplify 100"
+    #[argh(option)]
+    simplify: Vec<f64>,
+
+    /// how to simplify, "douglas-peucker" (the default) or "visvalingam-whyatt"
+    #[argh(option, default = "Algorithm::default()")]
+    simplify_algorithm: Algorithm,
+}
+
+/// How many problems are printed, the rest are only counted
+const PRINTED_ISSUES: usize = 20;
+
+/// For mistakes in the arguments or input files, which are not bugs.
+fn fail(message: &str) -> ! {
+    eprintln!("{message}");
+    std::process::exit(1)
 }
 
 fn main() {
-    let Args { shp, dbf, out } = argh::from_env();
+    let Args {
+        shp,
+        dbf,
+        out,
+        encoding,
+        project,
+        from,
+        repair,
+        simplify,
+        simplify_algorithm,
+    } = argh::from_env();
 
     let out = out.unwrap_or_else(|| shp.with_extension("borld"));
 
+    let transform = project.map(|to| {
+        let prj = shp.with_extension("prj");
+        let from = match from {
+            Some(from) => from,
+            None if prj.is_file() => {
+                let crs = Crs::from_prj_file(&prj)
+                    .unwrap_or_else(|e| fail(&format!("can't read {prj:?}: {e}")));
+                CoordinateSystem::from_crs(&crs).unwrap_or_else(|| {
+                    fail(&format!(
+                        "can't project from {:?} given by {prj:?}, give a known system with --from",
+                        crs.name
+                    ))
+                })
+            }
+            None => fail(&format!(
+                "no {prj:?} to tell which coordinate system the shapes are in, give it with --from"
+            )),
+        };
+
+        println!("Projecting from {from:?} to {to:?}");
+        Transform::new(from, to)
+    });
+
     let start = Instant::now();
-    println!("Creating shp objects from {shp:?} and {dbf:?}");
+    println!("Streaming objects from {shp:?} and {dbf:?} to {out:?}");
 
-    let objects = Spatial::new(&shp, &dbf).unwrap().into_objects();
+    // One record pair at a time, so memory use does not grow with the input
+    let mut shp_parser = Parser::new(&shp).unwrap();
+    let mut dbf_parser = Parser::new_dbf(&dbf).unwrap();
+    if let Some(encoding) = encoding {
+        dbf_parser = dbf_parser.with_encoding(encoding);
+    }
 
-    println!(
-        "shp objects ok ({:.2}s), processing..",
-        start.elapsed().as_secs_f32()
-    );
+    let shp_records = shp_parser.shp_records().unwrap();
+    let dbf_header = dbf_parser.parse_dbase_header().unwrap();
+    let dbf_records = dbf_parser.dbf_records(&dbf_header);
+
+    let fclass_idx = dbf_header
+        .index_of("fclass")
+        .expect("dBASE without fclass unhandled");
+    let name_idx = dbf_header
+        .index_of("name")
+        .expect("dBASE without name unhandled");
 
-    let objects: Vec<Object> = objects.into_iter().map(Into::into).collect();
+    let mut writer = BufWriter::new(File::create(&out).unwrap());
+
+    // Same layout as serializing a `Vec<Object>`: The length, then each element.
+    // The length is filled in at the end since deleted records are left out.
+    bincode::serialize_into(&mut writer, &0u64).unwrap();
+
+    let mut num_records = 0usize;
+    let mut num_objects = 0u64;
+    let (mut num_issues, mut num_repaired, mut num_dropped) = (0, 0, 0);
+    let mut num_unrepaired = 0;
+    let (mut num_null, mut num_unsupported) = (0, 0);
+
+    // Full detail first, then each level of detail
+    let mut num_points = vec![0; simplify.len() + 1];
+    for pair in RecordPairs::new(shp_records, dbf_records) {
+        let (shp, dbf) = pair.unwrap();
+        num_records += 1;
+
+        // A deleted row means the shape is deleted too
+        if dbf.deleted {
+            continue;
+        }
+
+        let mut shape = shp.shape;
+        if repair {
+            let issues = shape.validate(num_records as i32);
+            if !issues.is_empty() {
+                for issue in issues
+                    .iter()
+                    .take(PRINTED_ISSUES.saturating_sub(num_issues))
+                {
+                    println!("{issue}");
+                }
+                num_issues += issues.len();
+
+                if shape.can_repair() {
+                    num_repaired += 1;
+                    shape.repair();
+                    if matches!(shape, Shape::Null) {
+                        num_dropped += 1;
+                        continue;
+                    }
+                } else {
+                    num_unrepaired += 1;
+                }
+            }
+        }
+
+        if matches!(shape, Shape::Null) {
+            num_null += 1;
+            continue;
+        }
+
+        if let Some(transform) = &transform {
+            transform.shape(&mut shape);
+        }
+
+        // Multipoints are shown as one object per point
+        for shape in split_multipoints(shape) {
+            let lods: Result<Vec<Variant>, _> = match shape {
+                Shape::PolyLine(_)
+                | Shape::PolylineZ(_)
+                | Shape::Polygon(_)
+                | Shape::PolygonZ(_) => simplify
+                    .iter()
+                    .map(|tolerance| shape.simplify(simplify_algorithm, *tolerance).try_into())
+                    .collect(),
+                _ => Ok(vec![]),
+            };
+
+            let object = lods.and_then(|lods| {
+                let object = Object::try_from(spatial::Object {
+                    shape,
+                    fclass: FromStr::from_str(dbf.entries[fclass_idx].as_str().unwrap_or_default())
+                        .expect("expected known fclass"),
+                    name: dbf.entries[name_idx].to_string(),
+                })?;
+                Ok(Object { lods, ..object })
+            });
+            let object = match object {
+                Ok(object) => object,
+                Err(e) => {
+                    if num_unsupported < PRINTED_ISSUES {
+                        println!("skipping record {num_records}: {e}");
+                    }
+                    num_unsupported += 1;
+                    continue;
+                }
+            };
+
+            // Objects without levels of detail are shown as they are
+            num_points[0] += object.variant.num_points();
+            for (level, count) in num_points[1..].iter_mut().enumerate() {
+                *count += object
+                    .lods
+                    .get(level)
+                    .unwrap_or(&object.variant)
+                    .num_points();
+            }
+
+            bincode::serialize_into(&mut writer, &object).unwrap();
+            num_objects += 1;
+        }
+    }
+
+    for warning in dbf_parser.warnings() {
+        println!("{warning}");
+    }
+
+    if repair {
+        println!(
+            "found {num_issues} problems, repaired {num_repaired} shapes and dropped {num_dropped} with nothing left"
+        );
+        if num_unrepaired > 0 {
+            println!(
+                "{num_unrepaired} shapes with Z or M values have problems which are not repaired"
+            );
+        }
+    }
+
+    if num_null > 0 {
+        println!("skipped {num_null} null shapes");
+    }
+    if num_unsupported > 0 {
+        println!("skipped {num_unsupported} shapes which can't be shown");
+    }
+
+    for (tolerance, count) in simplify.iter().zip(&num_points[1..]) {
+        println!(
+            "simplified with tolerance {tolerance}: {count} of {} points",
+            num_points[0]
+        );
+    }
+
+    writer.seek(SeekFrom::Start(0)).unwrap();
+    bincode::serialize_into(&mut writer, &num_objects).unwrap();
+    writer.flush().unwrap();
 
     println!(
-        "preprocessed objects ok (total: {:.2}s), writing to output {out:?}",
+        "wrote {num_objects} objects (total: {:.2}s)",
         start.elapsed().as_secs_f32()
     );
-
-    let bytes = bincode::serialize(&objects).unwrap();
-    std::fs::write(out, bytes).unwrap();
 }
@@ -43,10 +43,22 @@ fn main() {
         .iter()
         .filter(|o| matches!(o.variant, Variant::Polygon(_)))
         .count();
+    let meshes = objects
+        .iter()
+        .filter(|o| matches!(o.variant, Variant::Mesh(_)))
+        .count();
 
     println!("num objects: {num}");
     println!("named objects: {with_name}/{num}");
     println!("lines: {lines}/{num}");
     println!("points: {points}/{num}");
     println!("polygons: {polygons}/{num}");
+    println!("meshes: {meshes}/{num}");
+
+    let levels = objects
+        .iter()
+        .map(|o| o.lods.len())
+        .max()
+        .unwrap_or_default();
+    println!("levels of detail: {levels}");
 }
@@ -1,6 +1,6 @@
 //! ECS versions of geodata
 
-use bevy::{math::DVec2, prelude::*};
+use bevy::{math::DVec3, prelude::*};
 use serde::{Deserialize, Serialize};
 use shpank::spatial::Fclass;
 
@@ -10,15 +10,34 @@ pub struct GeoName(pub String);
 #[derive(Debug, Component, Deref, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct GeoFeature(pub Fclass);
 
+/// Shapes without Z values have Z set to zero.
 #[derive(Debug, Component, Deref, Serialize, Deserialize, Clone, Copy)]
-pub struct GeoPoint(pub DVec2);
+pub struct GeoPoint(pub DVec3);
 
+/// One line per part, as for multi-part lines.
 #[derive(Debug, Component, Deref, Serialize, Deserialize, Clone)]
 pub struct GeoLines {
-    // TODO: Likely actually vec of vec
-    pub line: Vec<DVec2>,
+    pub lines: Vec<Vec<DVec3>>,
 }
+
+/// An outer ring with the holes inside it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GeoRings {
+    pub exterior: Vec<DVec3>,
+    pub interiors: Vec<Vec<DVec3>>,
+}
+
+/// One or more polygons, as for multipolygons.
 #[derive(Debug, Component, Deref, Serialize, Deserialize, Clone)]
 pub struct GeoPolygon {
-    pub polygon: Vec<Vec<DVec2>>,
+    pub polygons: Vec<GeoRings>,
+}
+
+/// A triangle list, as for buildings.
+#[derive(Debug, Component, Serialize, Deserialize, Clone)]
+pub struct GeoMesh {
+    pub positions: Vec<DVec3>,
+
+    /// Three indices into `positions` per triangle
+    pub indices: Vec<u32>,
 }
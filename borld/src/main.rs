@@ -56,6 +56,7 @@ fn load_all(mut commands: Commands, plugin: Res<LoadObjectsPlugin>) {
             name,
             feature,
             variant,
+            ..
         } in object_collection
         {
             let mut cmds = commands.spawn((feature, ObjectSourceFileIndex(index)));
@@ -64,6 +65,7 @@ fn load_all(mut commands: Commands, plugin: Res<LoadObjectsPlugin>) {
                 Variant::Point(p) => cmds.insert(p),
                 Variant::Line(l) => cmds.insert(l),
                 Variant::Polygon(p) => cmds.insert(p),
+                Variant::Mesh(m) => cmds.insert(m),
             };
 
             if let Some(name) = name {
@@ -1,6 +1,11 @@
-use bevy::math::DVec2;
+use std::{fmt, ops::Range};
+
+use bevy::math::DVec3;
 use serde::{Deserialize, Serialize};
-use shpank::shape::Shape;
+use shpank::{
+    rings::PolygonRings,
+    shape::{part_ranges, Point, PointZ, Shape, ShapeType},
+};
 
 use crate::ecs_geo::*;
 
@@ -9,6 +14,10 @@ pub struct Object {
     pub name: Option<GeoName>,
     pub feature: GeoFeature,
     pub variant: Variant,
+
+    /// Simplified versions of the variant, each coarser than the one before.
+    /// Empty unless preprocessed with `--simplify`.
+    pub lods: Vec<Variant>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -16,66 +25,151 @@ pub enum Variant {
     Point(GeoPoint),
     Line(GeoLines),
     Polygon(GeoPolygon),
+    Mesh(GeoMesh),
+}
+
+impl Variant {
+    pub fn num_points(&self) -> usize {
+        match self {
+            Variant::Point(_) => 1,
+            Variant::Line(line) => line.lines.iter().map(Vec::len).sum(),
+            Variant::Polygon(polygon) => polygon
+                .polygons
+                .iter()
+                .map(|rings| {
+                    rings.exterior.len() + rings.interiors.iter().map(Vec::len).sum::<usize>()
+                })
+                .sum(),
+            Variant::Mesh(mesh) => mesh.positions.len(),
+        }
+    }
+}
+
+/// 2D points are placed at Z = 0.
+fn flat(points: &[Point]) -> Vec<DVec3> {
+    points.iter().map(|p| DVec3::new(p.x, p.y, 0.)).collect()
+}
+
+fn elevated(points: impl Iterator<Item = [f64; 3]>) -> Vec<DVec3> {
+    points.map(DVec3::from_array).collect()
+}
+
+fn line(parts: &[i32], points: Vec<DVec3>) -> GeoLines {
+    GeoLines {
+        lines: part_ranges(parts, points.len())
+            .map(|range| points[range].to_vec())
+            .collect(),
+    }
+}
+
+fn polygon(rings: Vec<PolygonRings>, points: Vec<DVec3>) -> GeoPolygon {
+    let ring = |range: Range<usize>| points[range].to_vec();
+
+    GeoPolygon {
+        polygons: rings
+            .into_iter()
+            .map(|rings| GeoRings {
+                exterior: ring(rings.exterior),
+                interiors: rings.interiors.into_iter().map(ring).collect(),
+            })
+            .collect(),
+    }
+}
+
+/// Multipoints as one point per shape, since a [`Variant::Point`] is a single point.
+/// Measures are dropped, as for the other M shapes.
+/// Other shapes are kept as they are.
+pub fn split_multipoints(shape: Shape) -> Vec<Shape> {
+    match shape {
+        Shape::MultiPoint(multi) => multi.points.into_iter().map(Shape::Point).collect(),
+        Shape::MultiPointM(multi) => multi.points.into_iter().map(Shape::Point).collect(),
+        Shape::MultiPointZ(multi) => {
+            let m = |index: usize| multi.m.as_ref().and_then(|m| m.values[index]);
+            multi
+                .points_3d()
+                .enumerate()
+                .map(|(index, [x, y, z])| {
+                    Shape::PointZ(PointZ {
+                        x,
+                        y,
+                        z,
+                        m: m(index),
+                    })
+                })
+                .collect()
+        }
+        other => vec![other],
+    }
+}
+
+/// A shape which can't be shown as a [`Variant`].
+#[derive(Debug, Clone, Copy)]
+pub struct Unsupported(pub ShapeType);
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            ShapeType::Null => write!(f, "null shapes have nothing to show"),
+            ShapeType::MultiPoint | ShapeType::MultiPointZ | ShapeType::MultiPointM => {
+                write!(
+                    f,
+                    "{:?} shapes are shown one point at a time, see `split_multipoints`",
+                    self.0
+                )
+            }
+            shape_type => write!(f, "{shape_type:?} shapes can't be shown"),
+        }
+    }
 }
 
-impl From<shpank::spatial::Object> for Object {
-    fn from(object: shpank::spatial::Object) -> Self {
-        Self {
+impl std::error::Error for Unsupported {}
+
+impl TryFrom<Shape> for Variant {
+    type Error = Unsupported;
+
+    fn try_from(shape: Shape) -> Result<Self, Self::Error> {
+        Ok(match shape {
+            Shape::Point(point) => Variant::Point(GeoPoint(DVec3::new(point.x, point.y, 0.))),
+            Shape::PointZ(point) => Variant::Point(GeoPoint(DVec3::new(point.x, point.y, point.z))),
+            // Measures have nothing to do with position, so M shapes are shown as 2D shapes
+            Shape::PointM(point) => Variant::Point(GeoPoint(DVec3::new(point.x, point.y, 0.))),
+            Shape::PolylineM(polyline) => {
+                Variant::Line(line(&polyline.parts, flat(&polyline.points)))
+            }
+            Shape::PolyLine(polyline) => {
+                Variant::Line(line(&polyline.parts, flat(&polyline.points)))
+            }
+            Shape::PolylineZ(polyline) => {
+                Variant::Line(line(&polyline.parts, elevated(polyline.points_3d())))
+            }
+            Shape::Polygon(p) => Variant::Polygon(polygon(p.polygons(), flat(&p.points))),
+            Shape::PolygonZ(p) => Variant::Polygon(polygon(p.polygons(), elevated(p.points_3d()))),
+            Shape::PolygonM(p) => Variant::Polygon(polygon(p.polygons(), flat(&p.points))),
+            Shape::MultiPatch(patch) => {
+                let mesh = patch.triangulate();
+                Variant::Mesh(GeoMesh {
+                    positions: elevated(mesh.positions.into_iter()),
+                    indices: mesh.indices,
+                })
+            }
+            others => return Err(Unsupported(others.shape_type())),
+        })
+    }
+}
+
+impl TryFrom<shpank::spatial::Object> for Object {
+    type Error = Unsupported;
+
+    fn try_from(object: shpank::spatial::Object) -> Result<Self, Self::Error> {
+        Ok(Self {
             name: if object.name.is_empty() {
                 None
             } else {
                 Some(GeoName(object.name))
             },
             feature: GeoFeature(object.fclass),
-            variant: match object.shape {
-                Shape::Point(point) => Variant::Point(GeoPoint(DVec2::new(point.x, point.y))),
-                Shape::PolyLine(polyline) => {
-                    if polyline.parts.len() > 1 {
-                        unimplemented!()
-                    }
-
-                    Variant::Line({
-                        let points = polyline
-                            .points
-                            .into_iter()
-                            .map(|p| DVec2::new(p.x, p.y))
-                            .collect();
-                        GeoLines { line: points }
-                    })
-                }
-                Shape::Polygon(polygon) => {
-                    let total_points = polygon.points.len();
-                    let mut points_added = 0;
-                    let mut results = vec![];
-
-                    for window in polygon.parts.windows(2) {
-                        let [start, stop] = TryInto::<[_; 2]>::try_into(window).unwrap();
-                        let [start, stop] = [start as usize, stop as usize];
-
-                        let part: Vec<DVec2> = polygon.points[start..stop]
-                            .iter()
-                            .copied()
-                            .map(|p| DVec2::new(p.x, p.y))
-                            .collect();
-                        points_added += part.len();
-                        results.push(part);
-                    }
-
-                    let last = *polygon.parts.last().unwrap() as usize;
-
-                    let part: Vec<DVec2> = polygon.points[last..]
-                        .iter()
-                        .copied()
-                        .map(|p| DVec2::new(p.x, p.y))
-                        .collect();
-                    points_added += part.len();
-                    results.push(part);
-
-                    assert_eq!(total_points, points_added);
-                    Variant::Polygon(GeoPolygon { polygon: results })
-                }
-                others => unimplemented!("missing impl: {others:?}"),
-            },
-        }
+            variant: object.shape.try_into()?,
+            lods: vec![],
+        })
     }
 }
@@ -0,0 +1,163 @@
+use std::{fs::File, io::BufWriter, path::PathBuf, time::Instant};
+
+use argh::FromArgs;
+use shpank::{
+    clip::Region,
+    memo::MemoFile,
+    parse::{Error, Parser},
+    shape::{MinimumBoundingRectangle, Polygon, Shape},
+    spatial::RecordPairs,
+    write::{DbaseWriter, ShpWriter},
+};
+
+#[derive(Debug, FromArgs)]
+/// Clip a .shp- and .dbf file pair to a rectangle or to polygons,
+/// writing the shapes which are inside along with their rows.
+struct Args {
+    /// path to input Shapefile
+    #[argh(positional)]
+    shp: PathBuf,
+
+    /// path to input dBASE file
+    #[argh(positional)]
+    dbf: PathBuf,
+
+    /// output path without extension, e.g. out/oslo_roads.
+    /// The .shp, .shx and .dbf files are written there, and the .prj, .cpg and memo file copied
+    /// if there are any
+    #[argh(option)]
+    out: PathBuf,
+
+    /// rectangle to clip to, "min_x,min_y,max_x,max_y" in the coordinates of the input
+    #[argh(option, from_str_fn(parse_bbox))]
+    bbox: Option<MinimumBoundingRectangle>,
+
+    /// shapefile of polygons to clip to, e.g. the border of a city, in the coordinates of the input
+    #[argh(option)]
+    polygons: Option<PathBuf>,
+}
+
+fn parse_bbox(s: &str) -> Result<MinimumBoundingRectangle, String> {
+    let values = s
+        .split(',')
+        .map(|value| value.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("bad bounding box `{s}`: {e}"))?;
+
+    match values[..] {
+        [min_x, min_y, max_x, max_y] if min_x <= max_x && min_y <= max_y => {
+            Ok(MinimumBoundingRectangle {
+                x: min_x..max_x,
+                y: min_y..max_y,
+            })
+        }
+        _ => Err(format!(
+            "bad bounding box `{s}`, expected \"min_x,min_y,max_x,max_y\""
+        )),
+    }
+}
+
+/// All the rings of all the polygons in the file, as one region.
+fn polygons_region(path: &PathBuf) -> Region {
+    let shp_file = Parser::parse_shp_file(path).unwrap();
+
+    let mut parts = vec![];
+    let mut points = vec![];
+    for record in shp_file.records {
+        match record.shape {
+            Shape::Polygon(polygon) => {
+                parts.extend(polygon.parts.iter().map(|part| part + points.len() as i32));
+                points.extend(polygon.points);
+            }
+            Shape::Null => {}
+            other => panic!(
+                "expected polygons in {path:?}, found {:?}",
+                other.shape_type()
+            ),
+        }
+    }
+
+    Region::Polygon(Polygon {
+        mbr: MinimumBoundingRectangle::from_points(&points)
+            .unwrap_or_else(|| panic!("no polygons in {path:?}")),
+        parts,
+        points,
+    })
+}
+
+fn main() {
+    let Args {
+        shp,
+        dbf,
+        out,
+        bbox,
+        polygons,
+    } = argh::from_env();
+
+    let region = match (bbox, polygons) {
+        (Some(bbox), None) => Region::Rectangle(bbox),
+        (None, Some(polygons)) => polygons_region(&polygons),
+        _ => panic!("give either --bbox or --polygons"),
+    };
+
+    let start = Instant::now();
+    println!("Clipping {shp:?} and {dbf:?} to {out:?}");
+
+    let mut shp_parser = Parser::new(&shp).unwrap();
+    let mut dbf_parser = Parser::new_dbf(&dbf).unwrap();
+
+    let shp_records = shp_parser.shp_records().unwrap();
+    let shape_type = shp_records.header().shape_type;
+    // Rather than failing at the first shape, after the output files are created
+    assert!(
+        shape_type.can_clip(),
+        "{}, only points, multipoints, lines and polygons without Z or M values",
+        Error::UnsupportedClip(shape_type)
+    );
+
+    let dbf_header = dbf_parser.parse_dbase_header().unwrap();
+    let dbf_records = dbf_parser.dbf_records(&dbf_header);
+
+    let mut shp_writer = ShpWriter::create(out.with_extension("shp"), shape_type).unwrap();
+    let dbf_file = BufWriter::new(File::create(out.with_extension("dbf")).unwrap());
+    let mut dbf_writer = DbaseWriter::with_header(dbf_file, &dbf_header).unwrap();
+
+    let (mut num_records, mut num_kept) = (0, 0);
+    for pair in RecordPairs::new(shp_records, dbf_records) {
+        let (shp, dbf) = pair.unwrap();
+        num_records += 1;
+
+        // A deleted row means the shape is deleted too
+        if dbf.deleted {
+            continue;
+        }
+
+        if let Some(shape) = shp.shape.clip(&region).unwrap() {
+            shp_writer.write_shape(&shape).unwrap();
+            dbf_writer.write_record(&dbf).unwrap();
+            num_kept += 1;
+        }
+    }
+
+    shp_writer.finish().unwrap();
+    dbf_writer.finish().unwrap();
+
+    // So the output is read with the same coordinate system and text encoding
+    for (input, extension) in [(&shp, "prj"), (&dbf, "cpg")] {
+        let from = input.with_extension(extension);
+        if from.is_file() {
+            std::fs::copy(&from, out.with_extension(extension)).unwrap();
+        }
+    }
+
+    // The rows kept refer to their memos by block, so the memo file is copied whole
+    if let Some(memo) = MemoFile::find_next_to(&dbf) {
+        let extension = memo.extension().unwrap_or_default();
+        std::fs::copy(&memo, out.with_extension(extension)).unwrap();
+    }
+
+    println!(
+        "kept {num_kept} of {num_records} records (total: {:.2}s)",
+        start.elapsed().as_secs_f32()
+    );
+}
@@ -1,18 +1,21 @@
-use std::path::PathBuf;
+use std::{fs::File, io::BufReader, path::PathBuf};
 
 use argh::FromArgs;
-use shpank::spatial::Spatial;
+use shpank::{dataset::Dataset, spatial::Spatial};
+use zip::ZipArchive;
 
 #[derive(Debug, FromArgs)]
-/// Parse a .shp- and .dbf file pair then convert to objects.
+/// Open a Shapefile dataset then convert to objects.
 struct Args {
-    /// path to input Shapefile
+    /// path to the dataset without extension, e.g. data/gis_osm_roads_free_1,
+    /// or to a .zip archive containing it
     #[argh(positional)]
-    shp: PathBuf,
+    dataset: PathBuf,
 
-    /// path to input dBASE file
-    #[argh(positional)]
-    dbf: PathBuf,
+    /// name of the dataset inside the .zip archive, e.g. gis_osm_roads_free_1.
+    /// Not needed if there is only one
+    #[argh(option)]
+    layer: Option<String>,
 
     /// only keep objects which have a non-empty name
     #[argh(switch)]
@@ -20,13 +23,43 @@ struct Args {
 }
 
 fn main() {
-    let Args { shp, dbf, named } = argh::from_env();
+    let Args {
+        dataset,
+        layer,
+        named,
+    } = argh::from_env();
+
+    println!("Creating objects from {dataset:?}");
+    let is_zip = dataset
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"));
+
+    let spatial = match (layer, is_zip) {
+        (Some(layer), _) => Spatial::open_zip(&dataset, &layer),
+        (None, true) => {
+            let archive = ZipArchive::new(BufReader::new(File::open(&dataset).unwrap())).unwrap();
+            match &Dataset::names_in_zip(&archive)[..] {
+                [layer] => Spatial::open_zip(&dataset, layer),
+                layers => panic!(
+                    "expected one dataset in {dataset:?}, pick one of {layers:?} with --layer"
+                ),
+            }
+        }
+        (None, false) => Spatial::open(&dataset),
+    }
+    .unwrap();
+
+    for warning in &spatial.warnings {
+        println!("Warning: {warning}");
+    }
+    if let Some(crs) = &spatial.crs {
+        println!("Coordinate reference system: {}", crs.name);
+    }
 
-    println!("Creating objects from {shp:?} and {dbf:?}");
     let spatial = if named {
-        Spatial::new(&shp, &dbf).unwrap().into_named_objects()
+        spatial.into_named_objects()
     } else {
-        Spatial::new(&shp, &dbf).unwrap().into_objects()
+        spatial.into_objects()
     };
 
     println!("Spatial files parse OK- {} records", spatial.len());
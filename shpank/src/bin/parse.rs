@@ -24,7 +24,7 @@ impl FromStr for Mode {
 }
 
 #[derive(Debug, FromArgs)]
-/// Parse an input .shp or .dbf file then exit.
+/// Parse an input .shp, .shx or .dbf file then exit.
 /// Can be used for speedtesting.
 struct Args {
     /// path to input file
@@ -34,28 +34,43 @@ struct Args {
     /// which mode to parse in: [file|string]
     #[argh(option)]
     mode: Mode,
+
+    /// parse a .shp file on this many threads, using the .shx file next to it.
+    /// The file is memory mapped regardless of mode.
+    #[argh(option)]
+    threads: Option<usize>,
 }
 
 fn main() {
-    let Args { file, mode } = argh::from_env();
+    let Args {
+        file,
+        mode,
+        threads,
+    } = argh::from_env();
 
     enum Ext {
         Shp,
+        Shx,
         Dbf,
     }
 
     let ext = file.extension().unwrap().to_string_lossy().to_string();
     let ext = match ext.as_str() {
         "shp" => Ext::Shp,
+        "shx" => Ext::Shx,
         "dbf" => Ext::Dbf,
-        _ => panic!("expected a .shp or .dbf file"),
+        _ => panic!("expected a .shp, .shx or .dbf file"),
     };
 
     match ext {
         Ext::Shp => {
-            let f = match mode {
-                Mode::File => Parser::parse_shp_file(file).unwrap(),
-                Mode::Bytes => {
+            let f = match (threads, mode) {
+                (Some(threads), _) => {
+                    let shx = file.with_extension("shx");
+                    Parser::parse_shp_file_parallel(&file, &shx, threads).unwrap()
+                }
+                (None, Mode::File) => Parser::parse_shp_file(file).unwrap(),
+                (None, Mode::Bytes) => {
                     let bytes = std::fs::read(file).unwrap();
                     Parser::parse_shp_buffer(&bytes).unwrap()
                 }
@@ -63,6 +78,17 @@ fn main() {
 
             println!(".shp parse OK- {} records", f.records.len());
         }
+        Ext::Shx => {
+            let f = match mode {
+                Mode::File => Parser::parse_shx_file(file).unwrap(),
+                Mode::Bytes => {
+                    let bytes = std::fs::read(file).unwrap();
+                    Parser::parse_shx_buffer(&bytes).unwrap()
+                }
+            };
+
+            println!(".shx parse OK- {} records", f.records.len());
+        }
         Ext::Dbf => {
             let f = match mode {
                 Mode::File => Parser::parse_dbf_file(file).unwrap(),
@@ -1,5 +1,5 @@
 use argh::FromArgs;
-use shpank::parse::Parser;
+use shpank::{dbase::DeletedRecords, parse::Parser};
 use std::{collections::BTreeSet, path::PathBuf};
 
 #[derive(Debug, FromArgs)]
@@ -18,7 +18,7 @@ struct Args {
 fn main() {
     let Args { file, field } = argh::from_env();
 
-    let dbf = Parser::parse_dbf_file(&file).unwrap();
+    let dbf = Parser::parse_dbf_file_with(&file, DeletedRecords::Skip).unwrap();
     let idx = dbf
         .header
         .index_of(&field)
@@ -29,10 +29,9 @@ fn main() {
     let field_values = dbf
         .records
         .iter()
-        .map(|entry| &entry.entries[idx])
+        .map(|entry| entry.entries[idx].to_string())
         .collect::<BTreeSet<_>>()
         .into_iter()
-        .cloned()
         .collect::<Vec<_>>();
 
     let output = file.with_extension(format!("{field}.txt"));
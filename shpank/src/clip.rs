@@ -0,0 +1,736 @@
+//! Clipping shapes to a rectangle or a polygon, e.g. to cut a city out of a whole country.
+//!
+//! Lines are clipped with Liang-Barsky against rectangles, and split where they cross the rings
+//! of polygons.
+//! Polygons are clipped with Sutherland-Hodgman against rectangles, and with Greiner-Hormann
+//! against polygons, which handles concave rings and holes on both sides.
+//! Clipped rings are oriented by whether they are holes, see [`crate::rings`].
+
+use std::collections::HashMap;
+
+use crate::{
+    parse::{Error, Result},
+    rings::{ring_contains, signed_area, Orientation},
+    shape::{
+        part_ranges, MinimumBoundingRectangle, MultiPoint, Point, PolyLine, Polygon, Shape,
+        ShapeType,
+    },
+    validate::{cross, join, open_ring},
+};
+
+/// What to clip to.
+#[derive(Debug, Clone)]
+pub enum Region {
+    /// Points on the edges are inside.
+    Rectangle(MinimumBoundingRectangle),
+
+    /// Inside by the even-odd rule, so holes are not part of the region.
+    /// Several polygons can be clipped to at once by putting all their rings in one,
+    /// as long as they do not overlap.
+    Polygon(Polygon),
+}
+
+impl Region {
+    pub fn contains(&self, point: Point) -> bool {
+        match self {
+            Region::Rectangle(mbr) => rectangle_contains(mbr, point),
+            Region::Polygon(polygon) => {
+                polygon
+                    .rings()
+                    .filter(|ring| ring_contains(ring, point))
+                    .count()
+                    % 2
+                    == 1
+            }
+        }
+    }
+
+    fn mbr(&self) -> &MinimumBoundingRectangle {
+        match self {
+            Region::Rectangle(mbr) => mbr,
+            Region::Polygon(polygon) => &polygon.mbr,
+        }
+    }
+}
+
+impl ShapeType {
+    /// Whether shapes of this type can be clipped, see [`Shape::clip`].
+    /// Check this before clipping a file, since every shape of another type fails.
+    pub fn can_clip(&self) -> bool {
+        matches!(
+            self,
+            ShapeType::Null
+                | ShapeType::Point
+                | ShapeType::PointZ
+                | ShapeType::PointM
+                | ShapeType::MultiPoint
+                | ShapeType::PolyLine
+                | ShapeType::Polygon
+        )
+    }
+}
+
+impl Shape {
+    /// The part of the shape inside the region, `None` if none of it is.
+    ///
+    /// Points and multipoints keep the points inside, lines are cut where they leave the region,
+    /// and polygons become the area they share with it.
+    /// Multipoints, lines and polygons with Z or M values, and multipatches, can't be clipped yet,
+    /// see [`ShapeType::can_clip`].
+    pub fn clip(&self, region: &Region) -> Result<Option<Shape>> {
+        let mbr = match self {
+            Shape::Null => return Ok(None),
+            Shape::Point(point) => return Ok(region.contains(*point).then_some(self.clone())),
+            Shape::PointZ(point) => {
+                let point = Point {
+                    x: point.x,
+                    y: point.y,
+                };
+                return Ok(region.contains(point).then_some(self.clone()));
+            }
+            Shape::PointM(point) => {
+                let point = Point {
+                    x: point.x,
+                    y: point.y,
+                };
+                return Ok(region.contains(point).then_some(self.clone()));
+            }
+            Shape::MultiPoint(multi) => &multi.mbr,
+            Shape::PolyLine(line) => &line.mbr,
+            Shape::Polygon(polygon) => &polygon.mbr,
+            other => return Err(Error::UnsupportedClip(other.shape_type())),
+        };
+
+        if !overlaps(mbr, region.mbr()) {
+            return Ok(None);
+        }
+        if let Region::Rectangle(rectangle) = region {
+            if rectangle_contains_all(rectangle, mbr) {
+                return Ok(Some(self.clone()));
+            }
+        }
+
+        Ok(match self {
+            Shape::MultiPoint(multi) => {
+                let points: Vec<Point> = multi
+                    .points
+                    .iter()
+                    .copied()
+                    .filter(|point| region.contains(*point))
+                    .collect();
+                MinimumBoundingRectangle::from_points(&points)
+                    .map(|mbr| Shape::MultiPoint(MultiPoint { mbr, points }))
+            }
+            Shape::PolyLine(line) => line.clip(region).map(Shape::PolyLine),
+            Shape::Polygon(polygon) => polygon.clip(region)?.map(Shape::Polygon),
+            _ => unreachable!("handled above"),
+        })
+    }
+}
+
+impl PolyLine {
+    /// The pieces of the lines inside the region, `None` if there are none.
+    pub fn clip(&self, region: &Region) -> Option<PolyLine> {
+        let rings = region_rings(region);
+        let pieces = |a: Point, b: Point| match region {
+            Region::Rectangle(mbr) => liang_barsky(a, b, mbr).into_iter().collect(),
+            Region::Polygon(_) => polygon_pieces(a, b, &rings),
+        };
+
+        let mut lines: Vec<Vec<Point>> = vec![];
+        for range in part_ranges(&self.parts, self.points.len()) {
+            // Whether the last line ends where the current segment starts
+            let mut joined = false;
+
+            for segment in self.points[range].windows(2) {
+                let (a, b) = (segment[0], segment[1]);
+                let inside: Vec<(f64, f64)> = pieces(a, b);
+
+                for (start, end) in &inside {
+                    match lines.last_mut() {
+                        Some(line) if joined && *start == 0. => line.push(along(a, b, *end)),
+                        _ => lines.push(vec![along(a, b, *start), along(a, b, *end)]),
+                    }
+                    joined = *end == 1.;
+                }
+                if inside.is_empty() {
+                    joined = false;
+                }
+            }
+        }
+
+        let (parts, points) = join(lines);
+        Some(PolyLine {
+            mbr: MinimumBoundingRectangle::from_points(&points)?,
+            parts,
+            points,
+        })
+    }
+}
+
+impl Polygon {
+    /// The area shared with the region, `None` if there is none.
+    ///
+    /// When clipping to a polygon whose rings touch or run along those of this polygon,
+    /// the region is moved by a tiny amount until they cross clearly.
+    /// The points of the region are put back where they were, but where the rings cross
+    /// may then be off by up to 7e-7 times the size of the polygon and the region together.
+    /// Fails if moving the region does not help.
+    pub fn clip(&self, region: &Region) -> Result<Option<Polygon>> {
+        let rings: Vec<Vec<Point>> = self
+            .rings()
+            .map(open_ring)
+            .filter(|ring| ring.len() >= 3)
+            .collect();
+
+        let clipped = match region {
+            Region::Rectangle(mbr) => rings
+                .iter()
+                .map(|ring| sutherland_hodgman(ring, mbr))
+                .collect(),
+            Region::Polygon(polygon) => {
+                let others = region_rings(region);
+                let mbr = self.mbr.union(&polygon.mbr);
+                let extent = (mbr.x.end - mbr.x.start).max(mbr.y.end - mbr.y.start);
+
+                // Rings touching at a vertex or running along each other have no clear
+                // crossings, but moving the region by a tiny amount makes them cross or miss
+                (0..ATTEMPTS)
+                    .find_map(|attempt| {
+                        let angle = attempt as f64;
+                        let shift = extent * NUDGE * attempt as f64;
+                        let (dx, dy) = (shift * angle.cos(), shift * angle.sin());
+                        let nudged: Vec<Vec<Point>> = others
+                            .iter()
+                            .map(|ring| {
+                                ring.iter()
+                                    .map(|p| Point {
+                                        x: p.x + dx,
+                                        y: p.y + dy,
+                                    })
+                                    .collect()
+                            })
+                            .collect();
+
+                        let clipped = greiner_hormann(&rings, &nudged)?;
+                        Some(unnudge(clipped, &others, &nudged))
+                    })
+                    .ok_or_else(|| {
+                        Error::UnexpectedData(
+                            "Can't clip polygon, its rings run along those of the region"
+                                .to_string(),
+                        )
+                    })?
+            }
+        };
+
+        Ok(assemble(clipped))
+    }
+}
+
+/// The clipped rings with the points of the moved region put back, see [`Polygon::clip`].
+fn unnudge(
+    clipped: Vec<Vec<Point>>,
+    others: &[Vec<Point>],
+    nudged: &[Vec<Point>],
+) -> Vec<Vec<Point>> {
+    let key = |p: &Point| (p.x.to_bits(), p.y.to_bits());
+    let moved: HashMap<_, _> = nudged
+        .iter()
+        .flatten()
+        .zip(others.iter().flatten())
+        .map(|(nudged, original)| (key(nudged), *original))
+        .collect();
+
+    clipped
+        .into_iter()
+        .map(|ring| {
+            ring.into_iter()
+                .map(|p| moved.get(&key(&p)).copied().unwrap_or(p))
+                .collect()
+        })
+        .collect()
+}
+
+/// How many times the region is moved when clipping polygons to polygons, see [`Polygon::clip`].
+const ATTEMPTS: usize = 8;
+
+/// How far the region is moved each time, relative to the size of the shapes.
+const NUDGE: f64 = 1e-7;
+
+/// Parameters along an edge closer than this to its ends count as touching them.
+const TOUCHING: f64 = 1e-9;
+
+fn rectangle_contains(mbr: &MinimumBoundingRectangle, point: Point) -> bool {
+    (mbr.x.start..=mbr.x.end).contains(&point.x) && (mbr.y.start..=mbr.y.end).contains(&point.y)
+}
+
+/// Whether `inner` is inside `outer`.
+fn rectangle_contains_all(
+    outer: &MinimumBoundingRectangle,
+    inner: &MinimumBoundingRectangle,
+) -> bool {
+    outer.x.start <= inner.x.start
+        && inner.x.end <= outer.x.end
+        && outer.y.start <= inner.y.start
+        && inner.y.end <= outer.y.end
+}
+
+fn overlaps(a: &MinimumBoundingRectangle, b: &MinimumBoundingRectangle) -> bool {
+    a.x.start <= b.x.end && b.x.start <= a.x.end && a.y.start <= b.y.end && b.y.start <= a.y.end
+}
+
+/// The open rings of a polygon region, none for a rectangle.
+fn region_rings(region: &Region) -> Vec<Vec<Point>> {
+    match region {
+        Region::Rectangle(_) => vec![],
+        Region::Polygon(polygon) => polygon
+            .rings()
+            .map(open_ring)
+            .filter(|ring| ring.len() >= 3)
+            .collect(),
+    }
+}
+
+/// Whether the point is inside the rings by the even-odd rule.
+fn even_odd(rings: &[Vec<Point>], point: Point) -> bool {
+    rings
+        .iter()
+        .filter(|ring| ring_contains(ring, point))
+        .count()
+        % 2
+        == 1
+}
+
+/// The point a fraction `t` of the way from `a` to `b`, exactly `a` or `b` at the ends.
+fn along(a: Point, b: Point, t: f64) -> Point {
+    match t {
+        0. => a,
+        1. => b,
+        t => Point {
+            x: a.x + t * (b.x - a.x),
+            y: a.y + t * (b.y - a.y),
+        },
+    }
+}
+
+/// The part of the segment inside the rectangle, as fractions of the way along it.
+fn liang_barsky(a: Point, b: Point, mbr: &MinimumBoundingRectangle) -> Option<(f64, f64)> {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let (mut start, mut end) = (0f64, 1f64);
+
+    for (p, q) in [
+        (-dx, a.x - mbr.x.start),
+        (dx, mbr.x.end - a.x),
+        (-dy, a.y - mbr.y.start),
+        (dy, mbr.y.end - a.y),
+    ] {
+        if p == 0. {
+            // Parallel to the edge, and outside it
+            if q < 0. {
+                return None;
+            }
+        } else if p < 0. {
+            start = start.max(q / p);
+        } else {
+            end = end.min(q / p);
+        }
+    }
+
+    // Only touching a corner is not a piece
+    (start < end || (start == 0. && end == 1.)).then_some((start, end))
+}
+
+/// Where along the segment `a`-`b` it meets the segment `c`-`d`, if it does at a single point.
+fn meets(a: Point, b: Point, c: Point, d: Point) -> Option<f64> {
+    let (r, s) = ((b.x - a.x, b.y - a.y), (d.x - c.x, d.y - c.y));
+    let denominator = r.0 * s.1 - r.1 * s.0;
+    if denominator == 0. {
+        return None;
+    }
+
+    let (ca_x, ca_y) = (c.x - a.x, c.y - a.y);
+    let t = (ca_x * s.1 - ca_y * s.0) / denominator;
+    let u = (ca_x * r.1 - ca_y * r.0) / denominator;
+
+    ((0. ..=1.).contains(&t) && (0. ..=1.).contains(&u)).then_some(t)
+}
+
+/// The parts of the segment inside the rings, as fractions of the way along it.
+fn polygon_pieces(a: Point, b: Point, rings: &[Vec<Point>]) -> Vec<(f64, f64)> {
+    let mut cuts = vec![0., 1.];
+    for ring in rings {
+        for (i, c) in ring.iter().enumerate() {
+            let d = ring[(i + 1) % ring.len()];
+            cuts.extend(meets(a, b, *c, d));
+        }
+    }
+    cuts.sort_by(f64::total_cmp);
+    cuts.dedup();
+
+    let mut pieces: Vec<(f64, f64)> = vec![];
+    for cut in cuts.windows(2) {
+        let (start, end) = (cut[0], cut[1]);
+        if !even_odd(rings, along(a, b, (start + end) / 2.)) {
+            continue;
+        }
+
+        match pieces.last_mut() {
+            Some(piece) if piece.1 == start => piece.1 = end,
+            _ => pieces.push((start, end)),
+        }
+    }
+
+    pieces
+}
+
+/// An edge of a rectangle.
+#[derive(Clone, Copy)]
+enum Edge {
+    Left(f64),
+    Right(f64),
+    Bottom(f64),
+    Top(f64),
+}
+
+impl Edge {
+    fn inside(self, p: Point) -> bool {
+        match self {
+            Edge::Left(x) => p.x >= x,
+            Edge::Right(x) => p.x <= x,
+            Edge::Bottom(y) => p.y >= y,
+            Edge::Top(y) => p.y <= y,
+        }
+    }
+
+    /// Where the segment crosses the line along the edge.
+    fn cut(self, a: Point, b: Point) -> Point {
+        match self {
+            Edge::Left(x) | Edge::Right(x) => Point {
+                x,
+                y: a.y + (x - a.x) * (b.y - a.y) / (b.x - a.x),
+            },
+            Edge::Bottom(y) | Edge::Top(y) => Point {
+                x: a.x + (y - a.y) * (b.x - a.x) / (b.y - a.y),
+                y,
+            },
+        }
+    }
+}
+
+/// Clips the open ring to each edge of the rectangle in turn.
+/// Concave rings may come out with edges running along the rectangle and back.
+fn sutherland_hodgman(ring: &[Point], mbr: &MinimumBoundingRectangle) -> Vec<Point> {
+    let edges = [
+        Edge::Left(mbr.x.start),
+        Edge::Right(mbr.x.end),
+        Edge::Bottom(mbr.y.start),
+        Edge::Top(mbr.y.end),
+    ];
+
+    let mut clipped = ring.to_vec();
+    for edge in edges {
+        let input = std::mem::take(&mut clipped);
+        let Some(&last) = input.last() else {
+            break;
+        };
+
+        let mut previous = last;
+        for current in input {
+            match (edge.inside(previous), edge.inside(current)) {
+                (true, true) => clipped.push(current),
+                (true, false) => clipped.push(edge.cut(previous, current)),
+                (false, true) => clipped.extend([edge.cut(previous, current), current]),
+                (false, false) => {}
+            }
+            previous = current;
+        }
+    }
+
+    clipped
+}
+
+/// A vertex of a ring, or a crossing with a ring of the other polygon.
+struct Node {
+    point: Point,
+    next: usize,
+    previous: usize,
+
+    /// The same crossing among the rings of the other polygon, `None` for vertices
+    neighbour: Option<usize>,
+
+    /// Whether walking forwards goes into the other polygon here
+    entry: bool,
+    visited: bool,
+}
+
+/// The open rings of the area inside both sets of open rings, by the even-odd rule.
+/// `None` if the rings touch or run along each other, so there is no clear inside and outside
+/// at the crossings.
+fn greiner_hormann(subject: &[Vec<Point>], clip: &[Vec<Point>]) -> Option<Vec<Vec<Point>>> {
+    let edges = |rings: &[Vec<Point>]| -> Vec<Vec<Vec<(f64, usize)>>> {
+        rings.iter().map(|ring| vec![vec![]; ring.len()]).collect()
+    };
+    let (mut subject_cuts, mut clip_cuts) = (edges(subject), edges(clip));
+
+    let mut crossings = vec![];
+    for (si, ring) in subject.iter().enumerate() {
+        for (i, a) in ring.iter().enumerate() {
+            let b = ring[(i + 1) % ring.len()];
+
+            for (ci, other) in clip.iter().enumerate() {
+                for (j, c) in other.iter().enumerate() {
+                    let d = other[(j + 1) % other.len()];
+
+                    let Some((t, u)) = crossing(*a, b, *c, d)? else {
+                        continue;
+                    };
+                    subject_cuts[si][i].push((t, crossings.len()));
+                    clip_cuts[ci][j].push((u, crossings.len()));
+                    crossings.push(along(*a, b, t));
+                }
+            }
+        }
+    }
+
+    let mut nodes = vec![];
+    let (mut subject_nodes, mut clip_nodes) = (vec![0; crossings.len()], vec![0; crossings.len()]);
+    let subject_firsts = link(
+        &mut nodes,
+        subject,
+        subject_cuts,
+        &crossings,
+        &mut subject_nodes,
+    );
+    let clip_firsts = link(&mut nodes, clip, clip_cuts, &crossings, &mut clip_nodes);
+    for (s, c) in subject_nodes.iter().zip(&clip_nodes) {
+        nodes[*s].neighbour = Some(*c);
+        nodes[*c].neighbour = Some(*s);
+    }
+    mark_entries(&mut nodes, &subject_firsts, clip);
+    mark_entries(&mut nodes, &clip_firsts, subject);
+
+    let mut clipped = vec![];
+    for start in subject_nodes {
+        if nodes[start].visited {
+            continue;
+        }
+
+        let mut ring = vec![];
+        let mut current = start;
+        loop {
+            let forwards = nodes[current].entry;
+            nodes[current].visited = true;
+            if let Some(neighbour) = nodes[current].neighbour {
+                nodes[neighbour].visited = true;
+            }
+            ring.push(nodes[current].point);
+
+            // Along the current polygon until the next crossing, then over to the other one
+            loop {
+                current = match forwards {
+                    true => nodes[current].next,
+                    false => nodes[current].previous,
+                };
+                if nodes[current].neighbour.is_some() {
+                    break;
+                }
+                ring.push(nodes[current].point);
+            }
+
+            if nodes[current].visited {
+                break;
+            }
+            current = nodes[current].neighbour?;
+        }
+        clipped.push(ring);
+    }
+
+    // Rings without crossings are either wholly inside the other polygon or wholly outside
+    let uncrossed = |firsts: &[(usize, bool)], rings: &[Vec<Point>], other: &[Vec<Point>]| {
+        firsts
+            .iter()
+            .zip(rings)
+            .filter(|((_, crossed), ring)| !crossed && even_odd(other, ring[0]))
+            .map(|(_, ring)| ring.clone())
+            .collect::<Vec<_>>()
+    };
+    clipped.extend(uncrossed(&subject_firsts, subject, clip));
+    clipped.extend(uncrossed(&clip_firsts, clip, subject));
+
+    Some(clipped)
+}
+
+/// Where along the edges `a`-`b` and `c`-`d` they cross, if they do.
+/// `None` if they touch or run along each other.
+fn crossing(a: Point, b: Point, c: Point, d: Point) -> Option<Option<(f64, f64)>> {
+    let (r, s) = ((b.x - a.x, b.y - a.y), (d.x - c.x, d.y - c.y));
+    let denominator = r.0 * s.1 - r.1 * s.0;
+    let (length_r, length_s) = (r.0.hypot(r.1), s.0.hypot(s.1));
+
+    if denominator.abs() <= f64::EPSILON * length_r * length_s {
+        // Parallel, which only matters if they are on the same line and overlap
+        let apart = cross(a, b, c).abs() / length_r;
+        if apart > TOUCHING * length_r {
+            return Some(None);
+        }
+
+        let project = |p: Point| ((p.x - a.x) * r.0 + (p.y - a.y) * r.1) / (length_r * length_r);
+        let (from, to) = (project(c), project(d));
+        let overlapping = from.min(to) <= 1. + TOUCHING && from.max(to) >= -TOUCHING;
+        return (!overlapping).then_some(None);
+    }
+
+    let (ca_x, ca_y) = (c.x - a.x, c.y - a.y);
+    let t = (ca_x * s.1 - ca_y * s.0) / denominator;
+    let u = (ca_x * r.1 - ca_y * r.0) / denominator;
+
+    let within = |v: f64| (-TOUCHING..=1. + TOUCHING).contains(&v);
+    let strictly = |v: f64| v > TOUCHING && v < 1. - TOUCHING;
+    match (within(t) && within(u), strictly(t) && strictly(u)) {
+        (false, _) => Some(None),
+        (true, true) => Some(Some((t, u))),
+        (true, false) => None,
+    }
+}
+
+/// Adds the rings to the nodes, with the crossings on each edge in order along it.
+/// Gives the first node of each ring and whether it has crossings,
+/// and fills in the node of each crossing.
+fn link(
+    nodes: &mut Vec<Node>,
+    rings: &[Vec<Point>],
+    cuts: Vec<Vec<Vec<(f64, usize)>>>,
+    crossings: &[Point],
+    crossing_nodes: &mut [usize],
+) -> Vec<(usize, bool)> {
+    let node = |point| Node {
+        point,
+        next: 0,
+        previous: 0,
+        neighbour: None,
+        entry: false,
+        visited: false,
+    };
+
+    let mut firsts = vec![];
+    for (ring, ring_cuts) in rings.iter().zip(cuts) {
+        let first = nodes.len();
+        firsts.push((first, ring_cuts.iter().any(|edge| !edge.is_empty())));
+
+        for (point, mut edge_cuts) in ring.iter().zip(ring_cuts) {
+            nodes.push(node(*point));
+
+            edge_cuts.sort_by(|a, b| a.0.total_cmp(&b.0));
+            for (_, crossing) in edge_cuts {
+                crossing_nodes[crossing] = nodes.len();
+                nodes.push(node(crossings[crossing]));
+            }
+        }
+
+        let last = nodes.len() - 1;
+        for (index, node) in nodes.iter_mut().enumerate().skip(first) {
+            node.next = if index == last { first } else { index + 1 };
+            node.previous = if index == first { last } else { index - 1 };
+        }
+    }
+
+    firsts
+}
+
+/// Marks the crossings where walking forwards along the rings goes into the other polygon.
+fn mark_entries(nodes: &mut [Node], firsts: &[(usize, bool)], other: &[Vec<Point>]) {
+    for &(first, _) in firsts {
+        // The first node is a vertex, which is not on the other polygon's rings
+        let mut inside = even_odd(other, nodes[first].point);
+
+        let mut index = first;
+        loop {
+            if nodes[index].neighbour.is_some() {
+                nodes[index].entry = !inside;
+                inside = !inside;
+            }
+
+            index = nodes[index].next;
+            if index == first {
+                break;
+            }
+        }
+    }
+}
+
+/// A polygon of the open rings, oriented by how deeply they are nested.
+/// Rings without area are dropped, `None` if none are left.
+fn assemble(rings: Vec<Vec<Point>>) -> Option<Polygon> {
+    let mut rings: Vec<Vec<Point>> = rings
+        .iter()
+        .map(|ring| open_ring(ring))
+        .filter(|ring| ring.len() >= 3 && signed_area(ring) != 0.)
+        .collect();
+
+    for index in 0..rings.len() {
+        let depth = rings
+            .iter()
+            .enumerate()
+            .filter(|(other, ring)| *other != index && nested(&rings[index], ring))
+            .count();
+        let expected = match depth % 2 {
+            0 => Orientation::Clockwise,
+            _ => Orientation::CounterClockwise,
+        };
+
+        if Orientation::of(&rings[index]) != expected {
+            rings[index].reverse();
+        }
+    }
+
+    for ring in &mut rings {
+        ring.push(ring[0]);
+    }
+    let (parts, points) = join(rings);
+
+    Some(Polygon {
+        mbr: MinimumBoundingRectangle::from_points(&points)?,
+        parts,
+        points,
+    })
+}
+
+/// Whether the open ring `inner` is inside the open ring `outer`, where the rings may share
+/// points and edges but do not cross.
+/// Decided by the first vertex or edge midpoint of `inner` which is not on `outer`.
+fn nested(inner: &[Point], outer: &[Point]) -> bool {
+    let Some(mbr) = MinimumBoundingRectangle::from_points(outer) else {
+        return false;
+    };
+    let near = TOUCHING * (mbr.x.end - mbr.x.start).max(mbr.y.end - mbr.y.start);
+
+    let on_outer = |p: Point| {
+        outer.iter().enumerate().any(|(i, a)| {
+            let b = outer[(i + 1) % outer.len()];
+            let (dx, dy) = (b.x - a.x, b.y - a.y);
+            let length_sq = dx * dx + dy * dy;
+            let t = match length_sq {
+                0. => 0.,
+                _ => (((p.x - a.x) * dx + (p.y - a.y) * dy) / length_sq).clamp(0., 1.),
+            };
+            (a.x + t * dx - p.x).hypot(a.y + t * dy - p.y) <= near
+        })
+    };
+
+    inner
+        .iter()
+        .enumerate()
+        .flat_map(|(i, a)| {
+            let b = inner[(i + 1) % inner.len()];
+            [
+                *a,
+                Point {
+                    x: (a.x + b.x) / 2.,
+                    y: (a.y + b.y) / 2.,
+                },
+            ]
+        })
+        .find(|p| !on_outer(*p))
+        .is_some_and(|p| ring_contains(outer, p))
+}
@@ -0,0 +1,443 @@
+//! Coordinate reference systems, as given by the `.prj` file next to the `.shp`.
+//!
+//! The `.prj` file holds an ESRI flavoured WKT1 definition, e.g.
+//! `GEOGCS["GCS_WGS_1984",DATUM["D_WGS_1984",SPHEROID["WGS_1984",6378137.0,298.257223563]],...]`.
+//! See https://docs.ogc.org/is/18-010r7/18-010r7.html#Annex_C (the WKT1 part).
+
+use std::{fs, path::Path, str::FromStr};
+
+use crate::parse::{Error, Result};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Crs {
+    pub name: String,
+
+    /// The geographic system the coordinates are based on.
+    /// For geographic systems this is the same as `name`.
+    pub geographic_name: String,
+    pub datum: Datum,
+
+    /// Longitude of the prime meridian, in the angular unit
+    pub prime_meridian: f64,
+
+    /// Unit of latitudes and longitudes
+    pub angular_unit: Unit,
+
+    /// `None` if the coordinates are latitudes and longitudes
+    pub projection: Option<Projection>,
+
+    /// From the `AUTHORITY` node if there is one (which ESRI files don't have),
+    /// otherwise identified from the definition, see [`Crs::identify`]
+    pub epsg: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Datum {
+    pub name: String,
+    pub ellipsoid: Ellipsoid,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ellipsoid {
+    pub name: String,
+
+    /// In metres
+    pub semi_major_axis: f64,
+
+    /// Zero for a sphere
+    pub inverse_flattening: f64,
+}
+
+impl Ellipsoid {
+    pub fn flattening(&self) -> f64 {
+        match self.inverse_flattening {
+            0. => 0.,
+            inverse => 1. / inverse,
+        }
+    }
+}
+
+/// A map projection, from latitudes and longitudes to planar coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Projection {
+    /// E.g. `Transverse_Mercator`
+    pub method: String,
+    pub parameters: Vec<Parameter>,
+
+    /// Unit of the projected coordinates
+    pub linear_unit: Unit,
+}
+
+impl Projection {
+    /// Looks up a parameter, ignoring case and underscores,
+    /// so both `Central_Meridian` and `central_meridian` match `"central meridian"`.
+    pub fn parameter(&self, name: &str) -> Option<f64> {
+        self.parameters
+            .iter()
+            .find(|parameter| normalize(&parameter.name) == normalize(name))
+            .map(|parameter| parameter.value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unit {
+    pub name: String,
+
+    /// To metres for linear units, to radians for angular units
+    pub factor: f64,
+}
+
+/// Lowercase letters and digits only, to compare names which are spelled differently
+/// by ESRI and others, e.g. `D_WGS_1984` and `WGS 1984`.
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+impl Crs {
+    /// Reads and parses a `.prj` file.
+    pub fn from_prj_file<P: AsRef<Path>>(prj_path: P) -> Result<Self> {
+        fs::read_to_string(prj_path)?.parse()
+    }
+
+    pub fn from_wkt(wkt: &str) -> Result<Self> {
+        let root = WktParser { wkt, at: 0 }.parse()?;
+
+        let (mut crs, authority) = match root.keyword.as_str() {
+            "GEOGCS" => {
+                let authority = root.authority();
+                (geographic(&root)?, authority)
+            }
+            "PROJCS" => {
+                let mut crs = geographic(root.child("GEOGCS")?)?;
+                crs.name = root.name()?;
+                crs.projection = Some(Projection {
+                    method: root.child("PROJECTION")?.name()?,
+                    parameters: root
+                        .children("PARAMETER")
+                        .map(|parameter| {
+                            Ok(Parameter {
+                                name: parameter.name()?,
+                                value: parameter.number(1)?,
+                            })
+                        })
+                        .collect::<Result<_>>()?,
+                    linear_unit: unit(root.child("UNIT")?)?,
+                });
+
+                (crs, root.authority())
+            }
+            keyword => return Err(root.error(format!("`{keyword}` is not supported"))),
+        };
+
+        crs.epsg = authority.or_else(|| crs.identify());
+        Ok(crs)
+    }
+
+    pub fn is_geographic(&self) -> bool {
+        self.projection.is_none()
+    }
+
+    /// Identifies the EPSG code of the system by looking at its definition,
+    /// for the systems likely to be found in Norwegian data:
+    ///
+    /// - 4326: WGS 84 latitudes and longitudes
+    /// - 3857: Web Mercator
+    /// - 326xx: WGS 84 / UTM zone xxN, e.g. 32633 for zone 33N
+    /// - 258xx: ETRS89 (EUREF89) / UTM zone xxN, e.g. 25833 for zone 33N
+    pub fn identify(&self) -> Option<u32> {
+        let datum = normalize(&self.datum.name);
+        let wgs84 = datum.contains("wgs1984") || datum.contains("wgs84");
+        let etrs89 = ["etrs1989", "etrs89", "euref89", "europeanterrestrial"]
+            .iter()
+            .any(|name| datum.contains(name));
+
+        let degrees = (self.angular_unit.factor - 1f64.to_radians()).abs() < 1e-12;
+        if !degrees || self.prime_meridian != 0. {
+            return None;
+        }
+
+        let Some(projection) = &self.projection else {
+            return wgs84.then_some(4326);
+        };
+
+        if projection.linear_unit.factor != 1. {
+            return None;
+        }
+
+        let parameter = |name| projection.parameter(name).unwrap_or_default();
+        match normalize(&projection.method).as_str() {
+            "mercatorauxiliarysphere" | "popularvisualisationpseudomercator"
+                if wgs84
+                    && parameter("false easting") == 0.
+                    && parameter("false northing") == 0. =>
+            {
+                Some(3857)
+            }
+            "transversemercator"
+                if parameter("scale factor") == 0.9996
+                    && parameter("false easting") == 500_000.
+                    && parameter("false northing") == 0.
+                    && parameter("latitude of origin") == 0. =>
+            {
+                // Zone 1 is centred on 177°W, with 6° per zone
+                let zone = (parameter("central meridian") + 183.) / 6.;
+                if zone.fract() != 0. || !(1. ..=60.).contains(&zone) {
+                    return None;
+                }
+                let zone = zone as u32;
+
+                match (wgs84, etrs89) {
+                    (true, _) => Some(32600 + zone),
+                    (_, true) if (28..=38).contains(&zone) => Some(25800 + zone),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Crs {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_wkt(s)
+    }
+}
+
+fn geographic(geogcs: &Node) -> Result<Crs> {
+    let datum = geogcs.child("DATUM")?;
+    let spheroid = datum.child("SPHEROID")?;
+
+    Ok(Crs {
+        name: geogcs.name()?,
+        geographic_name: geogcs.name()?,
+        datum: Datum {
+            name: datum.name()?,
+            ellipsoid: Ellipsoid {
+                name: spheroid.name()?,
+                semi_major_axis: spheroid.number(1)?,
+                inverse_flattening: spheroid.number(2)?,
+            },
+        },
+        prime_meridian: geogcs.child("PRIMEM")?.number(1)?,
+        angular_unit: unit(geogcs.child("UNIT")?)?,
+        projection: None,
+        epsg: None,
+    })
+}
+
+fn unit(node: &Node) -> Result<Unit> {
+    Ok(Unit {
+        name: node.name()?,
+        factor: node.number(1)?,
+    })
+}
+
+#[derive(Debug)]
+enum Value {
+    Text(String),
+    Number(f64),
+    Node(Node),
+}
+
+/// A `KEYWORD[value, ...]` in the WKT.
+#[derive(Debug)]
+struct Node {
+    /// Byte offset of the keyword in the WKT
+    offset: usize,
+    keyword: String,
+    values: Vec<Value>,
+}
+
+impl Node {
+    fn error(&self, reason: String) -> Error {
+        Error::BadWkt {
+            offset: self.offset,
+            reason,
+        }
+    }
+
+    fn children<'a>(&'a self, keyword: &'a str) -> impl Iterator<Item = &'a Node> + 'a {
+        self.values.iter().filter_map(move |value| match value {
+            Value::Node(node) if node.keyword == keyword => Some(node),
+            _ => None,
+        })
+    }
+
+    fn child(&self, keyword: &str) -> Result<&Node> {
+        self.values
+            .iter()
+            .find_map(|value| match value {
+                Value::Node(node) if node.keyword == keyword => Some(node),
+                _ => None,
+            })
+            .ok_or_else(|| self.error(format!("`{}` has no `{keyword}`", self.keyword)))
+    }
+
+    /// The first value, which is the name for all the nodes we look at.
+    fn name(&self) -> Result<String> {
+        match self.values.first() {
+            Some(Value::Text(name)) => Ok(name.clone()),
+            _ => Err(self.error(format!("`{}` has no name", self.keyword))),
+        }
+    }
+
+    fn number(&self, index: usize) -> Result<f64> {
+        match self.values.get(index) {
+            Some(Value::Number(number)) => Ok(*number),
+            _ => Err(self.error(format!(
+                "`{}` has no number at position {index}",
+                self.keyword
+            ))),
+        }
+    }
+
+    /// The code of an `AUTHORITY["EPSG","4326"]` node.
+    fn authority(&self) -> Option<u32> {
+        let authority = self.children("AUTHORITY").next()?;
+        match authority.values.as_slice() {
+            [Value::Text(name), Value::Text(code)] if name.eq_ignore_ascii_case("EPSG") => {
+                code.parse().ok()
+            }
+            _ => None,
+        }
+    }
+}
+
+struct WktParser<'a> {
+    wkt: &'a str,
+    at: usize,
+}
+
+impl<'a> WktParser<'a> {
+    fn parse(mut self) -> Result<Node> {
+        let node = self.node()?;
+
+        self.skip_whitespace();
+        if self.at != self.wkt.len() {
+            return Err(self.error("Expected the end".into()));
+        }
+
+        Ok(node)
+    }
+
+    fn error(&self, reason: String) -> Error {
+        Error::BadWkt {
+            offset: self.at,
+            reason,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.wkt[self.at..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    /// Consumes characters while they match, returning them.
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let start = self.at;
+        while let Some(c) = self.peek().filter(|c| f(*c)) {
+            self.at += c.len_utf8();
+        }
+        &self.wkt[start..self.at]
+    }
+
+    fn expect(&mut self, expected: &[char]) -> Result<char> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if expected.contains(&c) => {
+                self.at += 1;
+                Ok(c)
+            }
+            found => Err(self.error(format!("Expected one of {expected:?}, found {found:?}"))),
+        }
+    }
+
+    fn node(&mut self) -> Result<Node> {
+        self.skip_whitespace();
+        let offset = self.at;
+        let keyword = self
+            .take_while(|c| c.is_ascii_alphanumeric() || c == '_')
+            .to_ascii_uppercase();
+        if keyword.is_empty() {
+            return Err(self.error("Expected a keyword".into()));
+        }
+
+        // Both brackets and parentheses are allowed
+        let close = match self.expect(&['[', '('])? {
+            '[' => ']',
+            _ => ')',
+        };
+
+        let mut values = vec![self.value()?];
+        while self.expect(&[',', close])? == ',' {
+            values.push(self.value()?);
+        }
+
+        Ok(Node {
+            offset,
+            keyword,
+            values,
+        })
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some('"') => {
+                self.at += 1;
+                let mut text = String::new();
+                loop {
+                    text.push_str(self.take_while(|c| c != '"'));
+                    if self.peek().is_none() {
+                        return Err(self.error("Unterminated text".into()));
+                    }
+                    self.at += 1;
+
+                    // A doubled quote is a quote in the text
+                    if self.peek() != Some('"') {
+                        return Ok(Value::Text(text));
+                    }
+                    text.push('"');
+                    self.at += 1;
+                }
+            }
+            Some(c) if c.is_ascii_digit() || matches!(c, '-' | '+' | '.') => {
+                let number = self
+                    .take_while(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'));
+                number
+                    .parse()
+                    .map(Value::Number)
+                    .map_err(|_| self.error(format!("Bad number `{number}`")))
+            }
+            // Enumerations like `NORTH` in `AXIS` are bare words, keep them as text
+            Some(c) if c.is_ascii_alphabetic() => {
+                let start = self.at;
+                let word = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                self.skip_whitespace();
+                match self.peek() {
+                    Some('[' | '(') => {
+                        self.at = start;
+                        self.node().map(Value::Node)
+                    }
+                    _ => Ok(Value::Text(word.to_string())),
+                }
+            }
+            found => Err(self.error(format!("Expected a value, found {found:?}"))),
+        }
+    }
+}
@@ -0,0 +1,364 @@
+//! Finding the files which make up a Shapefile dataset, given the path without extension,
+//! either in a directory or in a `.zip` archive such as the downloads from Geofabrik.
+
+use std::{
+    ffi::OsStr,
+    fs::File,
+    io::{self, BufReader, Cursor, Read, Seek},
+    path::{Path, PathBuf},
+};
+
+use zip::ZipArchive;
+
+use crate::{
+    dbase::{DbaseFile, DeletedRecords, FieldType},
+    memo::{MemoFile, MemoKind},
+    parse::{Error, Parser, Recovery, Result},
+    shape::ShpFile,
+    shx::ShxFile,
+    spatial::Spatial,
+};
+
+/// Extensions of the files in a dataset, see [`Dataset::missing`].
+const EXTENSIONS: [&str; 5] = ["shp", "shx", "dbf", "prj", "cpg"];
+
+/// Where each file of a dataset is, if it was found.
+/// Extensions are matched case-insensitively, so `ROADS.SHP` is found for `roads`.
+///
+/// `T` is a [`PathBuf`] for datasets in a directory, and the entry name for `.zip` archives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dataset<T> {
+    /// The name of the files, without extension
+    pub name: String,
+
+    pub shp: Option<T>,
+    pub shx: Option<T>,
+    pub dbf: Option<T>,
+    pub prj: Option<T>,
+    pub cpg: Option<T>,
+
+    /// `.dbt` or `.fpt`
+    pub memo: Option<T>,
+}
+
+impl<T> Dataset<T> {
+    /// Looks through the candidates for files named like the dataset.
+    fn find(name: &str, candidates: impl IntoIterator<Item = (String, T)>) -> Self {
+        let mut dataset = Self {
+            name: name.to_string(),
+            shp: None,
+            shx: None,
+            dbf: None,
+            prj: None,
+            cpg: None,
+            memo: None,
+        };
+
+        for (file_name, candidate) in candidates {
+            let Some((stem, extension)) = file_name.rsplit_once('.') else {
+                continue;
+            };
+            if !stem.eq_ignore_ascii_case(name) {
+                continue;
+            }
+
+            let slot = match extension.to_ascii_lowercase().as_str() {
+                "shp" => &mut dataset.shp,
+                "shx" => &mut dataset.shx,
+                "dbf" => &mut dataset.dbf,
+                "prj" => &mut dataset.prj,
+                "cpg" => &mut dataset.cpg,
+                "dbt" | "fpt" => &mut dataset.memo,
+                _ => continue,
+            };
+            slot.get_or_insert(candidate);
+        }
+
+        dataset
+    }
+
+    /// The extensions of the `.shp`, `.shx`, `.dbf`, `.prj` and `.cpg` files which were not found.
+    /// Only the `.shp` and `.dbf` files are needed to open the dataset.
+    pub fn missing(&self) -> Vec<&'static str> {
+        let found = [&self.shp, &self.shx, &self.dbf, &self.prj, &self.cpg];
+
+        EXTENSIONS
+            .into_iter()
+            .zip(found)
+            .filter(|(_, found)| found.is_none())
+            .map(|(extension, _)| extension)
+            .collect()
+    }
+
+    /// One warning per optional file which was not found.
+    fn missing_warnings(&self) -> Vec<String> {
+        self.missing()
+            .into_iter()
+            .filter(|extension| !matches!(*extension, "shp" | "dbf"))
+            .map(|extension| format!("No .{extension} file found"))
+            .collect()
+    }
+
+    /// The `.shp` and `.dbf` files, or which of them are missing.
+    fn required(&self) -> Result<(&T, &T)> {
+        match (&self.shp, &self.dbf) {
+            (Some(shp), Some(dbf)) => Ok((shp, dbf)),
+            _ => Err(Error::MissingFiles {
+                name: self.name.clone(),
+                missing: self
+                    .missing()
+                    .into_iter()
+                    .filter(|extension| ["shp", "dbf"].contains(extension))
+                    .collect(),
+            }),
+        }
+    }
+}
+
+/// The directory and file name of a dataset path, dropping an extension if it has one of ours.
+fn split_base(base: &Path) -> (PathBuf, String) {
+    let has_extension = base
+        .extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|extension| {
+            let extension = extension.to_ascii_lowercase();
+            EXTENSIONS.contains(&extension.as_str()) || extension == "dbt" || extension == "fpt"
+        });
+    let name = match has_extension {
+        true => base.file_stem(),
+        false => base.file_name(),
+    };
+
+    let dir = match base.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    (dir, name.unwrap_or_default().to_string_lossy().into_owned())
+}
+
+impl Dataset<PathBuf> {
+    /// Finds the files of the dataset, e.g. `data/gis_osm_roads_free_1`.
+    /// The path may also be that of one of the files, e.g. `data/gis_osm_roads_free_1.shp`.
+    pub fn find_next_to<P: AsRef<Path>>(base: P) -> Result<Self> {
+        let (dir, name) = split_base(base.as_ref());
+
+        let candidates = std::fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| (entry.file_name(), entry.path())))
+            .collect::<io::Result<Vec<_>>>()?
+            .into_iter()
+            .filter_map(|(file_name, path)| Some((file_name.into_string().ok()?, path)));
+
+        Ok(Self::find(&name, candidates))
+    }
+}
+
+impl Dataset<String> {
+    /// Finds the files of the dataset among the entries of a `.zip` archive.
+    /// Entries in folders are found too.
+    pub fn find_in_zip<R: Read + Seek>(archive: &ZipArchive<R>, name: &str) -> Self {
+        let candidates = archive.file_names().map(|entry| {
+            let file_name = entry.rsplit('/').next().unwrap_or(entry);
+            (file_name.to_string(), entry.to_string())
+        });
+
+        Self::find(name, candidates)
+    }
+
+    /// The names of the datasets in a `.zip` archive, one for each `.shp` entry.
+    pub fn names_in_zip<R: Read + Seek>(archive: &ZipArchive<R>) -> Vec<String> {
+        archive
+            .file_names()
+            .filter_map(|entry| {
+                let file_name = entry.rsplit('/').next().unwrap_or(entry);
+                let (stem, extension) = file_name.rsplit_once('.')?;
+                extension
+                    .eq_ignore_ascii_case("shp")
+                    .then(|| stem.to_string())
+            })
+            .collect()
+    }
+}
+
+impl Spatial {
+    /// Opens a dataset by its path without extension, e.g. `data/gis_osm_roads_free_1`,
+    /// see [`Dataset::find_next_to`].
+    ///
+    /// Only the `.shp` and `.dbf` files must exist.
+    /// If found, the `.prj` file gives the [`Spatial::crs`], the `.cpg` file the encoding of the `.dbf`
+    /// and the memo file the text of memo fields.
+    /// The `.shx` file is checked to index as many records as the `.shp` has,
+    /// or used to find the records after corrupt ones, see [`Spatial::open_with_recovery`].
+    /// A `.shx` file which can't be parsed is left out with a warning,
+    /// and so is each of the `.shx`, `.prj` and `.cpg` files which is missing.
+    pub fn open<P: AsRef<Path>>(base: P) -> Result<Self> {
+        Self::open_with_recovery(base, Recovery::Strict)
+    }
+
+    /// Like [`Spatial::open`], but corrupt `.shp` records may be skipped,
+    /// see [`Spatial::with_recovery`].
+    pub fn open_with_recovery<P: AsRef<Path>>(base: P, recovery: Recovery) -> Result<Self> {
+        let dataset = Dataset::find_next_to(base)?;
+        let (shp, dbf) = dataset.required()?;
+        let mut warnings = dataset.missing_warnings();
+
+        let shx = dataset
+            .shx
+            .as_ref()
+            .map(Parser::parse_shx_file)
+            .transpose()
+            .unwrap_or_else(|e| ignore_shx(&mut warnings, e));
+        let dbf = parse_dbf(
+            Parser::new(dbf)?,
+            read_text(dataset.cpg.as_ref(), |path| std::fs::read(path))?,
+            dataset.memo.as_ref().map(MemoFile::open).transpose()?,
+        )?;
+        let prj = read_text(dataset.prj.as_ref(), |path| std::fs::read(path))?;
+
+        let shp = parse_shp(Parser::new(shp)?, shx, recovery, dbf.records.len())?;
+
+        let mut spatial = Self::pair(shp, dbf, prj)?;
+        spatial.warnings.splice(0..0, warnings);
+        Ok(spatial)
+    }
+
+    /// Opens a dataset inside a `.zip` archive, e.g. `gis_osm_roads_free_1`
+    /// inside `norway-latest-free.shp.zip` from Geofabrik, see [`Dataset::names_in_zip`].
+    /// The files are read into memory, otherwise this is like [`Spatial::open`].
+    pub fn open_zip<P: AsRef<Path>>(zip_path: P, name: &str) -> Result<Self> {
+        Self::open_zip_with_recovery(zip_path, name, Recovery::Strict)
+    }
+
+    /// Like [`Spatial::open_zip`], but corrupt `.shp` records may be skipped,
+    /// see [`Spatial::with_recovery`].
+    pub fn open_zip_with_recovery<P: AsRef<Path>>(
+        zip_path: P,
+        name: &str,
+        recovery: Recovery,
+    ) -> Result<Self> {
+        let mut archive = ZipArchive::new(BufReader::new(File::open(zip_path.as_ref())?))?;
+        let dataset = Dataset::find_in_zip(&archive, name);
+        let (shp, dbf) = dataset.required()?;
+        let mut warnings = dataset.missing_warnings();
+
+        let mut read = |entry: &String| -> Result<Vec<u8>> {
+            let mut bytes = vec![];
+            archive.by_name(entry)?.read_to_end(&mut bytes)?;
+            Ok(bytes)
+        };
+
+        let shp_bytes = read(shp)?;
+        let shx = match &dataset.shx {
+            Some(entry) => Parser::parse_shx_buffer(&read(entry)?)
+                .map(Some)
+                .unwrap_or_else(|e| ignore_shx(&mut warnings, e)),
+            None => None,
+        };
+        let dbf_bytes = read(dbf)?;
+        let cpg = read_text(dataset.cpg.as_ref(), &mut read)?;
+        let memo = match &dataset.memo {
+            Some(entry) => {
+                let kind = match entry.to_ascii_lowercase().ends_with(".fpt") {
+                    true => MemoKind::Fpt,
+                    false => MemoKind::Dbt,
+                };
+                Some(MemoFile::new(Cursor::new(read(entry)?), kind)?)
+            }
+            None => None,
+        };
+        let prj = read_text(dataset.prj.as_ref(), &mut read)?;
+
+        let dbf = parse_dbf(Parser::with_reader(dbf_bytes.as_slice()), cpg, memo)?;
+        let shp = parse_shp(
+            Parser::with_reader(shp_bytes.as_slice()),
+            shx,
+            recovery,
+            dbf.records.len(),
+        )?;
+
+        let mut spatial = Self::pair(shp, dbf, prj)?;
+        spatial.warnings.splice(0..0, warnings);
+        Ok(spatial)
+    }
+}
+
+/// Parses the `.shp` with the `.shx` if it was found.
+/// With [`Recovery::Lenient`] the `.shx` is used to find the records after unusable ones,
+/// otherwise it must index as many records as there are.
+/// Every `.dbf` row has a record, so all of them are known to be lost if parsing has to stop.
+fn parse_shp<R: Read>(
+    parser: Parser<R>,
+    shx: Option<ShxFile>,
+    recovery: Recovery,
+    num_rows: usize,
+) -> Result<ShpFile> {
+    let parser = parser.with_recovery(recovery).with_num_records(num_rows);
+
+    let Some(shx) = shx else {
+        return parser.impl_parse_shp();
+    };
+    if recovery == Recovery::Lenient {
+        return parser.with_index(&shx).impl_parse_shp();
+    }
+
+    let shp = parser.impl_parse_shp()?;
+    let (shp_num, shx_num) = (shp.records.len(), shx.records.len());
+    if shp_num != shx_num {
+        return Err(Error::UnexpectedData(format!(
+            "Shapefile # records not equal to its index: {shp_num} vs {shx_num}"
+        )));
+    }
+
+    Ok(shp)
+}
+
+/// The `.shx` is only needed to check or recover the `.shp`, so it can be done without.
+fn ignore_shx(warnings: &mut Vec<String>, error: Error) -> Option<ShxFile> {
+    warnings.push(format!("Ignoring the .shx file: {error}"));
+    None
+}
+
+/// Reads a text file if it was found.
+/// Such as `.prj` files from ESRI tools in Latin-1, text which isn't UTF-8 is decoded lossily,
+/// since the names of the coordinate system and encoding are ASCII.
+fn read_text<T, E>(
+    location: Option<&T>,
+    read: impl FnOnce(&T) -> std::result::Result<Vec<u8>, E>,
+) -> Result<Option<String>>
+where
+    Error: From<E>,
+{
+    location
+        .map(|location| Ok(String::from_utf8_lossy(&read(location)?).into_owned()))
+        .transpose()
+}
+
+/// Parses the `.dbf` like [`Parser::parse_dbf_file_with`] does, with the given sidecars.
+/// Deleted rows are kept for [`Spatial`] to pair them up with their shapes.
+fn parse_dbf<R: Read, M: Read + Seek>(
+    parser: Parser<R>,
+    cpg: Option<String>,
+    memo: Option<MemoFile<M>>,
+) -> Result<DbaseFile> {
+    let parser = match cpg {
+        Some(cpg) => parser.with_cpg(&cpg),
+        None => parser,
+    };
+    let mut dbf = parser
+        .with_deleted_records(DeletedRecords::IncludeFlagged)
+        .parse_dbase_file()?;
+
+    let has_memos = dbf
+        .header
+        .fields
+        .iter()
+        .any(|field| matches!(field.type_, FieldType::Memo));
+
+    if let Some(mut memo) = memo.filter(|_| has_memos) {
+        for record in &mut dbf.records {
+            memo.resolve(&dbf.header, record)?;
+        }
+    }
+
+    Ok(dbf)
+}
@@ -1,21 +1,206 @@
-use std::{ffi::CStr, io};
+use std::{fmt, io};
 
-use crate::parse::{Error, Parser, Result};
+use crate::{
+    encoding::Encoding,
+    parse::{Error, Parser, Result},
+    write::encode_value,
+};
 
 #[derive(Debug, Clone)]
 pub struct DbaseFile {
     pub header: DbaseHeader,
     pub records: Vec<DbaseRecord>,
+
+    /// Problems which did not stop parsing, see [`Parser::warnings`].
+    pub warnings: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct DbaseRecord {
-    pub entries: Vec<String>,
+    /// Marked as deleted by the deletion flag.
+    /// Only ever set if parsing with [`DeletedRecords::IncludeFlagged`].
+    pub deleted: bool,
+
+    /// One per field, in the order of [`DbaseHeader::fields`]
+    pub entries: Vec<DbaseValue>,
+
+    /// Field index and bytes of the values which can't be written back from the value alone:
+    /// Blanks other than the usual, e.g. `*****` for an overflowed number,
+    /// values written another way than [`DbaseWriter`](crate::write::DbaseWriter) would,
+    /// e.g. numbers with a leading `+` or fewer decimals than declared,
+    /// and memos, which may have been replaced by their text.
+    /// Used by [`DbaseWriter`](crate::write::DbaseWriter) to write parsed records unchanged.
+    pub raw: Vec<(usize, Vec<u8>)>,
+}
+
+/// What to do with records marked as deleted.
+/// Editors such as QGIS only flag deleted records, so they are still present in the file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeletedRecords {
+    /// Leave them out
+    Skip,
+
+    /// Keep them, with [`DbaseRecord::deleted`] set
+    #[default]
+    IncludeFlagged,
+
+    /// Fail with [`Error::DeletedRecord`]
+    Error,
+}
+
+/// A single field of a record, decoded according to its [`FieldDescriptor`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DbaseValue {
+    /// Character field, trailing spaces trimmed
+    Text(String),
+
+    /// Numeric field without decimals
+    Integer(i64),
+
+    /// Floating point field
+    Float(f64),
+
+    /// Numeric field with decimals
+    Decimal(f64),
+
+    Date(DbaseDate),
+    DateTime(DbaseDateTime),
+    Bool(bool),
+
+    /// A blank or unknown value
+    Null,
+
+    /// Block number of the value in the memo (`.dbt`/`.fpt`) file,
+    /// see [`MemoFile::resolve`](crate::memo::MemoFile::resolve)
+    Memo(u32),
+}
+
+impl DbaseValue {
+    /// The text of a character field.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            DbaseValue::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            DbaseValue::Integer(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Any kind of number.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            DbaseValue::Integer(v) => Some(*v as f64),
+            DbaseValue::Float(v) | DbaseValue::Decimal(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            DbaseValue::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, DbaseValue::Null)
+    }
+}
+
+impl fmt::Display for DbaseValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbaseValue::Text(text) => write!(f, "{text}"),
+            DbaseValue::Integer(v) => write!(f, "{v}"),
+            DbaseValue::Float(v) | DbaseValue::Decimal(v) => write!(f, "{v}"),
+            DbaseValue::Date(date) => write!(f, "{date}"),
+            DbaseValue::DateTime(date_time) => write!(f, "{date_time}"),
+            DbaseValue::Bool(v) => write!(f, "{v}"),
+            DbaseValue::Null => Ok(()),
+            DbaseValue::Memo(block) => write!(f, "memo@{block}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DbaseDate {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl DbaseDate {
+    /// From a Julian day number, where 2440588 is 1970-01-01.
+    pub fn from_julian_day(day: i64) -> Option<Self> {
+        // Fliegel and Van Flandern
+        let l = day + 68569;
+        let n = 4 * l / 146097;
+        let l = l - (146097 * n + 3) / 4;
+        let i = 4000 * (l + 1) / 1461001;
+        let l = l - 1461 * i / 4 + 31;
+        let j = 80 * l / 2447;
+        let day = l - 2447 * j / 80;
+        let l = j / 11;
+        let month = j + 2 - 12 * l;
+        let year = 100 * (n - 49) + i + l;
+
+        Some(Self {
+            year: year.try_into().ok()?,
+            month: month.try_into().ok()?,
+            day: day.try_into().ok()?,
+        })
+    }
+
+    /// The Julian day number, see [`DbaseDate::from_julian_day`].
+    pub fn julian_day(&self) -> i64 {
+        let (year, month, day) = (self.year as i64, self.month as i64, self.day as i64);
+        // Fliegel and Van Flandern, where January and February count as months of the year before
+        let a = (month - 14) / 12;
+
+        1461 * (year + 4800 + a) / 4 + 367 * (month - 2 - 12 * a) / 12
+            - 3 * ((year + 4900 + a) / 100) / 4
+            + day
+            - 32075
+    }
+}
+
+impl fmt::Display for DbaseDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DbaseDateTime {
+    pub date: DbaseDate,
+
+    /// Since midnight
+    pub millis: u32,
+}
+
+impl fmt::Display for DbaseDateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.millis / 1000;
+        write!(
+            f,
+            "{}T{:02}:{:02}:{:02}",
+            self.date,
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
 }
 
 // https://en.wikipedia.org/wiki/.dbf#File_format_of_Level_5_DOS_dBASE
 #[derive(Debug, Clone)]
 pub struct DbaseHeader {
+    /// Version, and whether there is a memo file
     pub flags: u8,
 
     /// Last update: Years since 1900
@@ -37,10 +222,40 @@ pub struct DbaseHeader {
     // Stored as u16
     pub record_bytes: usize,
 
+    /// Code page of the text, see [`Encoding::from_language_driver`]
+    pub language_driver: u8,
+
+    /// Used to decode character fields.
+    /// Either set on the parser (e.g. from a `.cpg` file), from the language driver,
+    /// or UTF-8 if neither is known (Latin-1 if the `.cpg` file is unknown).
+    /// Since UTF-8 is then only a guess, text which isn't UTF-8 is decoded as Windows-1252
+    /// with a warning, see [`Parser::warnings`].
+    pub encoding: Encoding,
+
+    /// An unfinished dBASE IV transaction
+    pub incomplete_transaction: bool,
+    pub encrypted: bool,
+
+    /// There is a production `.mdx` index file
+    pub has_mdx: bool,
+
     pub fields: Vec<FieldDescriptor>,
+
+    /// Bytes after the terminator of the field descriptors,
+    /// such as the 263 byte Visual FoxPro backlink to a database container
+    pub backlink: Vec<u8>,
 }
 
 impl DbaseHeader {
+    /// dBASE level 7 has longer field descriptors and stores binary numbers big-endian.
+    pub fn is_dbase7(&self) -> bool {
+        self.flags & 0x07 == 0x04
+    }
+
+    pub fn is_visual_foxpro(&self) -> bool {
+        matches!(self.flags, 0x30..=0x32)
+    }
+
     /// Compares the provided field name (in trimmed lowercase ASCII)
     /// to the declared fields (in trimmed lowercase ASCII).
     /// If a match is found returns the index.
@@ -76,6 +291,30 @@ pub enum FieldType {
     Logical = b'L',
     Memo = b'M',
     Numeric = b'N',
+
+    /// Binary 4 byte integer (Visual FoxPro, dBASE 7)
+    Integer = b'I',
+
+    /// Binary 8 byte float (Visual FoxPro)
+    Double = b'B',
+
+    /// Binary 8 byte float, stored to sort bytewise (dBASE 7)
+    OrderedDouble = b'O',
+
+    /// Binary 8 byte integer, in ten-thousandths (Visual FoxPro)
+    Currency = b'Y',
+
+    /// Julian day and milliseconds since midnight (Visual FoxPro)
+    DateTime = b'T',
+
+    /// Milliseconds since the start of the Julian day count (dBASE 7)
+    Timestamp = b'@',
+
+    /// Like [`FieldType::Integer`], assigned by dBASE 7 itself
+    Autoincrement = b'+',
+
+    /// Hidden Visual FoxPro field with a bit per nullable field, not interpreted
+    NullFlags = b'0',
 }
 
 impl TryFrom<char> for FieldType {
@@ -90,6 +329,14 @@ impl TryFrom<char> for FieldType {
             v if v == FieldType::Logical as u8 => FieldType::Logical,
             v if v == FieldType::Memo as u8 => FieldType::Memo,
             v if v == FieldType::Numeric as u8 => FieldType::Numeric,
+            v if v == FieldType::Integer as u8 => FieldType::Integer,
+            v if v == FieldType::Double as u8 => FieldType::Double,
+            v if v == FieldType::OrderedDouble as u8 => FieldType::OrderedDouble,
+            v if v == FieldType::Currency as u8 => FieldType::Currency,
+            v if v == FieldType::DateTime as u8 => FieldType::DateTime,
+            v if v == FieldType::Timestamp as u8 => FieldType::Timestamp,
+            v if v == FieldType::Autoincrement as u8 => FieldType::Autoincrement,
+            v if v == FieldType::NullFlags as u8 => FieldType::NullFlags,
 
             _ => {
                 return Err(Error::UnexpectedData(format!(
@@ -108,7 +355,24 @@ pub struct FieldDescriptor {
 
     /// Number of bytes in this field
     pub field_length: usize,
-    // TODO: There is more but lets add if they occur in .dbf files
+
+    /// Digits after the decimal point for numeric fields
+    pub decimal_count: u8,
+
+    /// Visual FoxPro: 0x01 hidden system field, 0x02 nullable, 0x04 binary, 0x0C autoincrement
+    pub flags: u8,
+
+    /// dBASE III+/IV work area ID
+    pub work_area: u8,
+
+    /// Next value of an autoincrement field
+    pub autoincrement_next: u32,
+
+    /// Visual FoxPro: Step of an autoincrement field
+    pub autoincrement_step: u8,
+
+    /// The field has a tag in the production `.mdx` file
+    pub indexed: bool,
 }
 
 impl<R> Parser<R>
@@ -116,7 +380,9 @@ where
     R: io::Read,
 {
     pub fn parse_dbase_header(&mut self) -> Result<DbaseHeader> {
-        assert_eq!(self.num_bytes_read(), 0);
+        if self.num_bytes_read() != 0 {
+            return Err(Error::HeaderNotAtStart(self.num_bytes_read()));
+        }
 
         let flags = self.parse_u8()?;
         let (yy, mm, dd) = (self.parse_u8()?, self.parse_u8()?, self.parse_u8()?);
@@ -124,28 +390,64 @@ where
         let header_bytes = self.parse_u16_le()? as usize;
         let record_bytes = self.parse_u16_le()? as usize;
 
-        // Misc we don't care about
-        self.read_exact(&mut [0; 20])?;
-
-        // 1 byte extra for terminator 0x0D after field descriptors
-        let non_field_header_bytes = self.num_bytes_read() + 1;
-
-        let field_descr_bytes = header_bytes
-            .checked_sub(non_field_header_bytes)
+        // Reserved
+        self.read_exact(&mut [0; 2])?;
+        let incomplete_transaction = self.parse_u8()? != 0;
+        let encrypted = self.parse_u8()? != 0;
+
+        // Multi-user processing, only used by dBASE itself
+        self.read_exact(&mut [0; 12])?;
+        let has_mdx = self.parse_u8()? != 0;
+        let language_driver = self.parse_u8()?;
+        self.read_exact(&mut [0; 2])?;
+
+        let encoding = self
+            .encoding()
+            .or(Encoding::from_language_driver(language_driver))
+            .unwrap_or(self.fallback_encoding());
+
+        // The rest of the header is read at once, since the number of fields is only known
+        // by looking for the terminator 0x0D after the field descriptors.
+        // Visual FoxPro has a 263 byte backlink after the terminator.
+        let descriptors_offset = self.num_bytes_read();
+        let rest_bytes = header_bytes
+            .checked_sub(descriptors_offset)
+            .filter(|bytes| *bytes > 0)
             .ok_or_else(|| Error::UnexpectedData("Too few bytes in header".into()))?;
+        let mut rest = vec![0; rest_bytes];
+        self.read_exact(&mut rest)?;
 
-        assert!(field_descr_bytes % 32 == 0);
-
-        let num_fields = field_descr_bytes / 32;
+        let dbase7 = flags & 0x07 == 0x04;
+        let (mut at, descriptor_bytes) = match dbase7 {
+            // After a 32 byte language driver name and 4 reserved bytes
+            true => (36, 48),
+            false => (0, 32),
+        };
 
         let mut fields = vec![];
-        for _ in 0..num_fields {
-            fields.push(self.parse_dbase_field_descriptor()?);
+        loop {
+            match rest.get(at) {
+                Some(0x0D) => break,
+                Some(_) if at + descriptor_bytes < rest.len() => {
+                    let mut descriptor = Parser::with_reader_at(
+                        &rest[at..at + descriptor_bytes],
+                        descriptors_offset + at,
+                    );
+                    fields.push(match dbase7 {
+                        true => descriptor.parse_dbase7_field_descriptor()?,
+                        false => descriptor.parse_dbase_field_descriptor()?,
+                    });
+                    at += descriptor_bytes;
+                }
+                found => {
+                    return Err(Error::BadHeaderTerminator {
+                        offset: descriptors_offset + at,
+                        found: found.copied().unwrap_or_default(),
+                    })
+                }
+            }
         }
 
-        let terminator = self.parse_u8()?;
-        assert_eq!(terminator, 0x0D, "expected terminator char 0x0D");
-
         Ok(DbaseHeader {
             flags,
             yy,
@@ -154,7 +456,13 @@ where
             num_records,
             header_bytes,
             record_bytes,
+            language_driver,
+            encoding,
+            incomplete_transaction,
+            encrypted,
+            has_mdx,
             fields,
+            backlink: rest[at + 1..].to_vec(),
         })
     }
 
@@ -162,90 +470,378 @@ where
         self.parse_ascii()?.try_into()
     }
 
-    pub fn parse_dbase_field_descriptor(&mut self) -> Result<FieldDescriptor> {
-        let num_read_start = self.num_bytes_read();
-
-        let mut name_bytes = vec![0; 11];
+    fn parse_dbase_field_name(&mut self, num_bytes: usize) -> Result<String> {
+        let mut name_bytes = vec![0; num_bytes];
         self.read_exact(&mut name_bytes)?;
-        let name = CStr::from_bytes_until_nul(&name_bytes[..])?
-            .to_str()
+
+        // Nul terminated, unless it takes up all the bytes
+        let end = name_bytes.iter().position(|b| *b == 0).unwrap_or(num_bytes);
+
+        Ok(std::str::from_utf8(&name_bytes[..end])
             .map_err(|e| Error::UnexpectedData(format!("Utf8: {e:?}")))?
-            .to_string();
+            .to_string())
+    }
 
+    /// The 32 byte field descriptor of dBASE III+/IV and Visual FoxPro.
+    pub fn parse_dbase_field_descriptor(&mut self) -> Result<FieldDescriptor> {
+        let name = self.parse_dbase_field_name(11)?;
         let type_ = self.parse_dbase_field_type()?;
 
-        // 4 reserved bytes
+        // Visual FoxPro: Where the field is within the record
         self.read_exact(&mut [0; 4])?;
 
         let field_length = self.parse_u8()? as usize;
+        let decimal_count = self.parse_u8()?;
+        let flags = self.parse_u8()?;
 
-        // 15 bytes we don't care about
-        self.read_exact(&mut [0; 15])?;
+        // Bytes 19 to 23: Visual FoxPro uses these for autoincrement,
+        // dBASE for multi-user things such as the work area ID in byte 20
+        let mut autoincrement = [0; 5];
+        self.read_exact(&mut autoincrement)?;
+        let [a, work_area, b, c, autoincrement_step] = autoincrement;
 
-        let num_read_end = self.num_bytes_read();
+        // Reserved
+        self.read_exact(&mut [0; 7])?;
+        let indexed = self.parse_u8()? != 0;
 
-        let num_read = num_read_end
-            .checked_sub(num_read_start)
-            .ok_or_else(|| Error::UnexpectedData("Did not read 32 bytes".into()))?;
-        // Expected size of field descr
-        assert_eq!(num_read, 32);
+        Ok(FieldDescriptor {
+            name,
+            type_,
+            field_length,
+            decimal_count,
+            flags,
+            work_area,
+            autoincrement_next: u32::from_le_bytes([a, work_area, b, c]),
+            autoincrement_step,
+            indexed,
+        })
+    }
+
+    /// The 48 byte field descriptor of dBASE 7.
+    pub fn parse_dbase7_field_descriptor(&mut self) -> Result<FieldDescriptor> {
+        let name = self.parse_dbase_field_name(32)?;
+        let type_ = self.parse_dbase_field_type()?;
+        let field_length = self.parse_u8()? as usize;
+        let decimal_count = self.parse_u8()?;
+
+        // Reserved
+        self.read_exact(&mut [0; 2])?;
+        let indexed = self.parse_u8()? != 0;
+        self.read_exact(&mut [0; 2])?;
+
+        let autoincrement_next = self.parse_u32_le()?;
+        self.read_exact(&mut [0; 4])?;
 
         Ok(FieldDescriptor {
             name,
             type_,
             field_length,
+            decimal_count,
+            flags: 0,
+            work_area: 0,
+            autoincrement_next,
+            autoincrement_step: 1,
+            indexed,
         })
     }
 
     pub fn parse_dbase_record(&mut self, header: &DbaseHeader) -> Result<DbaseRecord> {
         // https://en.wikipedia.org/wiki/.dbf#Database_records
         // > Each record begins with a 1-byte "deletion" flag. The byte's value is a space (0x20), if the record is active, or an asterisk (0x2A), if the record is deleted.
+        let offset = self.num_bytes_read();
         let flag = self.parse_u8()?;
-        assert_eq!(
-            0x20,
-            flag,
-            "only active records handled (byte pos {})",
-            self.num_bytes_read()
-        );
-
-        let mut entries = vec![];
-        for FieldDescriptor {
-            name: _,
-            type_,
-            field_length,
-        } in &header.fields
-        {
-            let mut buf = vec![0; *field_length];
-            self.read_exact(&mut buf)?;
-
-            let entry = match type_ {
-                FieldType::Date => unimplemented!(),
-                FieldType::FloatingPoint => unimplemented!(),
-                FieldType::Logical => unimplemented!(),
-                FieldType::Memo => unimplemented!(),
-                FieldType::Character => std::str::from_utf8(&buf)?.trim_end().to_string(),
-                FieldType::Numeric => std::str::from_utf8(&buf)?.trim_end().to_string(),
-            };
+        let deleted = match flag {
+            0x20 => false,
+            0x2A if self.deleted_records() == DeletedRecords::Error => {
+                return Err(Error::DeletedRecord { offset })
+            }
+            0x2A => true,
+            flag => return Err(Error::BadDeletionFlag { offset, flag }),
+        };
+
+        let mut entries = Vec::with_capacity(header.fields.len());
+        let mut raw = vec![];
+        for (index, field) in header.fields.iter().enumerate() {
+            let (value, bytes) = self.read_dbase_value(header, field)?;
+
+            // Text is always written padded with spaces, so only other types are compared
+            let written_otherwise = !matches!(field.type_, FieldType::Character)
+                && encode_value(header.encoding, field, &value).as_ref() != Some(&bytes);
+            if written_otherwise || matches!(value, DbaseValue::Memo(_)) {
+                raw.push((index, bytes));
+            }
+            entries.push(value);
+        }
+
+        Ok(DbaseRecord {
+            deleted,
+            entries,
+            raw,
+        })
+    }
+
+    pub fn parse_dbase_value(
+        &mut self,
+        header: &DbaseHeader,
+        field: &FieldDescriptor,
+    ) -> Result<DbaseValue> {
+        self.read_dbase_value(header, field).map(|(value, _)| value)
+    }
+
+    /// Without an encoding from the parser (e.g. from a `.cpg` file) or the language driver,
+    /// UTF-8 is only a guess.
+    fn encoding_is_guessed(&self, header: &DbaseHeader) -> bool {
+        self.encoding().is_none()
+            && Encoding::from_language_driver(header.language_driver).is_none()
+            && header.encoding == Encoding::Utf8
+    }
 
-            entries.push(entry.trim_end().to_string());
+    /// The value along with the bytes it was decoded from.
+    fn read_dbase_value(
+        &mut self,
+        header: &DbaseHeader,
+        field: &FieldDescriptor,
+    ) -> Result<(DbaseValue, Vec<u8>)> {
+        let offset = self.num_bytes_read();
+        let mut buf = vec![0; field.field_length];
+        self.read_exact(&mut buf)?;
+
+        let value = match decode_field(header, field, &buf) {
+            Err(Error::Utf8Str(_)) if self.encoding_is_guessed(header) => {
+                self.warn_once(
+                    "The .dbf does not say how its text is encoded, and it isn't UTF-8, \
+                     so it is decoded as Windows-1252"
+                        .into(),
+                );
+                let text = Encoding::Windows1252.decode(&buf)?;
+                Some(DbaseValue::Text(text.trim_end().to_string()))
+            }
+            value => value?,
+        };
+
+        match value {
+            Some(value) => Ok((value, buf)),
+            None => Err(Error::BadValue {
+                offset,
+                type_: field.type_,
+                value: String::from_utf8_lossy(&buf).into_owned(),
+            }),
         }
+    }
 
-        Ok(DbaseRecord { entries })
+    /// Returns an iterator which parses one record at a time.
+    /// Must be called right after [`Parser::parse_dbase_header`].
+    ///
+    /// Unlike [`Parser::parse_dbase_record`], deleted records are left out
+    /// if parsing with [`DeletedRecords::Skip`].
+    pub fn dbf_records<'h>(&mut self, header: &'h DbaseHeader) -> DbfRecords<'_, 'h, R> {
+        DbfRecords {
+            parser: self,
+            header,
+            done: false,
+        }
     }
 
-    pub(crate) fn impl_parse_dbase_file(mut self) -> Result<DbaseFile> {
+    pub fn parse_dbase_file(mut self) -> Result<DbaseFile> {
         let header = self.parse_dbase_header()?;
+        let records = self.dbf_records(&header).collect::<Result<Vec<_>>>()?;
+
+        self.expect_bytes_read(header.dbase_num_bytes_header_and_records())?;
 
-        let mut records = vec![];
+        Ok(DbaseFile {
+            header,
+            records,
+            warnings: self.take_warnings(),
+        })
+    }
+}
 
-        let goal = header.dbase_num_bytes_header_and_records();
+/// Parses the records of a `.dbf` file one at a time, see [`Parser::dbf_records`].
+pub struct DbfRecords<'p, 'h, R> {
+    parser: &'p mut Parser<R>,
+    header: &'h DbaseHeader,
+    done: bool,
+}
 
-        while self.num_bytes_read() < goal {
-            records.push(self.parse_dbase_record(&header)?);
+impl<'p, 'h, R> Iterator for DbfRecords<'p, 'h, R>
+where
+    R: io::Read,
+{
+    type Item = Result<DbaseRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done
+            && self.parser.num_bytes_read() < self.header.dbase_num_bytes_header_and_records()
+        {
+            let record = self.parser.parse_dbase_record(self.header);
+            self.done = record.is_err();
+
+            let skip = self.parser.deleted_records() == DeletedRecords::Skip
+                && record.as_ref().is_ok_and(|record| record.deleted);
+            if !skip {
+                return Some(record);
+            }
+        }
+
+        None
+    }
+}
+
+/// Blank, unknown (`?`) or overflowed (`*`) values.
+fn is_blank(text: &str) -> bool {
+    text.chars().all(|c| matches!(c, ' ' | '?' | '*' | '\0'))
+}
+
+/// Decodes the bytes of any field type, or `None` if the bytes don't fit the type.
+pub(crate) fn decode_field(
+    header: &DbaseHeader,
+    field: &FieldDescriptor,
+    buf: &[u8],
+) -> Result<Option<DbaseValue>> {
+    Ok(match field.type_ {
+        FieldType::Character => {
+            let text = header.encoding.decode(buf)?.trim_end().to_string();
+            Some(DbaseValue::Text(text))
+        }
+        FieldType::Integer
+        | FieldType::Double
+        | FieldType::OrderedDouble
+        | FieldType::Currency
+        | FieldType::DateTime
+        | FieldType::Timestamp
+        | FieldType::Autoincrement
+        | FieldType::NullFlags => decode_binary_value(header, field, buf),
+        _ => decode_value(field, buf),
+    })
+}
+
+/// Decodes the non-character field types, or `None` if the bytes don't fit the type.
+fn decode_value(field: &FieldDescriptor, buf: &[u8]) -> Option<DbaseValue> {
+    // Visual FoxPro stores memo block numbers as binary
+    if let (FieldType::Memo, [a, b, c, d]) = (field.type_, buf) {
+        return Some(match u32::from_le_bytes([*a, *b, *c, *d]) {
+            0 => DbaseValue::Null,
+            block => DbaseValue::Memo(block),
+        });
+    }
+
+    let text = std::str::from_utf8(buf).ok()?.trim();
+    if is_blank(text) {
+        return Some(DbaseValue::Null);
+    }
+
+    Some(match field.type_ {
+        FieldType::FloatingPoint => DbaseValue::Float(text.parse().ok()?),
+        FieldType::Numeric if field.decimal_count == 0 => match text.parse() {
+            Ok(integer) => DbaseValue::Integer(integer),
+            // Some writers put decimals in fields declared without them
+            Err(_) => DbaseValue::Decimal(text.parse().ok()?),
+        },
+        FieldType::Numeric => DbaseValue::Decimal(text.parse().ok()?),
+        FieldType::Date => {
+            if text.len() != 8 || !text.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            if text == "00000000" {
+                return Some(DbaseValue::Null);
+            }
+
+            let date = DbaseDate {
+                year: text[0..4].parse().ok()?,
+                month: text[4..6].parse().ok()?,
+                day: text[6..8].parse().ok()?,
+            };
+            if !(1..=12).contains(&date.month) || !(1..=31).contains(&date.day) {
+                return None;
+            }
+
+            DbaseValue::Date(date)
         }
+        FieldType::Logical => match text.as_bytes()[0] {
+            b'T' | b't' | b'Y' | b'y' => DbaseValue::Bool(true),
+            b'F' | b'f' | b'N' | b'n' => DbaseValue::Bool(false),
+            _ => return None,
+        },
+        FieldType::Memo => match text.parse().ok()? {
+            // Block 0 is the memo file header, so it means no memo
+            0 => DbaseValue::Null,
+            block => DbaseValue::Memo(block),
+        },
+        _ => return None,
+    })
+}
+
+/// dBASE 7 stores integers big-endian with the sign bit flipped, so they sort bytewise.
+fn ordered_i32(bytes: [u8; 4]) -> i32 {
+    i32::from_be_bytes(bytes) ^ i32::MIN
+}
+
+/// Like [`ordered_i32`], but negative numbers have all bits flipped.
+fn ordered_f64(bytes: [u8; 8]) -> f64 {
+    let bits = u64::from_be_bytes(bytes);
+    f64::from_bits(match bits >> 63 {
+        1 => bits ^ (1 << 63),
+        _ => !bits,
+    })
+}
 
-        assert_eq!(self.num_bytes_read(), goal);
+const MILLIS_PER_DAY: u32 = 24 * 60 * 60 * 1000;
 
-        Ok(DbaseFile { header, records })
+fn date_time(julian_day: i64, millis: u32) -> Option<DbaseValue> {
+    if julian_day == 0 {
+        return Some(DbaseValue::Null);
+    }
+    if millis >= MILLIS_PER_DAY {
+        return None;
     }
+
+    Some(DbaseValue::DateTime(DbaseDateTime {
+        date: DbaseDate::from_julian_day(julian_day)?,
+        millis,
+    }))
+}
+
+/// Decodes the binary field types of Visual FoxPro and dBASE 7,
+/// or `None` if the field has the wrong length.
+fn decode_binary_value(
+    header: &DbaseHeader,
+    field: &FieldDescriptor,
+    buf: &[u8],
+) -> Option<DbaseValue> {
+    let four = || <[u8; 4]>::try_from(buf).ok();
+    let eight = || <[u8; 8]>::try_from(buf).ok();
+
+    Some(match field.type_ {
+        FieldType::Integer if header.is_dbase7() => {
+            DbaseValue::Integer(ordered_i32(four()?).into())
+        }
+        FieldType::Integer => DbaseValue::Integer(i32::from_le_bytes(four()?).into()),
+        FieldType::Autoincrement => DbaseValue::Integer(ordered_i32(four()?).into()),
+        FieldType::Double => DbaseValue::Float(f64::from_le_bytes(eight()?)),
+        FieldType::OrderedDouble => DbaseValue::Float(ordered_f64(eight()?)),
+        FieldType::Currency => DbaseValue::Decimal(i64::from_le_bytes(eight()?) as f64 / 10_000.),
+        FieldType::DateTime => {
+            let bytes = eight()?;
+            let (day, millis) = bytes.split_at(4);
+            date_time(
+                i32::from_le_bytes(day.try_into().ok()?).into(),
+                u32::from_le_bytes(millis.try_into().ok()?),
+            )?
+        }
+        FieldType::Timestamp => {
+            let millis = ordered_f64(eight()?);
+            if !millis.is_finite() || millis < 0. {
+                return None;
+            }
+            let millis = millis as u64;
+            let per_day = u64::from(MILLIS_PER_DAY);
+
+            date_time(
+                (millis / per_day).try_into().ok()?,
+                (millis % per_day).try_into().ok()?,
+            )?
+        }
+        FieldType::NullFlags => DbaseValue::Null,
+        _ => return None,
+    })
 }
@@ -0,0 +1,174 @@
+//! Text encodings of dBASE files.
+//!
+//! The encoding is given by the language driver ID in the `.dbf` header,
+//! or by the `.cpg` file next to it (which takes precedence).
+
+use std::{borrow::Cow, str::FromStr};
+
+use crate::parse::{Error, Result};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+
+    /// ISO-8859-1
+    Latin1,
+    Windows1252,
+
+    /// DOS US
+    Cp437,
+
+    /// DOS Western Europe
+    Cp850,
+
+    /// DOS Nordic
+    Cp865,
+}
+
+impl Encoding {
+    /// From the language driver ID at byte 29 of the `.dbf` header.
+    /// `None` if not set (0) or not known.
+    pub fn from_language_driver(id: u8) -> Option<Self> {
+        Some(match id {
+            0x01 | 0x09 | 0x0B | 0x0D | 0x0F | 0x11 | 0x15 | 0x18 | 0x19 | 0x1B => Self::Cp437,
+            0x02 | 0x0A | 0x0E | 0x10 | 0x12 | 0x14 | 0x16 | 0x1A | 0x1D | 0x25 | 0x37 => {
+                Self::Cp850
+            }
+            0x08 | 0x17 | 0x66 => Self::Cp865,
+            0x03 | 0x57 | 0x58 | 0x59 => Self::Windows1252,
+            _ => return None,
+        })
+    }
+
+    /// The language driver ID to write in the `.dbf` header for this encoding, if there is one.
+    pub fn language_driver(&self) -> Option<u8> {
+        match self {
+            Self::Utf8 | Self::Latin1 => None,
+            Self::Windows1252 => Some(0x03),
+            Self::Cp437 => Some(0x01),
+            Self::Cp850 => Some(0x02),
+            Self::Cp865 => Some(0x08),
+        }
+    }
+
+    /// Decodes to UTF-8, only allocating if needed.
+    pub fn decode<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, str>> {
+        let table = match self {
+            Self::Utf8 => return Ok(Cow::Borrowed(std::str::from_utf8(bytes)?)),
+            // ASCII is the same in the rest of them
+            _ if bytes.is_ascii() => return Ok(Cow::Borrowed(std::str::from_utf8(bytes)?)),
+            Self::Latin1 => return Ok(bytes.iter().map(|b| *b as char).collect()),
+            Self::Windows1252 => &WINDOWS_1252,
+            Self::Cp437 => &CP437,
+            Self::Cp850 => &CP850,
+            Self::Cp865 => &CP865,
+        };
+
+        Ok(bytes
+            .iter()
+            .map(|b| match b {
+                0..=0x7F => *b as char,
+                _ => table[(b - 0x80) as usize],
+            })
+            .collect())
+    }
+
+    /// Encodes from UTF-8, only allocating if needed.
+    /// `None` if the text has characters the encoding doesn't have.
+    pub fn encode<'a>(&self, text: &'a str) -> Option<Cow<'a, [u8]>> {
+        let table = match self {
+            Self::Utf8 => return Some(Cow::Borrowed(text.as_bytes())),
+            _ if text.is_ascii() => return Some(Cow::Borrowed(text.as_bytes())),
+            Self::Latin1 => return text.chars().map(|c| u8::try_from(c).ok()).collect(),
+            Self::Windows1252 => &WINDOWS_1252,
+            Self::Cp437 => &CP437,
+            Self::Cp850 => &CP850,
+            Self::Cp865 => &CP865,
+        };
+
+        text.chars()
+            .map(|c| match c {
+                '\0'..='\x7F' => Some(c as u8),
+                _ => table.iter().position(|t| *t == c).map(|i| 0x80 + i as u8),
+            })
+            .collect()
+    }
+}
+
+/// Parses the contents of a `.cpg` file, e.g. `UTF-8`, `1252`, `ANSI 1252` or `ISO-8859-1`.
+/// Case, spaces and dashes are ignored.
+impl FromStr for Encoding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let name: String = s
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+
+        let number = ["WINDOWS", "ANSI", "IBM", "OEM", "CP"]
+            .iter()
+            .find_map(|prefix| name.strip_prefix(prefix))
+            .unwrap_or(&name);
+
+        Ok(match number {
+            "UTF8" | "65001" => Self::Utf8,
+            "ISO88591" | "88591" | "LATIN1" | "28591" => Self::Latin1,
+            "1252" => Self::Windows1252,
+            "437" => Self::Cp437,
+            "850" => Self::Cp850,
+            "865" => Self::Cp865,
+            _ => return Err(Error::UnknownEncoding(s.trim().to_string())),
+        })
+    }
+}
+
+// Upper halves (0x80 to 0xFF) of the single byte code pages.
+// Bytes Windows-1252 leaves undefined map to the C1 control with the same value.
+
+#[rustfmt::skip]
+const WINDOWS_1252: [char; 128] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+    '\u{A0}', '¡', '¢', '£', '¤', '¥', '¦', '§', '¨', '©', 'ª', '«', '¬', '\u{AD}', '®', '¯',
+    '°', '±', '²', '³', '´', 'µ', '¶', '·', '¸', '¹', 'º', '»', '¼', '½', '¾', '¿',
+    'À', 'Á', 'Â', 'Ã', 'Ä', 'Å', 'Æ', 'Ç', 'È', 'É', 'Ê', 'Ë', 'Ì', 'Í', 'Î', 'Ï',
+    'Ð', 'Ñ', 'Ò', 'Ó', 'Ô', 'Õ', 'Ö', '×', 'Ø', 'Ù', 'Ú', 'Û', 'Ü', 'Ý', 'Þ', 'ß',
+    'à', 'á', 'â', 'ã', 'ä', 'å', 'æ', 'ç', 'è', 'é', 'ê', 'ë', 'ì', 'í', 'î', 'ï',
+    'ð', 'ñ', 'ò', 'ó', 'ô', 'õ', 'ö', '÷', 'ø', 'ù', 'ú', 'û', 'ü', 'ý', 'þ', 'ÿ',
+];
+
+#[rustfmt::skip]
+const CP437: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{A0}',
+];
+
+#[rustfmt::skip]
+const CP850: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', 'ø', '£', 'Ø', '×', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '®', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', 'Á', 'Â', 'À', '©', '╣', '║', '╗', '╝', '¢', '¥', '┐',
+    '└', '┴', '┬', '├', '─', '┼', 'ã', 'Ã', '╚', '╔', '╩', '╦', '╠', '═', '╬', '¤',
+    'ð', 'Ð', 'Ê', 'Ë', 'È', 'ı', 'Í', 'Î', 'Ï', '┘', '┌', '█', '▄', '¦', 'Ì', '▀',
+    'Ó', 'ß', 'Ô', 'Ò', 'õ', 'Õ', 'µ', 'þ', 'Þ', 'Ú', 'Û', 'Ù', 'ý', 'Ý', '¯', '´',
+    '\u{AD}', '±', '‗', '¾', '¶', '§', '÷', '¸', '°', '¨', '·', '¹', '³', '²', '■', '\u{A0}',
+];
+
+/// Like CP437, except for `ø` (0x9B), `Ø` (0x9D) and `¤` (0xAF)
+const CP865: [char; 128] = {
+    let mut table = CP437;
+    table[0x1B] = 'ø';
+    table[0x1D] = 'Ø';
+    table[0x2F] = '¤';
+    table
+};
@@ -0,0 +1,457 @@
+//! Measuring shapes: area, length, perimeter, centroid and a point on the surface.
+//!
+//! Planar measures take the coordinates as they are, so they are in the units of the projection.
+//! Geodesic measures take the coordinates to be longitude and latitude in degrees on WGS84,
+//! and are in metres and square metres.
+//!
+//! Only X and Y are measured, Z values are left out.
+//! Multipatches are measured as points.
+
+use std::{borrow::Cow, f64::consts::PI};
+
+use crate::{
+    project::Spheroid,
+    rings::{self, signed_area},
+    shape::{part_ranges, Point, Shape},
+};
+
+/// The points of a shape, and what they make up.
+enum Footprint<'a> {
+    Empty,
+    Points(Cow<'a, [Point]>),
+    Lines {
+        parts: &'a [i32],
+        points: &'a [Point],
+    },
+    Areas {
+        parts: &'a [i32],
+        points: &'a [Point],
+    },
+}
+
+impl Shape {
+    fn footprint(&self) -> Footprint<'_> {
+        let point = |x, y| Footprint::Points(Cow::Owned(vec![Point { x, y }]));
+
+        match self {
+            Shape::Null => Footprint::Empty,
+            Shape::Point(p) => Footprint::Points(Cow::Borrowed(std::slice::from_ref(p))),
+            Shape::PointZ(p) => point(p.x, p.y),
+            Shape::PointM(p) => point(p.x, p.y),
+            Shape::MultiPoint(m) => Footprint::Points(Cow::Borrowed(&m.points)),
+            Shape::MultiPointZ(m) => Footprint::Points(Cow::Borrowed(&m.points)),
+            Shape::MultiPointM(m) => Footprint::Points(Cow::Borrowed(&m.points)),
+            Shape::MultiPatch(m) => Footprint::Points(Cow::Borrowed(&m.points)),
+            Shape::PolyLine(l) => Footprint::Lines {
+                parts: &l.parts,
+                points: &l.points,
+            },
+            Shape::PolylineZ(l) => Footprint::Lines {
+                parts: &l.parts,
+                points: &l.points,
+            },
+            Shape::PolylineM(l) => Footprint::Lines {
+                parts: &l.parts,
+                points: &l.points,
+            },
+            Shape::Polygon(p) => Footprint::Areas {
+                parts: &p.parts,
+                points: &p.points,
+            },
+            Shape::PolygonZ(p) => Footprint::Areas {
+                parts: &p.parts,
+                points: &p.points,
+            },
+            Shape::PolygonM(p) => Footprint::Areas {
+                parts: &p.parts,
+                points: &p.points,
+            },
+        }
+    }
+
+    /// The area of polygons, with holes taken out, see [`rings::polygons`].
+    /// Zero for other shapes.
+    pub fn area(&self) -> f64 {
+        self.area_with(|ring| signed_area(ring).abs())
+    }
+
+    /// Like [`Shape::area`], in square metres on the WGS84 ellipsoid.
+    pub fn geodesic_area(&self) -> f64 {
+        self.area_with(geodesic_ring_area)
+    }
+
+    fn area_with(&self, ring_area: impl Fn(&[Point]) -> f64) -> f64 {
+        let Footprint::Areas { parts, points } = self.footprint() else {
+            return 0.;
+        };
+
+        rings::polygons(parts, points)
+            .into_iter()
+            .map(|polygon| {
+                let holes: f64 = polygon
+                    .interiors
+                    .into_iter()
+                    .map(|ring| ring_area(&points[ring]))
+                    .sum();
+                ring_area(&points[polygon.exterior]) - holes
+            })
+            .sum()
+    }
+
+    /// The length of all parts of lines.
+    /// Zero for other shapes, see [`Shape::perimeter`] for polygons.
+    pub fn length(&self) -> f64 {
+        self.length_with(planar_distance)
+    }
+
+    /// Like [`Shape::length`], in metres along the WGS84 ellipsoid.
+    pub fn geodesic_length(&self) -> f64 {
+        self.length_with(geodesic_distance)
+    }
+
+    fn length_with(&self, distance: impl Fn(Point, Point) -> f64) -> f64 {
+        let Footprint::Lines { parts, points } = self.footprint() else {
+            return 0.;
+        };
+
+        part_ranges(parts, points.len())
+            .map(|range| path_length(&points[range], &distance))
+            .sum()
+    }
+
+    /// The length of all rings of polygons, holes included.
+    /// Zero for other shapes.
+    pub fn perimeter(&self) -> f64 {
+        self.perimeter_with(planar_distance)
+    }
+
+    /// Like [`Shape::perimeter`], in metres along the WGS84 ellipsoid.
+    pub fn geodesic_perimeter(&self) -> f64 {
+        self.perimeter_with(geodesic_distance)
+    }
+
+    fn perimeter_with(&self, distance: impl Fn(Point, Point) -> f64) -> f64 {
+        let Footprint::Areas { parts, points } = self.footprint() else {
+            return 0.;
+        };
+
+        part_ranges(parts, points.len())
+            .map(|range| ring_length(&points[range], &distance))
+            .sum()
+    }
+
+    /// The centre of mass of the area of polygons, the length of lines or the points,
+    /// `None` if there are no points.
+    ///
+    /// Polygons without area are taken as lines, and lines without length as points.
+    /// The centroid need not be on the shape, see [`Shape::point_on_surface`].
+    pub fn centroid(&self) -> Option<Point> {
+        match self.footprint() {
+            Footprint::Empty => None,
+            Footprint::Points(points) => mean(&points),
+            Footprint::Lines { parts, points } => {
+                let parts = part_ranges(parts, points.len()).map(|range| &points[range]);
+                lines_centroid(parts, false).or_else(|| mean(points))
+            }
+            Footprint::Areas { parts, points } => areas_centroid(parts, points)
+                .or_else(|| {
+                    let rings = part_ranges(parts, points.len()).map(|range| &points[range]);
+                    lines_centroid(rings, true)
+                })
+                .or_else(|| mean(points)),
+        }
+    }
+
+    /// A point which is on the shape, `None` if there are no points.
+    ///
+    /// For polygons this is inside the largest polygon, on a horizontal line through its middle.
+    /// For lines and points it is the vertex closest to the centroid,
+    /// preferring those which are not line ends.
+    pub fn point_on_surface(&self) -> Option<Point> {
+        let centroid = self.centroid()?;
+
+        match self.footprint() {
+            Footprint::Empty => None,
+            Footprint::Points(points) => closest(&points, centroid),
+            Footprint::Lines { parts, points } => {
+                let inner = part_ranges(parts, points.len())
+                    .filter(|range| range.len() > 2)
+                    .flat_map(|range| &points[range.start + 1..range.end - 1])
+                    .copied()
+                    .collect::<Vec<_>>();
+                closest(&inner, centroid).or_else(|| closest(points, centroid))
+            }
+            Footprint::Areas { parts, points } => {
+                let largest = rings::polygons(parts, points).into_iter().max_by(|a, b| {
+                    let area = |polygon: &rings::PolygonRings| {
+                        signed_area(&points[polygon.exterior.clone()]).abs()
+                    };
+                    area(a).total_cmp(&area(b))
+                })?;
+
+                let mut rings = vec![&points[largest.exterior.clone()]];
+                rings.extend(largest.interiors.iter().map(|ring| &points[ring.clone()]));
+
+                inside_point(&rings).or_else(|| points.get(largest.exterior.start).copied())
+            }
+        }
+    }
+}
+
+fn planar_distance(a: Point, b: Point) -> f64 {
+    (b.x - a.x).hypot(b.y - a.y)
+}
+
+fn path_length(path: &[Point], distance: impl Fn(Point, Point) -> f64) -> f64 {
+    path.windows(2).map(|pair| distance(pair[0], pair[1])).sum()
+}
+
+/// Rings should repeat their first point at the end, if not the closing edge is added.
+fn ring_length(ring: &[Point], distance: impl Fn(Point, Point) -> f64) -> f64 {
+    let closing = match (ring.first(), ring.last()) {
+        (Some(first), Some(last)) => distance(*last, *first),
+        _ => 0.,
+    };
+
+    path_length(ring, &distance) + closing
+}
+
+fn mean(points: &[Point]) -> Option<Point> {
+    if points.is_empty() {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    Some(Point {
+        x: points.iter().map(|p| p.x).sum::<f64>() / n,
+        y: points.iter().map(|p| p.y).sum::<f64>() / n,
+    })
+}
+
+fn closest(points: &[Point], to: Point) -> Option<Point> {
+    points
+        .iter()
+        .copied()
+        .min_by(|a, b| planar_distance(*a, to).total_cmp(&planar_distance(*b, to)))
+}
+
+/// The midpoints of the edges weighted by their length, `None` without length.
+fn lines_centroid<'a>(paths: impl Iterator<Item = &'a [Point]>, closed: bool) -> Option<Point> {
+    let (mut x, mut y, mut length) = (0., 0., 0.);
+
+    for path in paths {
+        let closing = closed
+            .then(|| Some([*path.last()?, *path.first()?]))
+            .flatten();
+        let edges = path
+            .windows(2)
+            .map(|pair| [pair[0], pair[1]])
+            .chain(closing);
+
+        for [a, b] in edges {
+            let edge = planar_distance(a, b);
+            x += edge * (a.x + b.x) / 2.;
+            y += edge * (a.y + b.y) / 2.;
+            length += edge;
+        }
+    }
+
+    (length > 0.).then(|| Point {
+        x: x / length,
+        y: y / length,
+    })
+}
+
+/// The centroids of the rings weighted by their area, holes counting negatively.
+/// `None` without area.
+fn areas_centroid(parts: &[i32], points: &[Point]) -> Option<Point> {
+    let (mut x, mut y, mut area) = (0., 0., 0.);
+
+    for polygon in rings::polygons(parts, points) {
+        let exterior = std::iter::once((polygon.exterior, 1.));
+        let interiors = polygon.interiors.into_iter().map(|ring| (ring, -1.));
+
+        for (ring, sign) in exterior.chain(interiors) {
+            let ring = &points[ring];
+            let Some(first) = ring.first() else {
+                continue;
+            };
+
+            // Triangles fanning out from the first point, relative to it for precision
+            let (mut ring_x, mut ring_y, mut ring_area) = (0., 0., 0.);
+            for (a, b) in ring.iter().zip(ring.iter().skip(1).chain([first])) {
+                let (ax, ay) = (a.x - first.x, a.y - first.y);
+                let (bx, by) = (b.x - first.x, b.y - first.y);
+                let twice = ax * by - bx * ay;
+
+                ring_x += twice * (ax + bx) / 3.;
+                ring_y += twice * (ay + by) / 3.;
+                ring_area += twice;
+            }
+
+            // Orientation of the ring must not matter
+            let weight = sign * ring_area.signum();
+            x += weight * (ring_x + ring_area * first.x);
+            y += weight * (ring_y + ring_area * first.y);
+            area += weight * ring_area;
+        }
+    }
+
+    (area != 0.).then(|| Point {
+        x: x / area,
+        y: y / area,
+    })
+}
+
+/// A point inside the polygon given by an exterior ring and its holes,
+/// the middle of the widest span along a horizontal line through the middle of the polygon.
+///
+/// The line is placed between vertices so it crosses edges cleanly.
+fn inside_point(rings: &[&[Point]]) -> Option<Point> {
+    let ys = || rings.iter().flat_map(|ring| ring.iter().map(|p| p.y));
+    let low = ys().reduce(f64::min)?;
+    let high = ys().reduce(f64::max)?;
+    let middle = (low + high) / 2.;
+
+    let below = ys().filter(|y| *y <= middle).fold(low, f64::max);
+    let above = ys().filter(|y| *y > middle).fold(high, f64::min);
+    let y = (below + above) / 2.;
+
+    let mut crossings: Vec<f64> = rings
+        .iter()
+        .flat_map(|ring| ring.iter().zip(ring.iter().cycle().skip(1)))
+        .filter(|(a, b)| (a.y > y) != (b.y > y))
+        .map(|(a, b)| a.x + (y - a.y) * (b.x - a.x) / (b.y - a.y))
+        .collect();
+    crossings.sort_by(f64::total_cmp);
+
+    let (start, end) = crossings
+        .chunks_exact(2)
+        .map(|span| (span[0], span[1]))
+        .max_by(|a, b| (a.1 - a.0).total_cmp(&(b.1 - b.0)))?;
+
+    Some(Point {
+        x: (start + end) / 2.,
+        y,
+    })
+}
+
+/// The distance in metres between two points given as longitude and latitude in degrees,
+/// along the shortest path on the WGS84 ellipsoid.
+///
+/// Uses Vincenty's inverse formula, which is accurate to well below a millimetre.
+/// It may not converge for nearly antipodal points, then the distance is off by up to a few kilometres.
+pub fn geodesic_distance(from: Point, to: Point) -> f64 {
+    let Spheroid {
+        semi_major_axis: a,
+        flattening: f,
+    } = Spheroid::WGS84;
+    let b = a * (1. - f);
+
+    let l = (to.x - from.x).to_radians();
+    let (sin_u1, cos_u1) = ((1. - f) * from.y.to_radians().tan()).atan().sin_cos();
+    let (sin_u2, cos_u2) = ((1. - f) * to.y.to_radians().tan()).atan().sin_cos();
+
+    let mut lambda = l;
+    let mut iterations = 0;
+    let (sin_sigma, cos_sigma, sigma, cos_sq_alpha, cos_2_sigma_m) = loop {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+        let sin_sigma = (cos_u2 * sin_lambda).hypot(cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda);
+        if sin_sigma == 0. {
+            // The same point
+            return 0.;
+        }
+
+        let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+        let sigma = sin_sigma.atan2(cos_sigma);
+        let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+        let cos_sq_alpha = 1. - sin_alpha * sin_alpha;
+        let cos_2_sigma_m = if cos_sq_alpha == 0. {
+            // Along the equator
+            0.
+        } else {
+            cos_sigma - 2. * sin_u1 * sin_u2 / cos_sq_alpha
+        };
+
+        let c = f / 16. * cos_sq_alpha * (4. + f * (4. - 3. * cos_sq_alpha));
+        let previous = lambda;
+        lambda = l
+            + (1. - c)
+                * f
+                * sin_alpha
+                * (sigma
+                    + c * sin_sigma
+                        * (cos_2_sigma_m
+                            + c * cos_sigma * (-1. + 2. * cos_2_sigma_m * cos_2_sigma_m)));
+
+        iterations += 1;
+        if (lambda - previous).abs() < 1e-12 || iterations == 200 {
+            break (sin_sigma, cos_sigma, sigma, cos_sq_alpha, cos_2_sigma_m);
+        }
+    };
+
+    let u_sq = cos_sq_alpha * (a * a - b * b) / (b * b);
+    let big_a = 1. + u_sq / 16384. * (4096. + u_sq * (-768. + u_sq * (320. - 175. * u_sq)));
+    let big_b = u_sq / 1024. * (256. + u_sq * (-128. + u_sq * (74. - 47. * u_sq)));
+    let delta_sigma = big_b
+        * sin_sigma
+        * (cos_2_sigma_m
+            + big_b / 4.
+                * (cos_sigma * (-1. + 2. * cos_2_sigma_m * cos_2_sigma_m)
+                    - big_b / 6.
+                        * cos_2_sigma_m
+                        * (-3. + 4. * sin_sigma * sin_sigma)
+                        * (-3. + 4. * cos_2_sigma_m * cos_2_sigma_m)));
+
+    b * big_a * (sigma - delta_sigma)
+}
+
+/// The area in square metres of a ring given as longitude and latitude in degrees.
+///
+/// Latitudes are mapped to the sphere with the same area as the WGS84 ellipsoid (authalic latitudes),
+/// where the area is the sum of the spherical excess between each edge and the pole.
+/// Edges follow great circles on that sphere rather than geodesics on the ellipsoid,
+/// which is off by a few parts in a hundred thousand for edges a degree long, and far less for shorter ones.
+/// Rings around a pole are not supported.
+pub fn geodesic_ring_area(ring: &[Point]) -> f64 {
+    let a = Spheroid::WGS84.semi_major_axis;
+    let e2 = Spheroid::WGS84.e2();
+    let e = e2.sqrt();
+
+    // From Snyder, "Map Projections: A Working Manual", equation 3-12
+    let q = |sin_phi: f64| {
+        (1. - e2)
+            * (sin_phi / (1. - e2 * sin_phi * sin_phi)
+                - 1. / (2. * e) * ((1. - e * sin_phi) / (1. + e * sin_phi)).ln())
+    };
+    let q_pole = q(1.);
+    let radius_sq = a * a * q_pole / 2.;
+
+    // Tangent of half the authalic latitude
+    let tan_half_beta = |p: &Point| {
+        let beta = (q(p.y.to_radians().sin()) / q_pole).clamp(-1., 1.).asin();
+        (beta / 2.).tan()
+    };
+
+    let Some(first) = ring.first() else {
+        return 0.;
+    };
+    let excess: f64 = ring
+        .iter()
+        .zip(ring.iter().skip(1).chain([first]))
+        .map(|(p1, p2)| {
+            // Shortest way around, so edges may cross the antimeridian
+            let mut d_lambda = (p2.x - p1.x).to_radians();
+            if d_lambda > PI {
+                d_lambda -= 2. * PI;
+            } else if d_lambda < -PI {
+                d_lambda += 2. * PI;
+            }
+
+            let (t1, t2) = (tan_half_beta(p1), tan_half_beta(p2));
+            2. * ((d_lambda / 2.).tan() * (t1 + t2)).atan2(1. + t1 * t2)
+        })
+        .sum();
+
+    excess.abs() * radius_sq
+}
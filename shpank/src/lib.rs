@@ -1,6 +1,20 @@
+pub mod clip;
+pub mod crs;
+pub mod dataset;
 pub mod dbase;
+pub mod encoding;
+pub mod geometry;
+pub mod memo;
 pub mod parse;
+pub mod project;
+pub mod rings;
 pub mod shape;
+pub mod shx;
+pub mod simplify;
+pub mod triangulate;
+pub mod validate;
+pub mod view;
+pub mod write;
 
 /// Combined data
 pub mod spatial;
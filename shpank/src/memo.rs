@@ -0,0 +1,178 @@
+//! Memo files, holding the text of [`FieldType::Memo`](crate::dbase::FieldType::Memo) fields.
+//!
+//! The `.dbf` only stores the block number where the text starts in the memo file.
+//! See https://www.clicketyclick.dk/databases/xbase/format/dbt.html
+
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    dbase::{DbaseHeader, DbaseRecord, DbaseValue},
+    parse::{Error, Result},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoKind {
+    /// dBASE III and IV `.dbt`
+    Dbt,
+
+    /// FoxPro `.fpt`
+    Fpt,
+}
+
+/// dBASE IV memo blocks start with this, followed by the length
+const DBASE4_BLOCK_START: [u8; 4] = [0xFF, 0xFF, 0x08, 0x00];
+
+/// dBASE III memos end with this, although a single one is common too
+const END_OF_MEMO: u8 = 0x1A;
+
+/// FoxPro memo blocks say what they contain, this is text
+const FPT_TEXT: u32 = 1;
+
+pub struct MemoFile<R> {
+    reader: R,
+    kind: MemoKind,
+    block_size: usize,
+}
+
+impl MemoFile<BufReader<File>> {
+    /// The kind of memo file is given by the extension.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let kind = match path.as_ref().extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("fpt") => MemoKind::Fpt,
+            _ => MemoKind::Dbt,
+        };
+
+        Self::new(BufReader::new(File::open(path.as_ref())?), kind)
+    }
+
+    /// The `.dbt` or `.fpt` file next to the `.dbf` file, if there is one.
+    pub fn find_next_to<P: AsRef<Path>>(dbf_path: P) -> Option<PathBuf> {
+        ["dbt", "fpt", "DBT", "FPT"]
+            .into_iter()
+            .map(|ext| dbf_path.as_ref().with_extension(ext))
+            .find(|path| path.is_file())
+    }
+}
+
+impl<R> MemoFile<R>
+where
+    R: Read + Seek,
+{
+    /// Reads the block size from the header in the first block.
+    pub fn new(mut reader: R, kind: MemoKind) -> Result<Self> {
+        let mut header = [0; 24];
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_exact(&mut header)?;
+
+        let block_size = match kind {
+            MemoKind::Fpt => u16::from_be_bytes([header[6], header[7]]) as usize,
+            // Only set by dBASE IV, dBASE III always uses 512
+            MemoKind::Dbt => u16::from_le_bytes([header[20], header[21]]) as usize,
+        };
+
+        Ok(Self {
+            reader,
+            kind,
+            block_size: if block_size == 0 { 512 } else { block_size },
+        })
+    }
+
+    pub fn kind(&self) -> MemoKind {
+        self.kind
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// The raw bytes of the memo starting at the given block.
+    /// `None` for FoxPro memos that are not text, such as pictures.
+    pub fn read(&mut self, block: u32) -> Result<Option<Vec<u8>>> {
+        let offset = block as u64 * self.block_size as u64;
+        self.reader.seek(SeekFrom::Start(offset))?;
+
+        let mut start = Vec::with_capacity(8);
+        (&mut self.reader).take(8).read_to_end(&mut start)?;
+        if start.is_empty() {
+            return Err(Error::BadMemoBlock { block });
+        }
+
+        // A dBASE III memo at the end of the file may be shorter than a block header
+        if self.kind == MemoKind::Dbt && !start.starts_with(&DBASE4_BLOCK_START) {
+            return self.read_until_end_of_memo(start, block).map(Some);
+        }
+        let start: [u8; 8] = start
+            .try_into()
+            .map_err(|_| Error::BadMemoBlock { block })?;
+
+        let length = match self.kind {
+            MemoKind::Fpt => {
+                let type_ = u32::from_be_bytes([start[0], start[1], start[2], start[3]]);
+                if type_ != FPT_TEXT {
+                    return Ok(None);
+                }
+                u32::from_be_bytes([start[4], start[5], start[6], start[7]]) as u64
+            }
+            MemoKind::Dbt => {
+                // Includes the 8 bytes we just read
+                let length = u32::from_le_bytes([start[4], start[5], start[6], start[7]]) as u64;
+                length.checked_sub(8).ok_or(Error::BadMemoBlock { block })?
+            }
+        };
+
+        let mut memo = vec![];
+        (&mut self.reader).take(length).read_to_end(&mut memo)?;
+        if memo.len() as u64 != length {
+            return Err(Error::BadMemoBlock { block });
+        }
+
+        Ok(Some(memo))
+    }
+
+    /// dBASE III has no length, the memo goes on until [`END_OF_MEMO`] or the end of the file.
+    fn read_until_end_of_memo(&mut self, start: Vec<u8>, block: u32) -> Result<Vec<u8>> {
+        let mut memo = start;
+        let mut chunk = vec![0; self.block_size];
+        let mut searched = 0;
+
+        loop {
+            if let Some(end) = memo[searched..].iter().position(|b| *b == END_OF_MEMO) {
+                memo.truncate(searched + end);
+                return Ok(memo);
+            }
+            searched = memo.len();
+
+            let read = self.reader.read(&mut chunk)?;
+            if read == 0 {
+                return Ok(memo);
+            }
+            memo.extend_from_slice(&chunk[..read]);
+
+            if memo.len() > u32::MAX as usize {
+                return Err(Error::BadMemoBlock { block });
+            }
+        }
+    }
+
+    /// Replaces the [`DbaseValue::Memo`] values of the record with their text,
+    /// decoded with the encoding of the `.dbf`.
+    /// Memos which are not text are left as they are.
+    pub fn resolve(&mut self, header: &DbaseHeader, record: &mut DbaseRecord) -> Result<()> {
+        for value in &mut record.entries {
+            let DbaseValue::Memo(block) = value else {
+                continue;
+            };
+
+            if let Some(memo) = self.read(*block)? {
+                let text = header.encoding.decode(&memo)?;
+                *value = DbaseValue::Text(text.trim_end().to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
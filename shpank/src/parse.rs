@@ -1,8 +1,9 @@
 use std::{
     char, ffi,
     fs::File,
-    io::{self, BufReader},
+    io::{self, BufReader, Read},
     mem::size_of,
+    ops::Range,
     path::Path,
     string,
 };
@@ -10,11 +11,17 @@ use std::{
 use thiserror::Error;
 
 use crate::{
-    dbase::DbaseFile,
+    dbase::{DbaseFile, DeletedRecords, FieldType},
+    encoding::Encoding,
+    memo::MemoFile,
     shape::{
-        self, Double, Integer, MinimumBoundingRectangle, Point, PolyLine, Polygon, Shape,
-        ShapeType, ShpFile, ShpHeader, ShpLength, ShpRecord, ShpRecordHeader,
+        self, Double, Integer, Measure, Measures, MinimumBoundingRectangle, MultiPatch, MultiPoint,
+        MultiPointM, MultiPointZ, PatchType, Point, PointM, PointZ, PolyLine, Polygon, PolygonM,
+        PolygonZ, PolylineM, PolylineZ, Shape, ShapeType, ShpFile, ShpHeader, ShpLength, ShpRecord,
+        ShpRecordHeader,
     },
+    shx::{ShxFile, ShxRecord},
+    view::ShpMmap,
 };
 
 #[derive(Debug, Error)]
@@ -36,19 +43,325 @@ pub enum Error {
 
     #[error("Unexpected data: {0}")]
     UnexpectedData(String),
+
+    #[error("Truncated file: needed {needed} more bytes at byte offset {offset}")]
+    Truncated { offset: usize, needed: usize },
+
+    #[error("File length mismatch: header says {expected} bytes but {actual} bytes were parsed")]
+    FileLengthMismatch { expected: usize, actual: usize },
+
+    #[error("Record {record_number} at byte offset {offset}: content length says {expected} bytes but {actual} bytes were parsed")]
+    RecordLengthMismatch {
+        record_number: i32,
+        offset: usize,
+        expected: usize,
+        actual: usize,
+    },
+
+    #[error("Bad length `{length}` at byte offset {offset}")]
+    BadLength { offset: usize, length: i32 },
+
+    #[error("Bad count `{count}` at byte offset {offset}")]
+    BadCount { offset: usize, count: i32 },
+
+    #[error("Part indices out of order or out of bounds at byte offset {offset}")]
+    BadParts { offset: usize },
+
+    #[error("Header must be parsed from the start, but {0} bytes were already read")]
+    HeaderNotAtStart(usize),
+
+    #[error("Bad header terminator at byte offset {offset}: expected 0x0D, got 0x{found:02x}")]
+    BadHeaderTerminator { offset: usize, found: u8 },
+
+    #[error("Bad deletion flag at byte offset {offset}: 0x{flag:02x}")]
+    BadDeletionFlag { offset: usize, flag: u8 },
+
+    #[error("Deleted record at byte offset {offset}")]
+    DeletedRecord { offset: usize },
+
+    #[error("Bad {type_:?} value `{value}` at byte offset {offset}")]
+    BadValue {
+        offset: usize,
+        type_: FieldType,
+        value: String,
+    },
+
+    #[error("Memo block {block} is missing or cut short")]
+    BadMemoBlock { block: u32 },
+
+    #[error("Zip: {0:?}")]
+    Zip(#[from] zip::result::ZipError),
+
+    #[error("Dataset `{name}` has no {missing:?} file")]
+    MissingFiles {
+        name: String,
+        missing: Vec<&'static str>,
+    },
+
+    #[error("Bad WKT at byte offset {offset}: {reason}")]
+    BadWkt { offset: usize, reason: String },
+
+    #[error("Unknown encoding `{0}`")]
+    UnknownEncoding(String),
+
+    #[error("Unsupported field type: {0:?}")]
+    UnsupportedFieldType(FieldType),
+
+    #[error("Record {index} requested, but there are only {num_records} records")]
+    RecordOutOfRange { index: usize, num_records: usize },
+
+    #[error("Can't write a {actual:?} to a file of {expected:?} shapes")]
+    ShapeTypeMismatch {
+        expected: ShapeType,
+        actual: ShapeType,
+    },
+
+    #[error("Can't write a {shape_type:?}: {reason}")]
+    BadShape {
+        shape_type: ShapeType,
+        reason: String,
+    },
+
+    #[error("Field `{name}` can't be written: {reason}")]
+    BadFieldDescriptor { name: String, reason: String },
+
+    #[error("Value `{value}` does not fit field `{field}`")]
+    ValueDoesNotFit { field: String, value: String },
+
+    #[error("Shapefiles can't be larger than 2^31 16-bit words, {bytes} bytes needed")]
+    FileTooLarge { bytes: usize },
+
+    #[error("Can't clip {0:?} shapes")]
+    UnsupportedClip(ShapeType),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// What to do when a `.shp` record is corrupt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Recovery {
+    /// Stop parsing and return the error
+    #[default]
+    Strict,
+
+    /// Skip the record and keep going, collecting a [`Diagnostic`].
+    /// The record's content length is used to find the next record,
+    /// or the index if the record header is unusable, see [`Parser::with_index`].
+    Lenient,
+}
+
+/// A problem found while parsing with [`Recovery::Lenient`].
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// Position of the record in the file, starting at 0
+    pub record_index: usize,
+
+    /// As stated by the record header, if it could be read
+    pub record_number: Option<i32>,
+
+    /// Byte offset of the record header
+    pub offset: usize,
+
+    pub problem: String,
+}
+
+/// Parses the records of a `.shp` file one at a time, see [`Parser::shp_records`].
+pub struct ShpRecords<'p, R> {
+    parser: &'p mut Parser<R>,
+    header: ShpHeader,
+
+    /// Position of the next record in the file, starting at 0
+    record_index: usize,
+    dropped: Vec<usize>,
+    done: bool,
+}
+
+impl<'p, R> ShpRecords<'p, R>
+where
+    R: io::Read,
+{
+    pub fn header(&self) -> &ShpHeader {
+        &self.header
+    }
+
+    /// Positions of the records skipped so far, see [`ShpFile::dropped`].
+    pub fn dropped(&self) -> &[usize] {
+        &self.dropped
+    }
+
+    /// Checks that the last record ended where the file does.
+    fn finish(&mut self, goal: usize) -> Option<Result<ShpRecord>> {
+        self.done = true;
+
+        match self.parser.recovery {
+            Recovery::Strict => self.parser.expect_bytes_read(goal).err().map(Err),
+            Recovery::Lenient => {
+                let offset = self.parser.num_bytes_read();
+                if offset > goal {
+                    self.parser.diagnostics.push(Diagnostic {
+                        record_index: self.record_index,
+                        record_number: None,
+                        offset,
+                        problem: Error::FileLengthMismatch {
+                            expected: goal,
+                            actual: offset,
+                        }
+                        .to_string(),
+                    });
+                }
+                None
+            }
+        }
+    }
+}
+
+impl<'p, R> Iterator for ShpRecords<'p, R>
+where
+    R: io::Read,
+{
+    type Item = Result<ShpRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let goal = self.header.file_length.num_bytes();
+
+        while !self.done {
+            if self.parser.num_bytes_read() >= goal {
+                return self.finish(goal);
+            }
+
+            let record_index = self.record_index;
+            self.record_index += 1;
+
+            match self.parser.recovery {
+                Recovery::Strict => {
+                    let record = self.parser.parse_record();
+                    self.done = record.is_err();
+                    return Some(record);
+                }
+                Recovery::Lenient => match self.parser.parse_record_lenient(record_index, goal) {
+                    Ok(Some(record)) => return Some(Ok(record)),
+                    Ok(None) => self.dropped.push(record_index),
+                    Err(diagnostic) => {
+                        self.dropped.push(record_index);
+                        self.parser.diagnostics.push(diagnostic);
+
+                        if !self.parser.skip_to_indexed(self.record_index, goal) {
+                            // No way to find the next record, so the rest are lost
+                            let num_records = self.parser.num_records().unwrap_or_default();
+                            self.dropped.extend(self.record_index..num_records);
+                            self.done = true;
+                        }
+                    }
+                },
+            }
+        }
+
+        None
+    }
+}
+
 pub struct Parser<R> {
     bytes_read: usize,
     reader: R,
+    recovery: Recovery,
+    deleted_records: DeletedRecords,
+    encoding: Option<Encoding>,
+    fallback_encoding: Encoding,
+    warnings: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+    index: Option<Vec<ShxRecord>>,
+    num_records: Option<usize>,
 }
 
 impl<R> Parser<R> {
     pub fn num_bytes_read(&self) -> usize {
         self.bytes_read
     }
+
+    pub fn with_recovery(mut self, recovery: Recovery) -> Self {
+        self.recovery = recovery;
+        self
+    }
+
+    pub fn with_deleted_records(mut self, deleted_records: DeletedRecords) -> Self {
+        self.deleted_records = deleted_records;
+        self
+    }
+
+    pub(crate) fn deleted_records(&self) -> DeletedRecords {
+        self.deleted_records
+    }
+
+    /// Decode `.dbf` text with this encoding, regardless of what the file says.
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    pub(crate) fn encoding(&self) -> Option<Encoding> {
+        self.encoding
+    }
+
+    /// Decode `.dbf` text with the encoding named in a `.cpg` file.
+    /// An unknown name gives a warning instead, and the text is decoded as the `.dbf` says,
+    /// or as Latin-1 which can decode any byte.
+    pub fn with_cpg(mut self, cpg: &str) -> Self {
+        match cpg.parse() {
+            Ok(encoding) => self.with_encoding(encoding),
+            Err(e) => {
+                self.warnings.push(format!("Ignoring the .cpg file: {e}"));
+                self.fallback_encoding = Encoding::Latin1;
+                self
+            }
+        }
+    }
+
+    /// Used when neither [`Parser::with_encoding`] nor the `.dbf` gives an encoding.
+    pub(crate) fn fallback_encoding(&self) -> Encoding {
+        self.fallback_encoding
+    }
+
+    /// Problems which did not stop parsing, e.g. an unknown `.cpg` encoding.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Adds the warning unless it was given already, for problems which may repeat for every record.
+    pub(crate) fn warn_once(&mut self, warning: String) {
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    pub(crate) fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Problems found so far, see [`Recovery::Lenient`].
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// With [`Recovery::Lenient`], the offsets in the index are used to find the next record
+    /// when a record header is unusable.
+    pub fn with_index(mut self, shx: &ShxFile) -> Self {
+        self.index = Some(shx.records.clone());
+        self
+    }
+
+    /// The number of records the `.shp` file should have, e.g. from the `.dbf`.
+    /// With [`Recovery::Lenient`], all the records up to this count are dropped
+    /// if the rest of the file can't be parsed.
+    /// Taken from the index if not given, see [`Parser::with_index`].
+    pub fn with_num_records(mut self, num_records: usize) -> Self {
+        self.num_records = Some(num_records);
+        self
+    }
+
+    fn num_records(&self) -> Option<usize> {
+        self.num_records
+            .or_else(|| self.index.as_ref().map(|index| index.len()))
+    }
 }
 
 impl Parser<BufReader<File>> {
@@ -57,24 +370,196 @@ impl Parser<BufReader<File>> {
         Ok(Self::with_reader(BufReader::new(f)))
     }
 
+    /// Opens a `.dbf` file.
+    /// If there is a `.cpg` file next to it, its encoding is used for the text.
+    pub fn new_dbf<P: AsRef<Path>>(dbf_path: P) -> Result<Self> {
+        let parser = Self::new(dbf_path.as_ref())?;
+
+        match std::fs::read_to_string(dbf_path.as_ref().with_extension("cpg")) {
+            Ok(cpg) => Ok(parser.with_cpg(&cpg)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(parser),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     pub fn parse_shp_file<P: AsRef<Path>>(shp_path: P) -> Result<ShpFile> {
-        let parser = Self::new(shp_path)?;
-        parser.impl_parse_shp()
+        Self::parse_shp_file_with(shp_path, Recovery::Strict)
+    }
+
+    /// With [`Recovery::Lenient`] the `.shx` next to the `.shp` is used as the index,
+    /// if there is one which can be parsed, see [`Parser::with_index`].
+    pub fn parse_shp_file_with<P: AsRef<Path>>(shp_path: P, recovery: Recovery) -> Result<ShpFile> {
+        Self::new_shp(shp_path, recovery)?.impl_parse_shp()
+    }
+
+    /// Opens a `.shp` file, see [`Parser::parse_shp_file_with`].
+    pub(crate) fn new_shp<P: AsRef<Path>>(shp_path: P, recovery: Recovery) -> Result<Self> {
+        let parser = Self::new(shp_path.as_ref())?.with_recovery(recovery);
+
+        if recovery == Recovery::Strict {
+            return Ok(parser);
+        }
+
+        match Self::parse_shx_file(shp_path.as_ref().with_extension("shx")) {
+            Ok(shx) => Ok(parser.with_index(&shx)),
+            // Only needed to skip unusable records, so parsing can go on without it
+            Err(_) => Ok(parser),
+        }
     }
 
     pub fn parse_dbf_file<P: AsRef<Path>>(dbf_path: P) -> Result<DbaseFile> {
-        let parser = Self::new(dbf_path)?;
-        parser.impl_parse_dbase_file()
+        Self::parse_dbf_file_with(dbf_path, DeletedRecords::default())
+    }
+
+    /// Memo fields are replaced by their text if there is a memo file next to the `.dbf`,
+    /// see [`MemoFile::resolve`].
+    pub fn parse_dbf_file_with<P: AsRef<Path>>(
+        dbf_path: P,
+        deleted_records: DeletedRecords,
+    ) -> Result<DbaseFile> {
+        let parser = Self::new_dbf(dbf_path.as_ref())?.with_deleted_records(deleted_records);
+        let mut dbf = parser.parse_dbase_file()?;
+
+        let has_memos = dbf
+            .header
+            .fields
+            .iter()
+            .any(|field| matches!(field.type_, FieldType::Memo));
+
+        if let Some(memo_path) = MemoFile::find_next_to(dbf_path).filter(|_| has_memos) {
+            let mut memo = MemoFile::open(memo_path)?;
+            for record in &mut dbf.records {
+                memo.resolve(&dbf.header, record)?;
+            }
+        }
+
+        Ok(dbf)
+    }
+
+    pub fn parse_shx_file<P: AsRef<Path>>(shx_path: P) -> Result<ShxFile> {
+        let parser = Self::new(shx_path)?;
+        parser.impl_parse_shx()
+    }
+
+    /// Memory maps the `.shp` file then parses it on `threads` threads,
+    /// see [`Parser::parse_shp_buffer_parallel`].
+    pub fn parse_shp_file_parallel<P: AsRef<Path>>(
+        shp_path: P,
+        shx_path: P,
+        threads: usize,
+    ) -> Result<ShpFile> {
+        let shx = Self::parse_shx_file(shx_path)?;
+        let shp = ShpMmap::open(shp_path)?;
+
+        Parser::parse_shp_buffer_parallel(&shp, &shx, threads)
     }
 }
 
 impl<'b> Parser<&'b [u8]> {
     pub fn parse_shp_buffer(buf: &'b [u8]) -> Result<ShpFile> {
-        Self::with_reader(buf).impl_parse_shp()
+        Self::parse_shp_buffer_with(buf, Recovery::Strict)
+    }
+
+    pub fn parse_shp_buffer_with(buf: &'b [u8], recovery: Recovery) -> Result<ShpFile> {
+        Self::with_reader(buf)
+            .with_recovery(recovery)
+            .impl_parse_shp()
+    }
+
+    /// Uses the index to split the records into one contiguous range per thread.
+    /// Each thread parses its records straight from the shared buffer,
+    /// and the ranges are joined in order afterwards.
+    ///
+    /// The records in the index must be in order without overlapping,
+    /// and within the length of the file given in its header, as an outdated index may not be.
+    pub fn parse_shp_buffer_parallel(
+        buf: &'b [u8],
+        shx: &ShxFile,
+        threads: usize,
+    ) -> Result<ShpFile> {
+        let mut parser = Self::with_reader(buf);
+        let header = parser.parse_header()?;
+
+        let file_bytes = header.file_length.num_bytes();
+        let mut previous_end = parser.num_bytes_read();
+        for (index, entry) in shx.records.iter().enumerate() {
+            let offset = entry.offset.num_bytes();
+            let end = offset + entry.num_bytes();
+            if offset < previous_end {
+                return Err(Error::UnexpectedData(format!(
+                    "Index entry {index} at byte offset {offset} overlaps the record before it"
+                )));
+            }
+            if end > file_bytes {
+                return Err(Error::UnexpectedData(format!(
+                    "Index entry {index} ends at byte offset {end}, past the end of the file at {file_bytes}"
+                )));
+            }
+            previous_end = end;
+        }
+
+        let chunk_size = shx.records.len().div_ceil(threads.max(1)).max(1);
+
+        let chunks: Vec<Result<Vec<ShpRecord>>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = shx
+                .records
+                .chunks(chunk_size)
+                .map(|entries| {
+                    scope.spawn(move || {
+                        entries
+                            .iter()
+                            .map(|entry| {
+                                let offset = entry.offset.num_bytes();
+                                let start = offset + ShxRecord::RECORD_HEADER_BYTES;
+                                let content_bytes = entry.content_length.num_bytes();
+                                let record = buf.get(offset..start + content_bytes).ok_or(
+                                    Error::Truncated {
+                                        offset: start,
+                                        needed: content_bytes,
+                                    },
+                                )?;
+
+                                Parser::with_reader_at(record, offset).parse_record()
+                            })
+                            .collect()
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("parser thread panicked"))
+                .collect()
+        });
+
+        let mut records = Vec::with_capacity(shx.records.len());
+        for chunk in chunks {
+            records.extend(chunk?);
+        }
+
+        Ok(ShpFile {
+            header,
+            records,
+            dropped: vec![],
+            diagnostics: vec![],
+        })
     }
 
     pub fn parse_dbf_buffer(buf: &'b [u8]) -> Result<DbaseFile> {
-        Self::with_reader(buf).impl_parse_dbase_file()
+        Self::parse_dbf_buffer_with(buf, DeletedRecords::default())
+    }
+
+    pub fn parse_dbf_buffer_with(
+        buf: &'b [u8],
+        deleted_records: DeletedRecords,
+    ) -> Result<DbaseFile> {
+        Self::with_reader(buf)
+            .with_deleted_records(deleted_records)
+            .parse_dbase_file()
+    }
+
+    pub fn parse_shx_buffer(buf: &'b [u8]) -> Result<ShxFile> {
+        Self::with_reader(buf).impl_parse_shx()
     }
 }
 
@@ -86,27 +571,175 @@ where
         Self {
             bytes_read: 0,
             reader,
+            recovery: Recovery::default(),
+            deleted_records: DeletedRecords::default(),
+            encoding: None,
+            fallback_encoding: Encoding::default(),
+            warnings: vec![],
+            diagnostics: vec![],
+            index: None,
+            num_records: None,
         }
     }
 
-    fn impl_parse_shp(mut self) -> Result<ShpFile> {
+    /// For readers which start somewhere within a file,
+    /// so that byte offsets in errors are still from the start of the file.
+    pub(crate) fn with_reader_at(reader: R, offset: usize) -> Self {
+        Self {
+            bytes_read: offset,
+            ..Self::with_reader(reader)
+        }
+    }
+
+    /// Parses the `.shp` header, then returns an iterator which parses one record at a time.
+    /// Must be called at the start of the file.
+    ///
+    /// With [`Recovery::Lenient`] corrupt records are skipped and diagnostics are collected
+    /// in the parser as the iterator goes.
+    pub fn shp_records(&mut self) -> Result<ShpRecords<'_, R>> {
         let header = self.parse_header()?;
 
-        let mut records = vec![];
+        Ok(ShpRecords {
+            parser: self,
+            header,
+            record_index: 0,
+            dropped: vec![],
+            done: false,
+        })
+    }
+
+    pub(crate) fn impl_parse_shp(mut self) -> Result<ShpFile> {
+        let mut iter = self.shp_records()?;
+        let records = iter.by_ref().collect::<Result<Vec<_>>>()?;
+        let ShpRecords {
+            header, dropped, ..
+        } = iter;
+
+        Ok(ShpFile {
+            header,
+            records,
+            dropped,
+            diagnostics: self.diagnostics,
+        })
+    }
+
+    /// Reads the whole record as given by its content length before parsing the shape in it.
+    /// If the shape is corrupt the record is skipped and a diagnostic is collected,
+    /// and the next record can be parsed as usual.
+    ///
+    /// Errors if the record header is unusable, the record goes past `file_end`
+    /// or the file ends, since then the next record can't be found.
+    fn parse_record_lenient(
+        &mut self,
+        record_index: usize,
+        file_end: usize,
+    ) -> std::result::Result<Option<ShpRecord>, Diagnostic> {
+        let record_offset = self.num_bytes_read();
+        let diagnostic = |record_number, error: Error| Diagnostic {
+            record_index,
+            record_number,
+            offset: record_offset,
+            problem: error.to_string(),
+        };
+
+        let record_header = self
+            .parse_record_header()
+            .and_then(|header| {
+                self.check_content_length(&header, record_offset)
+                    .map(|_| header)
+            })
+            .map_err(|e| diagnostic(None, e))?;
+        let record_number = Some(record_header.record_number);
+
+        let content_bytes = record_header.content_length.num_bytes();
+        if self.num_bytes_read() + content_bytes > file_end {
+            return Err(diagnostic(
+                record_number,
+                Error::BadLength {
+                    offset: record_offset,
+                    length: record_header.content_length.0,
+                },
+            ));
+        }
+
+        // The content length may be garbage, so memory is only used for bytes which are there
+        let content = self
+            .read_up_to(content_bytes)
+            .map_err(|e| diagnostic(record_number, e))?;
+
+        let mut content_parser =
+            Parser::with_reader_at(&content[..], self.num_bytes_read() - content.len());
 
-        let goal = header.file_length.num_bytes();
+        match content_parser.parse_record_content(&record_header, record_offset) {
+            Ok(record) => Ok(Some(record)),
+            Err(e) => {
+                self.diagnostics.push(diagnostic(record_number, e));
+                Ok(None)
+            }
+        }
+    }
 
-        while self.num_bytes_read() < goal {
-            records.push(self.parse_record()?);
+    /// Skips ahead to the record at `record_index` as given by the index, if there is one.
+    /// False if it is not ahead of where parsing is, or not before `file_end`.
+    fn skip_to_indexed(&mut self, record_index: usize, file_end: usize) -> bool {
+        let Some(entry) = self
+            .index
+            .as_ref()
+            .and_then(|index| index.get(record_index))
+        else {
+            return false;
+        };
+        let offset = entry.offset.num_bytes();
+        let num_bytes_read = self.num_bytes_read();
+        if !(num_bytes_read..file_end).contains(&offset) {
+            return false;
         }
 
-        assert_eq!(self.num_bytes_read(), goal);
+        let skip = offset - num_bytes_read;
+        let skipped = io::copy(&mut self.reader.by_ref().take(skip as u64), &mut io::sink());
+        self.bytes_read += skipped.as_ref().map_or(0, |skipped| *skipped as usize);
 
-        Ok(ShpFile { header, records })
+        self.bytes_read == offset
+    }
+
+    /// Like [`Parser::read_exact`], but the buffer only grows as bytes are read.
+    fn read_up_to(&mut self, num_bytes: usize) -> Result<Vec<u8>> {
+        let offset = self.bytes_read;
+        let mut buf = vec![];
+        self.reader
+            .by_ref()
+            .take(num_bytes as u64)
+            .read_to_end(&mut buf)?;
+        self.bytes_read += buf.len();
+
+        if buf.len() < num_bytes {
+            return Err(Error::Truncated {
+                offset,
+                needed: num_bytes,
+            });
+        }
+
+        Ok(buf)
+    }
+
+    /// Checks that parsing ended exactly where the file says it should.
+    pub(crate) fn expect_bytes_read(&self, expected: usize) -> Result<()> {
+        let actual = self.num_bytes_read();
+        if actual != expected {
+            return Err(Error::FileLengthMismatch { expected, actual });
+        }
+
+        Ok(())
     }
 
     pub fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
-        self.reader.read_exact(buf)?;
+        self.reader.read_exact(buf).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => Error::Truncated {
+                offset: self.bytes_read,
+                needed: buf.len(),
+            },
+            _ => e.into(),
+        })?;
         self.bytes_read += buf.len();
 
         Ok(())
@@ -158,6 +791,24 @@ where
         Ok(f64::from_le_bytes(self.consume_8()?))
     }
 
+    /// A min followed by a max double.
+    fn parse_range(&mut self) -> Result<Range<Double>> {
+        let min = self.parse_double()?;
+        let max = self.parse_double()?;
+
+        Ok(min..max)
+    }
+
+    fn parse_doubles(&mut self, num: usize) -> Result<Vec<Double>> {
+        let mut doubles = Vec::with_capacity(num);
+
+        for _ in 0..num {
+            doubles.push(self.parse_double()?);
+        }
+
+        Ok(doubles)
+    }
+
     fn parse_point(&mut self) -> Result<shape::Point> {
         Ok(Point {
             x: self.parse_double()?,
@@ -199,7 +850,7 @@ where
         Ok(i32::from_le_bytes(self.consume_4()?))
     }
 
-    fn parse_length(&mut self) -> Result<ShpLength> {
+    pub(crate) fn parse_length(&mut self) -> Result<ShpLength> {
         self.parse_i32_be().map(ShpLength)
     }
 
@@ -225,10 +876,8 @@ where
         let version = self.parse_integer()?;
         let shape_type = self.parse_shape_type()?;
         let mbr = self.parse_mbr()?;
-        let min_z = self.parse_double()?;
-        let max_z = self.parse_double()?;
-        let min_m = self.parse_double()?;
-        let max_m = self.parse_double()?;
+        let z_range = self.parse_range()?;
+        let m_range = self.parse_range()?;
 
         let shp_header = ShpHeader {
             file_code,
@@ -236,8 +885,8 @@ where
             version,
             shape_type,
             mbr,
-            z_range: min_z..max_z,
-            m_range: min_m..max_m,
+            z_range,
+            m_range,
         };
 
         Ok(shp_header)
@@ -254,55 +903,323 @@ where
         Shape::Null
     }
 
-    fn parse_polygon(&mut self) -> Result<Shape> {
-        let mbr = self.parse_mbr()?;
-
-        let num_parts = self.parse_integer()?;
-        let num_points = self.parse_integer()?;
+    /// Parses a number of parts, points etc. in a record.
+    /// The count is checked against how many elements of the given size the rest of the record
+    /// has room for, to not trust corrupt counts with allocations.
+    fn parse_count(&mut self, record_end: usize, element_bytes: usize) -> Result<usize> {
+        let offset = self.num_bytes_read();
+        let count = self.parse_integer()?;
+
+        let room = record_end.saturating_sub(self.num_bytes_read()) / element_bytes;
+        match usize::try_from(count) {
+            Ok(num) if num <= room => Ok(num),
+            _ => Err(Error::BadCount { offset, count }),
+        }
+    }
 
-        let mut parts = Vec::with_capacity(4 * num_parts as usize);
+    /// Part indices must be increasing and point into the points.
+    fn parse_parts(&mut self, num_parts: usize, num_points: usize) -> Result<Vec<i32>> {
+        let offset = self.num_bytes_read();
+        let mut parts = Vec::with_capacity(num_parts);
 
         for _ in 0..num_parts {
             let part_idx = self.parse_integer()?;
             parts.push(part_idx);
         }
 
-        let mut points = Vec::with_capacity(size_of::<Point>() * num_points as usize);
+        let in_bounds = parts
+            .iter()
+            .all(|part| usize::try_from(*part).is_ok_and(|part| part <= num_points));
+        let increasing = parts.windows(2).all(|w| w[0] <= w[1]);
+        if !in_bounds || !increasing {
+            return Err(Error::BadParts { offset });
+        }
+
+        Ok(parts)
+    }
+
+    fn parse_points(&mut self, num_points: usize) -> Result<Vec<Point>> {
+        let mut points = Vec::with_capacity(num_points);
+
         for _ in 0..num_points {
             let point = self.parse_point()?;
             points.push(point);
         }
 
+        Ok(points)
+    }
+
+    fn parse_polygon(&mut self, record_end: usize) -> Result<Shape> {
+        let mbr = self.parse_mbr()?;
+
+        let num_parts = self.parse_count(record_end, size_of::<Integer>())?;
+        let num_points = self.parse_count(record_end, size_of::<Point>())?;
+
+        let parts = self.parse_parts(num_parts, num_points)?;
+        let points = self.parse_points(num_points)?;
+
         Ok(Shape::Polygon(Polygon { parts, points, mbr }))
     }
 
-    fn parse_polyline(&mut self) -> Result<Shape> {
+    fn parse_polyline(&mut self, record_end: usize) -> Result<Shape> {
         let mbr = self.parse_mbr()?;
 
-        let num_parts = self.parse_integer()?;
-        let num_points = self.parse_integer()?;
+        let num_parts = self.parse_count(record_end, size_of::<Integer>())?;
+        let num_points = self.parse_count(record_end, size_of::<Point>())?;
 
-        let mut parts = Vec::with_capacity(4 * num_parts as usize);
+        let parts = self.parse_parts(num_parts, num_points)?;
+        let points = self.parse_points(num_points)?;
 
-        for _ in 0..num_parts {
-            let part_idx = self.parse_integer()?;
-            parts.push(part_idx);
-        }
+        Ok(Shape::PolyLine(PolyLine { mbr, parts, points }))
+    }
+
+    fn parse_multipoint(&mut self, record_end: usize) -> Result<Shape> {
+        let mbr = self.parse_mbr()?;
+
+        let num_points = self.parse_count(record_end, size_of::<Point>())?;
+        let points = self.parse_points(num_points)?;
+
+        Ok(Shape::MultiPoint(MultiPoint { mbr, points }))
+    }
+
+    /// Z shapes may or may not end with M values.
+    /// If the record has bytes left after the Z values, they are there.
+    fn has_measures(&self, record_end: usize) -> bool {
+        self.num_bytes_read() < record_end
+    }
 
-        let mut points = Vec::with_capacity(size_of::<Point>() * num_points as usize);
+    fn parse_measure(&mut self) -> Result<Measure> {
+        Ok(shape::measure(self.parse_double()?))
+    }
+
+    /// Parses an M range followed by `num_points` M values.
+    fn parse_measures(&mut self, num_points: usize) -> Result<Measures> {
+        let range = self.parse_range()?;
+        let range = (shape::measure(range.start).is_some() && shape::measure(range.end).is_some())
+            .then_some(range);
+
+        let mut values = Vec::with_capacity(num_points);
         for _ in 0..num_points {
-            let point = self.parse_point()?;
-            points.push(point);
+            values.push(self.parse_measure()?);
         }
 
-        Ok(Shape::PolyLine(PolyLine { mbr, parts, points }))
+        Ok(Measures { range, values })
+    }
+
+    /// Parses an optional M range followed by `num_points` M values.
+    fn parse_optional_measures(
+        &mut self,
+        num_points: usize,
+        record_end: usize,
+    ) -> Result<Option<Measures>> {
+        if !self.has_measures(record_end) {
+            return Ok(None);
+        }
+
+        self.parse_measures(num_points).map(Some)
+    }
+
+    fn parse_point_z(&mut self, record_end: usize) -> Result<Shape> {
+        let Point { x, y } = self.parse_point()?;
+        let z = self.parse_double()?;
+        let m = if self.has_measures(record_end) {
+            self.parse_measure()?
+        } else {
+            None
+        };
+
+        Ok(Shape::PointZ(PointZ { x, y, z, m }))
+    }
+
+    fn parse_polyline_z(&mut self, record_end: usize) -> Result<Shape> {
+        let mbr = self.parse_mbr()?;
+
+        let num_parts = self.parse_count(record_end, size_of::<Integer>())?;
+        let num_points = self.parse_count(record_end, size_of::<Point>())?;
+
+        let parts = self.parse_parts(num_parts, num_points)?;
+        let points = self.parse_points(num_points)?;
+
+        let z_range = self.parse_range()?;
+        let z = self.parse_doubles(num_points)?;
+
+        let m = self.parse_optional_measures(num_points, record_end)?;
+
+        Ok(Shape::PolylineZ(PolylineZ {
+            mbr,
+            parts,
+            points,
+            z_range,
+            z,
+            m,
+        }))
+    }
+
+    fn parse_polygon_z(&mut self, record_end: usize) -> Result<Shape> {
+        let mbr = self.parse_mbr()?;
+
+        let num_parts = self.parse_count(record_end, size_of::<Integer>())?;
+        let num_points = self.parse_count(record_end, size_of::<Point>())?;
+
+        let parts = self.parse_parts(num_parts, num_points)?;
+        let points = self.parse_points(num_points)?;
+
+        let z_range = self.parse_range()?;
+        let z = self.parse_doubles(num_points)?;
+
+        let m = self.parse_optional_measures(num_points, record_end)?;
+
+        Ok(Shape::PolygonZ(PolygonZ {
+            mbr,
+            parts,
+            points,
+            z_range,
+            z,
+            m,
+        }))
+    }
+
+    fn parse_multipoint_z(&mut self, record_end: usize) -> Result<Shape> {
+        let mbr = self.parse_mbr()?;
+
+        let num_points = self.parse_count(record_end, size_of::<Point>())?;
+        let points = self.parse_points(num_points)?;
+
+        let z_range = self.parse_range()?;
+        let z = self.parse_doubles(num_points)?;
+
+        let m = self.parse_optional_measures(num_points, record_end)?;
+
+        Ok(Shape::MultiPointZ(MultiPointZ {
+            mbr,
+            points,
+            z_range,
+            z,
+            m,
+        }))
+    }
+
+    fn parse_point_m(&mut self) -> Result<Shape> {
+        let Point { x, y } = self.parse_point()?;
+        let m = self.parse_measure()?;
+
+        Ok(Shape::PointM(PointM { x, y, m }))
+    }
+
+    fn parse_polyline_m(&mut self, record_end: usize) -> Result<Shape> {
+        let mbr = self.parse_mbr()?;
+
+        let num_parts = self.parse_count(record_end, size_of::<Integer>())?;
+        let num_points = self.parse_count(record_end, size_of::<Point>())?;
+
+        let parts = self.parse_parts(num_parts, num_points)?;
+        let points = self.parse_points(num_points)?;
+        let m = self.parse_measures(num_points)?;
+
+        Ok(Shape::PolylineM(PolylineM {
+            mbr,
+            parts,
+            points,
+            m,
+        }))
+    }
+
+    fn parse_polygon_m(&mut self, record_end: usize) -> Result<Shape> {
+        let mbr = self.parse_mbr()?;
+
+        let num_parts = self.parse_count(record_end, size_of::<Integer>())?;
+        let num_points = self.parse_count(record_end, size_of::<Point>())?;
+
+        let parts = self.parse_parts(num_parts, num_points)?;
+        let points = self.parse_points(num_points)?;
+        let m = self.parse_measures(num_points)?;
+
+        Ok(Shape::PolygonM(PolygonM {
+            mbr,
+            parts,
+            points,
+            m,
+        }))
+    }
+
+    fn parse_multipoint_m(&mut self, record_end: usize) -> Result<Shape> {
+        let mbr = self.parse_mbr()?;
+
+        let num_points = self.parse_count(record_end, size_of::<Point>())?;
+        let points = self.parse_points(num_points)?;
+        let m = self.parse_measures(num_points)?;
+
+        Ok(Shape::MultiPointM(MultiPointM { mbr, points, m }))
+    }
+
+    fn parse_patch_types(&mut self, num_parts: usize) -> Result<Vec<PatchType>> {
+        let mut part_types = Vec::with_capacity(num_parts);
+
+        for _ in 0..num_parts {
+            part_types.push(self.parse_integer()?.try_into()?);
+        }
+
+        Ok(part_types)
+    }
+
+    fn parse_multipatch(&mut self, record_end: usize) -> Result<Shape> {
+        let mbr = self.parse_mbr()?;
+
+        let num_parts = self.parse_count(record_end, size_of::<Integer>())?;
+        let num_points = self.parse_count(record_end, size_of::<Point>())?;
+
+        let parts = self.parse_parts(num_parts, num_points)?;
+        let part_types = self.parse_patch_types(num_parts)?;
+        let points = self.parse_points(num_points)?;
+
+        let z_range = self.parse_range()?;
+        let z = self.parse_doubles(num_points)?;
+
+        let m = self.parse_optional_measures(num_points, record_end)?;
+
+        Ok(Shape::MultiPatch(MultiPatch {
+            mbr,
+            parts,
+            part_types,
+            points,
+            z_range,
+            z,
+            m,
+        }))
     }
 
     pub fn parse_record(&mut self) -> Result<ShpRecord> {
+        let record_offset = self.num_bytes_read();
         let record_header = self.parse_record_header()?;
+        self.check_content_length(&record_header, record_offset)?;
 
+        self.parse_record_content(&record_header, record_offset)
+    }
+
+    fn check_content_length(
+        &self,
+        record_header: &ShpRecordHeader,
+        record_offset: usize,
+    ) -> Result<()> {
+        if record_header.content_length.0 < 0 {
+            return Err(Error::BadLength {
+                offset: record_offset,
+                length: record_header.content_length.0,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Parses the shape following a record header.
+    fn parse_record_content(
+        &mut self,
+        record_header: &ShpRecordHeader,
+        record_offset: usize,
+    ) -> Result<ShpRecord> {
         let num_bytes_parsed_before_record = self.num_bytes_read();
         let num_bytes_required_for_record = record_header.content_length.num_bytes();
+        let record_end = num_bytes_parsed_before_record + num_bytes_required_for_record;
 
         // Shape always comes first
         let shape_type = self.parse_shape_type()?;
@@ -310,22 +1227,29 @@ where
         let shape = match shape_type {
             ShapeType::Null => self.parse_null(),
             ShapeType::Point => shape::Shape::Point(self.parse_point()?),
-            ShapeType::PolyLine => self.parse_polyline()?,
-            ShapeType::Polygon => self.parse_polygon()?,
-            ShapeType::MultiPoint => unimplemented!(),
-            ShapeType::PointZ => unimplemented!(),
-            ShapeType::PolylineZ => unimplemented!(),
-            ShapeType::PolygonZ => unimplemented!(),
-            ShapeType::MultiPointZ => unimplemented!(),
-            ShapeType::PointM => unimplemented!(),
-            ShapeType::PolylineM => unimplemented!(),
-            ShapeType::PolygonM => unimplemented!(),
-            ShapeType::MultiPointM => unimplemented!(),
-            ShapeType::MultiPatch => unimplemented!(),
+            ShapeType::PolyLine => self.parse_polyline(record_end)?,
+            ShapeType::Polygon => self.parse_polygon(record_end)?,
+            ShapeType::MultiPoint => self.parse_multipoint(record_end)?,
+            ShapeType::PointZ => self.parse_point_z(record_end)?,
+            ShapeType::PolylineZ => self.parse_polyline_z(record_end)?,
+            ShapeType::PolygonZ => self.parse_polygon_z(record_end)?,
+            ShapeType::MultiPointZ => self.parse_multipoint_z(record_end)?,
+            ShapeType::PointM => self.parse_point_m()?,
+            ShapeType::PolylineM => self.parse_polyline_m(record_end)?,
+            ShapeType::PolygonM => self.parse_polygon_m(record_end)?,
+            ShapeType::MultiPointM => self.parse_multipoint_m(record_end)?,
+            ShapeType::MultiPatch => self.parse_multipatch(record_end)?,
         };
 
         let read_for_record = self.num_bytes_read() - num_bytes_parsed_before_record;
-        assert_eq!(num_bytes_required_for_record, read_for_record);
+        if read_for_record != num_bytes_required_for_record {
+            return Err(Error::RecordLengthMismatch {
+                record_number: record_header.record_number,
+                offset: record_offset,
+                expected: num_bytes_required_for_record,
+                actual: read_for_record,
+            });
+        }
 
         Ok(ShpRecord { shape })
     }
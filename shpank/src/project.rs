@@ -0,0 +1,461 @@
+//! Reprojection of coordinates between the systems used for Norwegian data:
+//! Longitudes and latitudes, Web Mercator, Transverse Mercator (UTM),
+//! and a local east/north/up plane for rendering.
+//!
+//! Transverse Mercator uses the Krüger series to third order, which is accurate
+//! to about a millimetre within a UTM zone.
+//! See https://en.wikipedia.org/wiki/Universal_Transverse_Mercator_coordinate_system#Simplified_formulae
+
+use std::{f64::consts::FRAC_PI_4, str::FromStr};
+
+use crate::{
+    crs::Crs,
+    parse::{Error, Result},
+    shape::{MinimumBoundingRectangle, Point, Shape},
+};
+
+/// The shape of the earth, as used by a datum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spheroid {
+    /// In metres
+    pub semi_major_axis: f64,
+    pub flattening: f64,
+}
+
+impl Spheroid {
+    pub const WGS84: Self = Self {
+        semi_major_axis: 6_378_137.,
+        flattening: 1. / 298.257223563,
+    };
+
+    /// Used by ETRS89 (EUREF89), which Norwegian mapping agencies use
+    pub const GRS80: Self = Self {
+        semi_major_axis: 6_378_137.,
+        flattening: 1. / 298.257222101,
+    };
+
+    /// First eccentricity squared
+    pub(crate) fn e2(&self) -> f64 {
+        self.flattening * (2. - self.flattening)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoordinateSystem {
+    /// Longitude and latitude in degrees, on WGS84 or ETRS89 (which differ by less than a metre)
+    Geographic,
+
+    /// EPSG:3857, in metres
+    WebMercator,
+
+    /// In metres, e.g. a UTM zone, see [`CoordinateSystem::utm`]
+    TransverseMercator {
+        spheroid: Spheroid,
+
+        /// In degrees
+        central_meridian: f64,
+        scale_factor: f64,
+        false_easting: f64,
+        false_northing: f64,
+    },
+
+    /// East, north and up in metres, from a tangent plane touching the earth at the origin.
+    /// Keeps distances and angles close to the origin, which makes it a good fit for rendering.
+    LocalEnu {
+        /// Longitude and latitude in degrees, and height in metres
+        origin: [f64; 3],
+    },
+}
+
+impl CoordinateSystem {
+    /// UTM zone on WGS84 or GRS80.
+    pub fn utm(zone: u8, north: bool, spheroid: Spheroid) -> Self {
+        Self::TransverseMercator {
+            spheroid,
+            // Zone 1 is centred on 177°W, with 6° per zone
+            central_meridian: zone as f64 * 6. - 183.,
+            scale_factor: 0.9996,
+            false_easting: 500_000.,
+            false_northing: if north { 0. } else { 10_000_000. },
+        }
+    }
+
+    /// For 4326, 3857 and the northern UTM zones on WGS84 (326xx) and ETRS89 (258xx).
+    pub fn from_epsg(code: u32) -> Option<Self> {
+        Some(match code {
+            4326 | 4258 => Self::Geographic,
+            3857 => Self::WebMercator,
+            32601..=32660 => Self::utm((code - 32600) as u8, true, Spheroid::WGS84),
+            32701..=32760 => Self::utm((code - 32700) as u8, false, Spheroid::WGS84),
+            25828..=25838 => Self::utm((code - 25800) as u8, true, Spheroid::GRS80),
+            _ => return None,
+        })
+    }
+
+    /// The system of a `.prj` file.
+    /// Transverse Mercator is supported with any parameters, the others only if identified.
+    pub fn from_crs(crs: &Crs) -> Option<Self> {
+        if let Some(system) = crs.epsg.and_then(Self::from_epsg) {
+            return Some(system);
+        }
+
+        let projection = crs.projection.as_ref()?;
+        let transverse_mercator = projection
+            .method
+            .eq_ignore_ascii_case("Transverse_Mercator");
+        let degrees = (crs.angular_unit.factor - 1f64.to_radians()).abs() < 1e-12;
+        if !transverse_mercator || !degrees || projection.linear_unit.factor != 1. {
+            return None;
+        }
+
+        let ellipsoid = &crs.datum.ellipsoid;
+        Some(Self::TransverseMercator {
+            spheroid: Spheroid {
+                semi_major_axis: ellipsoid.semi_major_axis,
+                flattening: ellipsoid.flattening(),
+            },
+            central_meridian: projection.parameter("central meridian")? + crs.prime_meridian,
+            scale_factor: projection.parameter("scale factor")?,
+            false_easting: projection.parameter("false easting")?,
+            false_northing: projection.parameter("false northing")?,
+        })
+    }
+
+    /// From longitude and latitude in degrees and height in metres.
+    pub fn project(&self, [lon, lat, height]: [f64; 3]) -> [f64; 3] {
+        match *self {
+            Self::Geographic => [lon, lat, height],
+            Self::WebMercator => {
+                let radius = Spheroid::WGS84.semi_major_axis;
+                let lat = lat.clamp(-WEB_MERCATOR_MAX_LATITUDE, WEB_MERCATOR_MAX_LATITUDE);
+                [
+                    radius * lon.to_radians(),
+                    radius * (FRAC_PI_4 + lat.to_radians() / 2.).tan().ln(),
+                    height,
+                ]
+            }
+            Self::TransverseMercator {
+                spheroid,
+                central_meridian,
+                scale_factor,
+                false_easting,
+                false_northing,
+            } => {
+                let series = Kruger::new(spheroid);
+                let (lat, lon) = (lat.to_radians(), (lon - central_meridian).to_radians());
+                let e = spheroid.e2().sqrt();
+
+                let t = (lat.sin().atanh() - e * (e * lat.sin()).atanh()).sinh();
+                let xi = (t / lon.cos()).atan();
+                let eta = (lon.sin() / (1. + t * t).sqrt()).atanh();
+
+                let (mut x, mut y) = (eta, xi);
+                for (j, alpha) in (1..).zip(series.alpha) {
+                    let j = 2. * j as f64;
+                    x += alpha * (j * xi).cos() * (j * eta).sinh();
+                    y += alpha * (j * xi).sin() * (j * eta).cosh();
+                }
+
+                let k = scale_factor * series.a;
+                [false_easting + k * x, false_northing + k * y, height]
+            }
+            Self::LocalEnu { origin } => {
+                let [x, y, z] = ecef([lon, lat, height]);
+                let [x0, y0, z0] = ecef(origin);
+                let (dx, dy, dz) = (x - x0, y - y0, z - z0);
+
+                let (sin_lat, cos_lat) = origin[1].to_radians().sin_cos();
+                let (sin_lon, cos_lon) = origin[0].to_radians().sin_cos();
+                [
+                    -sin_lon * dx + cos_lon * dy,
+                    -sin_lat * cos_lon * dx - sin_lat * sin_lon * dy + cos_lat * dz,
+                    cos_lat * cos_lon * dx + cos_lat * sin_lon * dy + sin_lat * dz,
+                ]
+            }
+        }
+    }
+
+    /// To longitude and latitude in degrees and height in metres.
+    pub fn unproject(&self, [x, y, z]: [f64; 3]) -> [f64; 3] {
+        match *self {
+            Self::Geographic => [x, y, z],
+            Self::WebMercator => {
+                let radius = Spheroid::WGS84.semi_major_axis;
+                [
+                    (x / radius).to_degrees(),
+                    (2. * (y / radius).exp().atan() - 2. * FRAC_PI_4).to_degrees(),
+                    z,
+                ]
+            }
+            Self::TransverseMercator {
+                spheroid,
+                central_meridian,
+                scale_factor,
+                false_easting,
+                false_northing,
+            } => {
+                let series = Kruger::new(spheroid);
+                let k = scale_factor * series.a;
+                let (xi, eta) = ((y - false_northing) / k, (x - false_easting) / k);
+
+                let (mut xi_, mut eta_) = (xi, eta);
+                for (j, beta) in (1..).zip(series.beta) {
+                    let j = 2. * j as f64;
+                    xi_ -= beta * (j * xi).sin() * (j * eta).cosh();
+                    eta_ -= beta * (j * xi).cos() * (j * eta).sinh();
+                }
+
+                let chi = (xi_.sin() / eta_.cosh()).asin();
+                let mut lat = chi;
+                for (j, delta) in (1..).zip(series.delta) {
+                    lat += delta * (2. * j as f64 * chi).sin();
+                }
+                let lon = (eta_.sinh() / xi_.cos()).atan();
+
+                [central_meridian + lon.to_degrees(), lat.to_degrees(), z]
+            }
+            Self::LocalEnu { origin } => {
+                let (sin_lat, cos_lat) = origin[1].to_radians().sin_cos();
+                let (sin_lon, cos_lon) = origin[0].to_radians().sin_cos();
+                let [x0, y0, z0] = ecef(origin);
+
+                geodetic([
+                    x0 - sin_lon * x - sin_lat * cos_lon * y + cos_lat * cos_lon * z,
+                    y0 + cos_lon * x - sin_lat * sin_lon * y + cos_lat * sin_lon * z,
+                    z0 + cos_lat * y + sin_lat * z,
+                ])
+            }
+        }
+    }
+}
+
+/// Web Mercator is cut off to make the world square.
+const WEB_MERCATOR_MAX_LATITUDE: f64 = 85.051_128_779_806_59;
+
+/// Parses `4326`/`wgs84`, `3857`/`web-mercator`, an EPSG code of a UTM zone (e.g. `EPSG:25833`),
+/// `utm33` (on WGS84, `utm33s` for the south) or `enu:lon,lat[,height]`.
+impl FromStr for CoordinateSystem {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let unknown = || Error::UnexpectedData(format!("Unknown coordinate system `{s}`"));
+        let lower = s.trim().to_ascii_lowercase();
+        let lower = lower.strip_prefix("epsg:").unwrap_or(&lower);
+
+        if let Some(origin) = lower.strip_prefix("enu:") {
+            let origin = origin
+                .split(',')
+                .map(|n| n.trim().parse())
+                .collect::<std::result::Result<Vec<f64>, _>>()
+                .map_err(|_| unknown())?;
+
+            return match origin[..] {
+                [lon, lat] => Ok(Self::LocalEnu {
+                    origin: [lon, lat, 0.],
+                }),
+                [lon, lat, height] => Ok(Self::LocalEnu {
+                    origin: [lon, lat, height],
+                }),
+                _ => Err(unknown()),
+            };
+        }
+
+        if let Some(zone) = lower.strip_prefix("utm") {
+            let (zone, north) = match zone.strip_suffix('s') {
+                Some(zone) => (zone, false),
+                None => (zone.strip_suffix('n').unwrap_or(zone), true),
+            };
+            return match zone.parse() {
+                Ok(zone @ 1..=60) => Ok(Self::utm(zone, north, Spheroid::WGS84)),
+                _ => Err(unknown()),
+            };
+        }
+
+        match lower {
+            "wgs84" | "geographic" => Ok(Self::Geographic),
+            "web-mercator" | "webmercator" => Ok(Self::WebMercator),
+            code => code
+                .parse()
+                .ok()
+                .and_then(Self::from_epsg)
+                .ok_or_else(unknown),
+        }
+    }
+}
+
+/// Earth-centred, earth-fixed coordinates on WGS84.
+fn ecef([lon, lat, height]: [f64; 3]) -> [f64; 3] {
+    let spheroid = Spheroid::WGS84;
+    let (sin_lat, cos_lat) = lat.to_radians().sin_cos();
+    let (sin_lon, cos_lon) = lon.to_radians().sin_cos();
+    let n = spheroid.semi_major_axis / (1. - spheroid.e2() * sin_lat * sin_lat).sqrt();
+
+    [
+        (n + height) * cos_lat * cos_lon,
+        (n + height) * cos_lat * sin_lon,
+        (n * (1. - spheroid.e2()) + height) * sin_lat,
+    ]
+}
+
+/// Back from [`ecef`], iterating until the latitude settles.
+fn geodetic([x, y, z]: [f64; 3]) -> [f64; 3] {
+    let spheroid = Spheroid::WGS84;
+    let e2 = spheroid.e2();
+    let p = x.hypot(y);
+
+    let mut lat = z.atan2(p * (1. - e2));
+    let mut height = 0.;
+    for _ in 0..10 {
+        let sin_lat = lat.sin();
+        let n = spheroid.semi_major_axis / (1. - e2 * sin_lat * sin_lat).sqrt();
+        height = p / lat.cos() - n;
+
+        let next = z.atan2(p * (1. - e2 * n / (n + height)));
+        if (next - lat).abs() < 1e-14 {
+            lat = next;
+            break;
+        }
+        lat = next;
+    }
+
+    [y.atan2(x).to_degrees(), lat.to_degrees(), height]
+}
+
+/// Coefficients of the Krüger series for a spheroid.
+struct Kruger {
+    /// Radius of the rectifying sphere
+    a: f64,
+    alpha: [f64; 3],
+    beta: [f64; 3],
+    delta: [f64; 3],
+}
+
+impl Kruger {
+    fn new(spheroid: Spheroid) -> Self {
+        let f = spheroid.flattening;
+        let n = f / (2. - f);
+        let (n2, n3) = (n * n, n * n * n);
+
+        Self {
+            a: spheroid.semi_major_axis / (1. + n) * (1. + n2 / 4. + n2 * n2 / 64.),
+            alpha: [
+                n / 2. - 2. / 3. * n2 + 5. / 16. * n3,
+                13. / 48. * n2 - 3. / 5. * n3,
+                61. / 240. * n3,
+            ],
+            beta: [
+                n / 2. - 2. / 3. * n2 + 37. / 96. * n3,
+                1. / 48. * n2 + 1. / 15. * n3,
+                17. / 480. * n3,
+            ],
+            delta: [
+                2. * n - 2. / 3. * n2 - 2. * n3,
+                7. / 3. * n2 - 8. / 5. * n3,
+                56. / 15. * n3,
+            ],
+        }
+    }
+}
+
+/// Converts coordinates from one system to another, going through longitudes and latitudes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub from: CoordinateSystem,
+    pub to: CoordinateSystem,
+}
+
+impl Transform {
+    pub fn new(from: CoordinateSystem, to: CoordinateSystem) -> Self {
+        Self { from, to }
+    }
+
+    pub fn point(&self, point: [f64; 3]) -> [f64; 3] {
+        if self.from == self.to {
+            return point;
+        }
+
+        self.to.project(self.from.unproject(point))
+    }
+
+    /// Converts all the points of the shape, and updates its bounding box and Z range.
+    /// 2D points are taken to be at height 0, and stay 2D.
+    pub fn shape(&self, shape: &mut Shape) {
+        let flat = |point: &mut Point| {
+            let [x, y, _] = self.point([point.x, point.y, 0.]);
+            *point = Point { x, y };
+        };
+        let elevated = |points: &mut [Point], z: &mut [f64]| {
+            for (point, z) in points.iter_mut().zip(z) {
+                let [x, y, up] = self.point([point.x, point.y, *z]);
+                *point = Point { x, y };
+                *z = up;
+            }
+        };
+        let mbr = |mbr: &mut MinimumBoundingRectangle, points: &[Point]| {
+            if let Some(projected) = MinimumBoundingRectangle::from_points(points) {
+                *mbr = projected;
+            }
+        };
+        let z_range = |range: &mut std::ops::Range<f64>, z: &[f64]| {
+            if let Some(min) = z.iter().copied().reduce(f64::min) {
+                *range = min..z.iter().copied().fold(min, f64::max);
+            }
+        };
+
+        match shape {
+            Shape::Null => {}
+            Shape::Point(point) => flat(point),
+            Shape::PointM(point) => {
+                let [x, y, _] = self.point([point.x, point.y, 0.]);
+                (point.x, point.y) = (x, y);
+            }
+            Shape::PointZ(point) => {
+                [point.x, point.y, point.z] = self.point([point.x, point.y, point.z]);
+            }
+            Shape::PolyLine(line) => {
+                line.points.iter_mut().for_each(flat);
+                mbr(&mut line.mbr, &line.points);
+            }
+            Shape::Polygon(polygon) => {
+                polygon.points.iter_mut().for_each(flat);
+                mbr(&mut polygon.mbr, &polygon.points);
+            }
+            Shape::MultiPoint(multipoint) => {
+                multipoint.points.iter_mut().for_each(flat);
+                mbr(&mut multipoint.mbr, &multipoint.points);
+            }
+            Shape::PolylineM(line) => {
+                line.points.iter_mut().for_each(flat);
+                mbr(&mut line.mbr, &line.points);
+            }
+            Shape::PolygonM(polygon) => {
+                polygon.points.iter_mut().for_each(flat);
+                mbr(&mut polygon.mbr, &polygon.points);
+            }
+            Shape::MultiPointM(multipoint) => {
+                multipoint.points.iter_mut().for_each(flat);
+                mbr(&mut multipoint.mbr, &multipoint.points);
+            }
+            Shape::PolylineZ(line) => {
+                elevated(&mut line.points, &mut line.z);
+                mbr(&mut line.mbr, &line.points);
+                z_range(&mut line.z_range, &line.z);
+            }
+            Shape::PolygonZ(polygon) => {
+                elevated(&mut polygon.points, &mut polygon.z);
+                mbr(&mut polygon.mbr, &polygon.points);
+                z_range(&mut polygon.z_range, &polygon.z);
+            }
+            Shape::MultiPointZ(multipoint) => {
+                elevated(&mut multipoint.points, &mut multipoint.z);
+                mbr(&mut multipoint.mbr, &multipoint.points);
+                z_range(&mut multipoint.z_range, &multipoint.z);
+            }
+            Shape::MultiPatch(patch) => {
+                elevated(&mut patch.points, &mut patch.z);
+                mbr(&mut patch.mbr, &patch.points);
+                z_range(&mut patch.z_range, &patch.z);
+            }
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! The rings of polygons, and which of them are holes.
+//!
+//! A Shapefile polygon is any number of rings.
+//! From "ESRI Shapefile Technical Description" the vertices of outer rings are in clockwise order,
+//! and those of holes in counter-clockwise order.
+//! A polygon with several outer rings is a multipolygon.
+
+use std::ops::Range;
+
+use crate::shape::{part_ranges, Point, Polygon, PolygonM, PolygonZ};
+
+/// The order of the vertices of a ring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    /// An outer ring
+    Clockwise,
+
+    /// A hole
+    CounterClockwise,
+}
+
+impl Orientation {
+    /// Rings without area, e.g. with fewer than three distinct points, count as holes.
+    pub fn of(ring: &[Point]) -> Self {
+        if signed_area(ring) < 0. {
+            Self::Clockwise
+        } else {
+            Self::CounterClockwise
+        }
+    }
+}
+
+/// The area of the ring, positive if counter-clockwise.
+/// The ring may or may not repeat its first point at the end.
+pub fn signed_area(ring: &[Point]) -> f64 {
+    let Some(first) = ring.first() else {
+        return 0.;
+    };
+
+    // Relative to the first point, which keeps large coordinates precise
+    let twice: f64 = ring
+        .iter()
+        .zip(ring.iter().skip(1).chain([first]))
+        .map(|(a, b)| (a.x - first.x) * (b.y - first.y) - (b.x - first.x) * (a.y - first.y))
+        .sum();
+
+    twice / 2.
+}
+
+/// Whether the point is inside the ring, by the even-odd rule.
+/// Points on the boundary may be either.
+pub fn ring_contains(ring: &[Point], point: Point) -> bool {
+    let Some(last) = ring.last() else {
+        return false;
+    };
+
+    let mut inside = false;
+    let mut previous = last;
+    for current in ring {
+        if (current.y > point.y) != (previous.y > point.y) {
+            let x = current.x
+                + (point.y - current.y) * (previous.x - current.x) / (previous.y - current.y);
+            if point.x < x {
+                inside = !inside;
+            }
+        }
+        previous = current;
+    }
+
+    inside
+}
+
+/// Whether the inner ring is inside the outer ring.
+/// Rings may touch, so it is enough that most of the points of the inner ring are inside.
+pub fn ring_within(inner: &[Point], outer: &[Point]) -> bool {
+    let inside = inner
+        .iter()
+        .filter(|point| ring_contains(outer, **point))
+        .count();
+
+    inside * 2 > inner.len()
+}
+
+/// An outer ring along with the holes inside it.
+/// The rings are index ranges into the points of the shape.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolygonRings {
+    pub exterior: Range<usize>,
+    pub interiors: Vec<Range<usize>>,
+}
+
+/// Groups the rings of a polygon shape into polygons, each an outer ring with its holes.
+///
+/// Each hole belongs to the smallest outer ring containing it.
+/// Holes outside all outer rings, such as in files not following the orientation rules,
+/// are taken as outer rings instead.
+/// Polygons are in the order of their outer rings.
+pub fn polygons(parts: &[i32], points: &[Point]) -> Vec<PolygonRings> {
+    let (outers, inners): (Vec<_>, Vec<_>) = part_ranges(parts, points.len())
+        .partition(|range| Orientation::of(&points[range.clone()]) == Orientation::Clockwise);
+
+    let mut polygons: Vec<PolygonRings> = outers
+        .into_iter()
+        .map(|exterior| PolygonRings {
+            exterior,
+            interiors: vec![],
+        })
+        .collect();
+    let areas: Vec<f64> = polygons
+        .iter()
+        .map(|polygon| signed_area(&points[polygon.exterior.clone()]).abs())
+        .collect();
+
+    let mut orphans = vec![];
+    for inner in inners {
+        let hole = &points[inner.clone()];
+
+        let container = polygons
+            .iter()
+            .zip(&areas)
+            .enumerate()
+            .filter(|(_, (polygon, _))| ring_within(hole, &points[polygon.exterior.clone()]))
+            .min_by(|(_, (_, a)), (_, (_, b))| a.total_cmp(b))
+            .map(|(index, _)| index);
+
+        match container {
+            Some(index) => polygons[index].interiors.push(inner),
+            None => orphans.push(inner),
+        }
+    }
+
+    polygons.extend(orphans.into_iter().map(|exterior| PolygonRings {
+        exterior,
+        interiors: vec![],
+    }));
+    polygons.sort_by_key(|polygon| polygon.exterior.start);
+
+    polygons
+}
+
+impl Polygon {
+    /// The points of each ring, in the order of the parts.
+    pub fn rings(&self) -> impl Iterator<Item = &[Point]> + '_ {
+        part_ranges(&self.parts, self.points.len()).map(|range| &self.points[range])
+    }
+
+    /// See [`polygons`].
+    pub fn polygons(&self) -> Vec<PolygonRings> {
+        polygons(&self.parts, &self.points)
+    }
+}
+
+impl PolygonZ {
+    /// See [`polygons`]. Orientation only depends on X and Y.
+    pub fn polygons(&self) -> Vec<PolygonRings> {
+        polygons(&self.parts, &self.points)
+    }
+}
+
+impl PolygonM {
+    /// See [`polygons`].
+    pub fn polygons(&self) -> Vec<PolygonRings> {
+        polygons(&self.parts, &self.points)
+    }
+}
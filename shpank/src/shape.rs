@@ -1,6 +1,9 @@
 use std::ops::Range;
 
-use crate::parse::Error;
+use crate::{
+    parse::{Diagnostic, Error},
+    triangulate::{triangulate_polygon_3d, TriangleMesh},
+};
 
 #[derive(Debug, Clone)]
 pub struct MinimumBoundingRectangle {
@@ -8,6 +11,33 @@ pub struct MinimumBoundingRectangle {
     pub y: Range<f64>,
 }
 
+impl MinimumBoundingRectangle {
+    /// The smallest rectangle containing all the points, `None` if there are none.
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Point>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+
+        Some(points.fold(
+            Self {
+                x: first.x..first.x,
+                y: first.y..first.y,
+            },
+            |mbr, p| Self {
+                x: mbr.x.start.min(p.x)..mbr.x.end.max(p.x),
+                y: mbr.y.start.min(p.y)..mbr.y.end.max(p.y),
+            },
+        ))
+    }
+
+    /// The smallest rectangle containing both rectangles.
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            x: self.x.start.min(other.x.start)..self.x.end.max(other.x.end),
+            y: self.y.start.min(other.y.start)..self.y.end.max(other.y.end),
+        }
+    }
+}
+
 pub type Integer = i32;
 pub type Double = f64;
 
@@ -26,6 +56,14 @@ impl ShpLength {
 pub struct ShpFile {
     pub header: ShpHeader,
     pub records: Vec<ShpRecord>,
+
+    /// Positions (starting at 0) of records in the file which were skipped
+    /// because they were corrupt, in increasing order.
+    /// Only non-empty when parsing with [`crate::parse::Recovery::Lenient`].
+    pub dropped: Vec<usize>,
+
+    /// What was wrong with the file, see [`crate::parse::Recovery::Lenient`].
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 // See https://en.wikipedia.org/wiki/Shapefile#Shapefile_headers
@@ -62,7 +100,7 @@ pub struct ShpRecord {
     pub shape: Shape,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ShapeType {
     Null = 0,
     Point = 1,
@@ -126,7 +164,28 @@ pub enum Shape {
     MultiPatch(MultiPatch),
 }
 
-#[derive(Debug, Clone, Copy)]
+impl Shape {
+    pub fn shape_type(&self) -> ShapeType {
+        match self {
+            Shape::Null => ShapeType::Null,
+            Shape::Point(_) => ShapeType::Point,
+            Shape::PolyLine(_) => ShapeType::PolyLine,
+            Shape::Polygon(_) => ShapeType::Polygon,
+            Shape::MultiPoint(_) => ShapeType::MultiPoint,
+            Shape::PointZ(_) => ShapeType::PointZ,
+            Shape::PolylineZ(_) => ShapeType::PolylineZ,
+            Shape::PolygonZ(_) => ShapeType::PolygonZ,
+            Shape::MultiPointZ(_) => ShapeType::MultiPointZ,
+            Shape::PointM(_) => ShapeType::PointM,
+            Shape::PolylineM(_) => ShapeType::PolylineM,
+            Shape::PolygonM(_) => ShapeType::PolygonM,
+            Shape::MultiPointM(_) => ShapeType::MultiPointM,
+            Shape::MultiPatch(_) => ShapeType::MultiPatch,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Point {
     pub x: f64,
     pub y: f64,
@@ -147,31 +206,387 @@ pub struct Polygon {
 }
 
 #[derive(Debug, Clone)]
-pub struct MultiPoint;
+pub struct MultiPoint {
+    pub mbr: MinimumBoundingRectangle,
+    pub points: Vec<Point>,
+}
 
-#[derive(Debug, Clone)]
-pub struct PointZ;
+#[derive(Debug, Clone, Copy)]
+pub struct PointZ {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+
+    /// Optional for Z shapes, see [`ShpRecordHeader::content_length`].
+    /// Also `None` if the measure is "no data".
+    pub m: Measure,
+}
 
 #[derive(Debug, Clone)]
-pub struct PolylineZ;
+pub struct PolylineZ {
+    pub mbr: MinimumBoundingRectangle,
+    pub parts: Vec<i32>,
+    pub points: Vec<Point>,
+    pub z_range: Range<f64>,
+
+    /// One Z value per point
+    pub z: Vec<f64>,
+
+    /// Optional for Z shapes, see [`ShpRecordHeader::content_length`]
+    pub m: Option<Measures>,
+}
 
 #[derive(Debug, Clone)]
-pub struct PolygonZ;
+pub struct PolygonZ {
+    pub mbr: MinimumBoundingRectangle,
+    pub parts: Vec<i32>,
+    pub points: Vec<Point>,
+    pub z_range: Range<f64>,
+
+    /// One Z value per point
+    pub z: Vec<f64>,
+
+    /// Optional for Z shapes, see [`ShpRecordHeader::content_length`]
+    pub m: Option<Measures>,
+}
 
 #[derive(Debug, Clone)]
-pub struct MultiPointZ;
+pub struct MultiPointZ {
+    pub mbr: MinimumBoundingRectangle,
+    pub points: Vec<Point>,
+    pub z_range: Range<f64>,
+
+    /// One Z value per point
+    pub z: Vec<f64>,
+
+    /// Optional for Z shapes, see [`ShpRecordHeader::content_length`]
+    pub m: Option<Measures>,
+}
+
+/// A measure value, `None` if the file stores "no data".
+pub type Measure = Option<f64>;
 
+/// From "ESRI Shapefile Technical Description" any floating point number
+/// smaller than this is considered "no data" when it is a measure.
+pub const NO_DATA_LIMIT: f64 = -1e38;
+
+/// What is written for measures which are "no data".
+pub const NO_DATA: f64 = -1e39;
+
+/// Maps the ESRI "no data" sentinel to `None`.
+pub fn measure(value: f64) -> Measure {
+    (value >= NO_DATA_LIMIT).then_some(value)
+}
+
+/// The M range along with one M value per point.
 #[derive(Debug, Clone)]
-pub struct PointM;
+pub struct Measures {
+    /// `None` if either end of the range is "no data"
+    pub range: Option<Range<f64>>,
+    pub values: Vec<Measure>,
+}
+
+/// Index ranges into the points of a shape, one per part.
+pub fn part_ranges(parts: &[i32], num_points: usize) -> impl Iterator<Item = Range<usize>> + '_ {
+    parts.iter().enumerate().map(move |(index, start)| {
+        let end = parts
+            .get(index + 1)
+            .map_or(num_points, |next| *next as usize);
+
+        *start as usize..end
+    })
+}
+
+/// Pairs up 2D points with their Z values.
+fn zip_z<'a>(points: &'a [Point], z: &'a [f64]) -> impl Iterator<Item = [f64; 3]> + 'a {
+    points.iter().zip(z).map(|(p, z)| [p.x, p.y, *z])
+}
+
+impl PolylineZ {
+    pub fn points_3d(&self) -> impl Iterator<Item = [f64; 3]> + '_ {
+        zip_z(&self.points, &self.z)
+    }
+}
+
+impl PolygonZ {
+    pub fn points_3d(&self) -> impl Iterator<Item = [f64; 3]> + '_ {
+        zip_z(&self.points, &self.z)
+    }
+}
+
+impl MultiPointZ {
+    pub fn points_3d(&self) -> impl Iterator<Item = [f64; 3]> + '_ {
+        zip_z(&self.points, &self.z)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PointM {
+    pub x: f64,
+    pub y: f64,
+    pub m: Measure,
+}
 
 #[derive(Debug, Clone)]
-pub struct PolylineM;
+pub struct PolylineM {
+    pub mbr: MinimumBoundingRectangle,
+    pub parts: Vec<i32>,
+    pub points: Vec<Point>,
+    pub m: Measures,
+}
 
 #[derive(Debug, Clone)]
-pub struct PolygonM;
+pub struct PolygonM {
+    pub mbr: MinimumBoundingRectangle,
+    pub parts: Vec<i32>,
+    pub points: Vec<Point>,
+    pub m: Measures,
+}
 
 #[derive(Debug, Clone)]
-pub struct MultiPointM;
+pub struct MultiPointM {
+    pub mbr: MinimumBoundingRectangle,
+    pub points: Vec<Point>,
+    pub m: Measures,
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    (b.x - a.x).hypot(b.y - a.y)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+impl PolylineM {
+    /// Iterates over all segments which do not cross parts,
+    /// as pairs of point indices.
+    fn segments(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        part_ranges(&self.parts, self.points.len()).flat_map(|part| part.clone().zip(part.skip(1)))
+    }
+
+    /// Finds the location of the given measure along the line,
+    /// e.g. where a kilometre post is.
+    ///
+    /// The position is linearly interpolated between the vertices around it.
+    /// Segments where a vertex has no measure are skipped.
+    /// If several segments contain the measure, the first one is used.
+    pub fn point_at_measure(&self, m: f64) -> Option<Point> {
+        self.segments().find_map(|(i, j)| {
+            let (m_i, m_j) = (self.m.values[i]?, self.m.values[j]?);
+            let (p_i, p_j) = (self.points[i], self.points[j]);
+
+            if m < m_i.min(m_j) || m > m_i.max(m_j) {
+                return None;
+            }
+
+            let t = if m_i == m_j {
+                0.
+            } else {
+                (m - m_i) / (m_j - m_i)
+            };
+
+            Some(Point {
+                x: lerp(p_i.x, p_j.x, t),
+                y: lerp(p_i.y, p_j.y, t),
+            })
+        })
+    }
+
+    /// Finds the measure at the given distance along a part,
+    /// linearly interpolated between the vertices around it.
+    ///
+    /// `None` if the distance is outside the part or a vertex
+    /// around it has no measure.
+    pub fn measure_at_distance(&self, part: usize, distance_along: f64) -> Measure {
+        let range = part_ranges(&self.parts, self.points.len()).nth(part)?;
+
+        if distance_along < 0. {
+            return None;
+        }
+
+        let mut travelled = 0.;
+        for (i, j) in range.clone().zip(range.skip(1)) {
+            let length = distance(self.points[i], self.points[j]);
+
+            if travelled + length >= distance_along {
+                let (m_i, m_j) = (self.m.values[i]?, self.m.values[j]?);
+                let t = if length == 0. {
+                    0.
+                } else {
+                    (distance_along - travelled) / length
+                };
+
+                return Some(lerp(m_i, m_j, t));
+            }
+
+            travelled += length;
+        }
+
+        None
+    }
+}
+
+/// How the points of a [`MultiPatch`] part are to be interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchType {
+    /// Each vertex after the first two forms a triangle with the two before it
+    TriangleStrip = 0,
+
+    /// Each vertex after the first two forms a triangle with the one before it and the first
+    TriangleFan = 1,
+
+    /// Outer ring of a polygon
+    OuterRing = 2,
+
+    /// Hole of the preceding outer ring
+    InnerRing = 3,
+
+    /// First ring of a polygon of unspecified ring types
+    FirstRing = 4,
+
+    /// Ring following a first ring, treated as a hole
+    Ring = 5,
+}
+
+impl TryFrom<i32> for PatchType {
+    type Error = Error;
+
+    fn try_from(value: i32) -> crate::parse::Result<Self> {
+        Ok(match value {
+            v if v == PatchType::TriangleStrip as i32 => PatchType::TriangleStrip,
+            v if v == PatchType::TriangleFan as i32 => PatchType::TriangleFan,
+            v if v == PatchType::OuterRing as i32 => PatchType::OuterRing,
+            v if v == PatchType::InnerRing as i32 => PatchType::InnerRing,
+            v if v == PatchType::FirstRing as i32 => PatchType::FirstRing,
+            v if v == PatchType::Ring as i32 => PatchType::Ring,
+            _ => {
+                return Err(Error::UnexpectedData(format!(
+                    "The number `{value}` does not correspond to a patch type"
+                )))
+            }
+        })
+    }
+}
 
 #[derive(Debug, Clone)]
-pub struct MultiPatch;
+pub struct MultiPatch {
+    pub mbr: MinimumBoundingRectangle,
+    pub parts: Vec<i32>,
+
+    /// One per part
+    pub part_types: Vec<PatchType>,
+
+    pub points: Vec<Point>,
+    pub z_range: Range<f64>,
+
+    /// One Z value per point
+    pub z: Vec<f64>,
+
+    /// Optional, see [`ShpRecordHeader::content_length`]
+    pub m: Option<Measures>,
+}
+
+/// Triangulates a polygon given as point index ranges for its
+/// exterior ring followed by its holes.
+fn triangulate_rings(positions: &[[f64; 3]], rings: &[Range<usize>], indices: &mut Vec<u32>) {
+    if rings.is_empty() {
+        return;
+    }
+
+    // Rings repeat their first point at the end
+    let rings: Vec<Range<usize>> = rings
+        .iter()
+        .map(|range| {
+            let closed = range.len() > 1 && positions[range.start] == positions[range.end - 1];
+            if closed {
+                range.start..range.end - 1
+            } else {
+                range.clone()
+            }
+        })
+        .collect();
+
+    let vertices: Vec<Vec<[f64; 3]>> = rings
+        .iter()
+        .map(|range| positions[range.clone()].to_vec())
+        .collect();
+
+    // Triangulation indexes the rings as if concatenated, map back to point indices
+    let to_point_index = |mut index: usize| {
+        for range in &rings {
+            if index < range.len() {
+                return (range.start + index) as u32;
+            }
+            index -= range.len();
+        }
+        unreachable!("triangulation index out of range")
+    };
+
+    for triangle in triangulate_polygon_3d(&vertices[0], &vertices[1..]) {
+        indices.extend(triangle.map(to_point_index));
+    }
+}
+
+impl MultiPatch {
+    pub fn points_3d(&self) -> impl Iterator<Item = [f64; 3]> + '_ {
+        zip_z(&self.points, &self.z)
+    }
+
+    /// Turns all parts into a single indexed triangle list.
+    ///
+    /// Positions are all the points of the patch in order,
+    /// so indices refer to the same points as the parts do.
+    ///
+    /// Rings are grouped into polygons: An outer ring with the inner rings following it,
+    /// or a first ring with the rings following it.
+    /// Triangles keep the winding of the strip, fan or outer ring they come from.
+    pub fn triangulate(&self) -> TriangleMesh {
+        let positions: Vec<[f64; 3]> = self.points_3d().collect();
+        let mut indices = vec![];
+
+        // Exterior then holes of the polygon currently being collected
+        let mut polygon: Vec<Range<usize>> = vec![];
+
+        for (range, type_) in part_ranges(&self.parts, self.points.len()).zip(&self.part_types) {
+            if matches!(
+                type_,
+                PatchType::TriangleStrip
+                    | PatchType::TriangleFan
+                    | PatchType::OuterRing
+                    | PatchType::FirstRing
+            ) {
+                triangulate_rings(&positions, &polygon, &mut indices);
+                polygon.clear();
+            }
+
+            match type_ {
+                PatchType::TriangleStrip => {
+                    for (n, i) in (range.start..range.end.saturating_sub(2)).enumerate() {
+                        let i = i as u32;
+                        // Every other triangle is flipped to keep the winding consistent
+                        if n % 2 == 0 {
+                            indices.extend([i, i + 1, i + 2]);
+                        } else {
+                            indices.extend([i + 1, i, i + 2]);
+                        }
+                    }
+                }
+                PatchType::TriangleFan => {
+                    let first = range.start as u32;
+                    for i in (range.start + 1)..range.end.saturating_sub(1) {
+                        let i = i as u32;
+                        indices.extend([first, i, i + 1]);
+                    }
+                }
+                PatchType::OuterRing
+                | PatchType::FirstRing
+                | PatchType::InnerRing
+                | PatchType::Ring => polygon.push(range),
+            }
+        }
+        triangulate_rings(&positions, &polygon, &mut indices);
+
+        TriangleMesh { positions, indices }
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use crate::{
+    parse::{Error, Parser, Result},
+    shape::{ShpHeader, ShpLength, ShpRecord},
+};
+
+/// The index of a `.shp` file.
+/// Much smaller than the `.shp` file, since it only has the location of each record.
+#[derive(Debug, Clone)]
+pub struct ShxFile {
+    /// Same as the `.shp` header, except the file length is that of the `.shx`
+    pub header: ShpHeader,
+    pub records: Vec<ShxRecord>,
+}
+
+// See https://en.wikipedia.org/wiki/Shapefile#Shapefile_shape_index_format_(.shx)
+#[derive(Debug, Clone, Copy)]
+pub struct ShxRecord {
+    /// In 16-bit words, from the start of the `.shp` file to the record header
+    pub offset: ShpLength,
+
+    /// In 16-bit words, same as in the record header in the `.shp` file.
+    /// This is not including the record header.
+    pub content_length: ShpLength,
+}
+
+impl ShxRecord {
+    /// Size of a record header in the `.shp` file
+    pub const RECORD_HEADER_BYTES: usize = 8;
+
+    /// Number of bytes the record takes up in the `.shp` file, including its header.
+    pub fn num_bytes(&self) -> usize {
+        Self::RECORD_HEADER_BYTES + self.content_length.num_bytes()
+    }
+}
+
+impl<R> Parser<R>
+where
+    R: io::Read,
+{
+    pub fn parse_shx_record(&mut self) -> Result<ShxRecord> {
+        let offset_of_entry = self.num_bytes_read();
+
+        let offset = self.parse_length()?;
+        let content_length = self.parse_length()?;
+
+        // Records can't be inside the 100 byte header
+        if offset.0 < 50 {
+            return Err(Error::BadLength {
+                offset: offset_of_entry,
+                length: offset.0,
+            });
+        }
+
+        if content_length.0 < 0 {
+            return Err(Error::BadLength {
+                offset: offset_of_entry + 4,
+                length: content_length.0,
+            });
+        }
+
+        Ok(ShxRecord {
+            offset,
+            content_length,
+        })
+    }
+
+    pub(crate) fn impl_parse_shx(mut self) -> Result<ShxFile> {
+        let header = self.parse_header()?;
+
+        let mut records = vec![];
+
+        let goal = header.file_length.num_bytes();
+
+        while self.num_bytes_read() < goal {
+            records.push(self.parse_shx_record()?);
+        }
+
+        self.expect_bytes_read(goal)?;
+
+        Ok(ShxFile { header, records })
+    }
+}
+
+/// Reads single records from a `.shp` file without parsing the records before it,
+/// by looking up where they are in the [`ShxFile`].
+///
+/// Since each record is found on its own, a corrupt record does not affect reading the others.
+pub struct ShpReader<R> {
+    reader: R,
+    header: ShpHeader,
+    index: ShxFile,
+}
+
+impl ShpReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(shp_path: P, shx_path: P) -> Result<Self> {
+        let index = Parser::parse_shx_file(shx_path)?;
+        let shp = File::open(shp_path.as_ref())?;
+
+        Self::new(BufReader::new(shp), index)
+    }
+}
+
+impl<R> ShpReader<R>
+where
+    R: Read + Seek,
+{
+    pub fn new(mut reader: R, index: ShxFile) -> Result<Self> {
+        reader.seek(SeekFrom::Start(0))?;
+        let header = Parser::with_reader(&mut reader).parse_header()?;
+
+        Ok(Self {
+            reader,
+            header,
+            index,
+        })
+    }
+
+    /// The header of the `.shp` file.
+    pub fn header(&self) -> &ShpHeader {
+        &self.header
+    }
+
+    pub fn index(&self) -> &ShxFile {
+        &self.index
+    }
+
+    pub fn num_records(&self) -> usize {
+        self.index.records.len()
+    }
+
+    /// Seeks to and parses the record at the given position (starting at 0).
+    pub fn read_record(&mut self, index: usize) -> Result<ShpRecord> {
+        let entry = *self
+            .index
+            .records
+            .get(index)
+            .ok_or(Error::RecordOutOfRange {
+                index,
+                num_records: self.num_records(),
+            })?;
+
+        let offset = entry.offset.num_bytes();
+        self.reader.seek(SeekFrom::Start(offset as u64))?;
+
+        let record = (&mut self.reader).take(entry.num_bytes() as u64);
+        Parser::with_reader_at(record, offset).parse_record()
+    }
+}
@@ -0,0 +1,423 @@
+//! Simplifying lines and polygons by dropping points which add little to their shape.
+//!
+//! The tolerance is in the units of the coordinates, e.g. metres for projected data.
+//! Rings keep their first point, stay closed and keep at least three distinct points.
+
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashSet},
+    ops::Range,
+    str::FromStr,
+};
+
+use crate::{
+    parse::{Error, Result},
+    shape::{
+        part_ranges, Measures, MinimumBoundingRectangle, Point, PolyLine, Polygon, PolygonZ,
+        PolylineZ, Shape,
+    },
+    validate::{cross, join, polygon_problems, Problem},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Algorithm {
+    /// Keeps the points which are further than the tolerance from the simplified line.
+    /// Good at keeping the outline.
+    #[default]
+    DouglasPeucker,
+
+    /// Drops the points making the smallest triangles with their neighbours,
+    /// until all are larger than the tolerance squared.
+    /// Gives smoother results, without spikes.
+    VisvalingamWhyatt,
+}
+
+/// `douglas-peucker` (or `dp`) or `visvalingam-whyatt` (or `vw`).
+impl FromStr for Algorithm {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "douglas-peucker" | "dp" => Ok(Self::DouglasPeucker),
+            "visvalingam-whyatt" | "vw" => Ok(Self::VisvalingamWhyatt),
+            _ => Err(Error::UnexpectedData(format!(
+                "Unknown simplification algorithm `{s}`"
+            ))),
+        }
+    }
+}
+
+/// How many times a polygon is simplified with half the tolerance
+/// when simplifying makes it invalid, before giving up and keeping the original.
+const ATTEMPTS: usize = 8;
+
+impl Shape {
+    /// Simplifies lines and polygons, see [`PolyLine::simplify`] and [`Polygon::simplify`].
+    /// Lines and polygons with Z values keep the Z and M values of the points which are kept.
+    /// Other shapes are returned as they are.
+    pub fn simplify(&self, algorithm: Algorithm, tolerance: f64) -> Shape {
+        match self {
+            Shape::PolyLine(line) => Shape::PolyLine(line.simplify(algorithm, tolerance)),
+            Shape::PolylineZ(line) => Shape::PolylineZ(line.simplify(algorithm, tolerance)),
+            Shape::Polygon(polygon) => Shape::Polygon(polygon.simplify(algorithm, tolerance)),
+            Shape::PolygonZ(polygon) => Shape::PolygonZ(polygon.simplify(algorithm, tolerance)),
+            other => other.clone(),
+        }
+    }
+}
+
+impl PolyLine {
+    /// Each line keeps its first and last point.
+    pub fn simplify(&self, algorithm: Algorithm, tolerance: f64) -> PolyLine {
+        let (parts, kept) = simplify_lines(&self.parts, &self.points, algorithm, tolerance);
+        let points = pick(&self.points, &kept);
+
+        PolyLine {
+            mbr: MinimumBoundingRectangle::from_points(&points).unwrap_or(self.mbr.clone()),
+            parts,
+            points,
+        }
+    }
+}
+
+impl PolylineZ {
+    /// Simplified by X and Y alone, see [`PolyLine::simplify`].
+    pub fn simplify(&self, algorithm: Algorithm, tolerance: f64) -> PolylineZ {
+        let (parts, kept) = simplify_lines(&self.parts, &self.points, algorithm, tolerance);
+        let points = pick(&self.points, &kept);
+        let z = pick(&self.z, &kept);
+
+        PolylineZ {
+            mbr: MinimumBoundingRectangle::from_points(&points).unwrap_or(self.mbr.clone()),
+            parts,
+            points,
+            z_range: range_of(z.iter().copied()).unwrap_or(self.z_range.clone()),
+            z,
+            m: self.m.as_ref().map(|m| pick_measures(m, &kept)),
+        }
+    }
+}
+
+impl Polygon {
+    /// Simplifies each ring, making sure the polygon does not get any problems it did not have before:
+    /// Rings crossing themselves or each other, holes turning into outer rings or the other way around,
+    /// or rings collapsing.
+    /// If it would, the tolerance is halved and the polygon simplified again.
+    ///
+    /// Each polygon is simplified on its own, so an edge shared with a neighbouring polygon,
+    /// e.g. the border between two counties, may be simplified differently in each
+    /// and leave gaps or overlaps between them.
+    pub fn simplify(&self, algorithm: Algorithm, tolerance: f64) -> Polygon {
+        let Some((parts, kept)) = simplify_rings(&self.parts, &self.points, algorithm, tolerance)
+        else {
+            return self.clone();
+        };
+        let points = pick(&self.points, &kept);
+
+        Polygon {
+            mbr: MinimumBoundingRectangle::from_points(&points).unwrap_or(self.mbr.clone()),
+            parts,
+            points,
+        }
+    }
+}
+
+impl PolygonZ {
+    /// Simplified by X and Y alone, see [`Polygon::simplify`].
+    pub fn simplify(&self, algorithm: Algorithm, tolerance: f64) -> PolygonZ {
+        let Some((parts, kept)) = simplify_rings(&self.parts, &self.points, algorithm, tolerance)
+        else {
+            return self.clone();
+        };
+        let points = pick(&self.points, &kept);
+        let z = pick(&self.z, &kept);
+
+        PolygonZ {
+            mbr: MinimumBoundingRectangle::from_points(&points).unwrap_or(self.mbr.clone()),
+            parts,
+            points,
+            z_range: range_of(z.iter().copied()).unwrap_or(self.z_range.clone()),
+            z,
+            m: self.m.as_ref().map(|m| pick_measures(m, &kept)),
+        }
+    }
+}
+
+/// Parts and indices of the points to keep, see [`PolyLine::simplify`].
+fn simplify_lines(
+    parts: &[i32],
+    points: &[Point],
+    algorithm: Algorithm,
+    tolerance: f64,
+) -> (Vec<i32>, Vec<usize>) {
+    simplify_parts(parts, points, |line| match algorithm {
+        Algorithm::DouglasPeucker => douglas_peucker(line, tolerance),
+        Algorithm::VisvalingamWhyatt => visvalingam_whyatt(line, tolerance, 2),
+    })
+}
+
+/// Parts and indices of the points to keep, see [`Polygon::simplify`].
+/// `None` if the polygon can't be simplified without breaking it.
+fn simplify_rings(
+    parts: &[i32],
+    points: &[Point],
+    algorithm: Algorithm,
+    tolerance: f64,
+) -> Option<(Vec<i32>, Vec<usize>)> {
+    let before = broken(parts, points);
+
+    let mut tolerance = tolerance;
+    for _ in 0..ATTEMPTS {
+        let (simplified_parts, kept) = simplify_parts(parts, points, |ring| {
+            simplify_ring(ring, algorithm, tolerance)
+        });
+
+        if broken(&simplified_parts, &pick(points, &kept)).is_subset(&before) {
+            return Some((simplified_parts, kept));
+        }
+        tolerance /= 2.;
+    }
+
+    None
+}
+
+fn pick<T: Copy>(values: &[T], kept: &[usize]) -> Vec<T> {
+    kept.iter().map(|index| values[*index]).collect()
+}
+
+fn pick_measures(m: &Measures, kept: &[usize]) -> Measures {
+    let values = pick(&m.values, kept);
+
+    Measures {
+        range: range_of(values.iter().flatten().copied()),
+        values,
+    }
+}
+
+/// The smallest range containing all the values, `None` if there are none.
+fn range_of(values: impl IntoIterator<Item = f64>) -> Option<Range<f64>> {
+    values
+        .into_iter()
+        .map(|v| v..v)
+        .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+}
+
+/// A problem simplifying can cause, see [`Polygon::simplify`].
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Broken {
+    Crossing { part: usize, other_part: usize },
+    WrongOrientation { part: usize },
+    TooFewPoints { part: usize },
+}
+
+/// Problems are told apart by their parts and not their points,
+/// since simplifying keeps the parts but not the points.
+fn broken(parts: &[i32], points: &[Point]) -> HashSet<Broken> {
+    polygon_problems(parts, points)
+        .into_iter()
+        .filter_map(|(part, _, problem)| match problem {
+            Problem::SelfIntersection { other_part, .. } => {
+                Some(Broken::Crossing { part, other_part })
+            }
+            Problem::WrongOrientation => Some(Broken::WrongOrientation { part }),
+            Problem::TooFewPoints => Some(Broken::TooFewPoints { part }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Keeps the points of each part given by `keep`,
+/// giving the parts and the indices of the points kept.
+fn simplify_parts(
+    parts: &[i32],
+    points: &[Point],
+    keep: impl Fn(&[Point]) -> Vec<usize>,
+) -> (Vec<i32>, Vec<usize>) {
+    join(part_ranges(parts, points.len()).map(|range| {
+        let start = range.start;
+        keep(&points[range])
+            .into_iter()
+            .map(|index| start + index)
+            .collect()
+    }))
+}
+
+/// Indices of the points of the ring to keep.
+fn simplify_ring(ring: &[Point], algorithm: Algorithm, tolerance: f64) -> Vec<usize> {
+    // Three distinct points and the first repeated
+    const SMALLEST: usize = 4;
+
+    if ring.len() <= SMALLEST {
+        return (0..ring.len()).collect();
+    }
+
+    match algorithm {
+        Algorithm::VisvalingamWhyatt => visvalingam_whyatt(ring, tolerance, SMALLEST),
+        Algorithm::DouglasPeucker => {
+            // The ring starts and ends at the same point, so split it at the point furthest away
+            // and simplify both halves
+            let first = ring[0];
+            let furthest = (1..ring.len() - 1)
+                .max_by(|a, b| distance(first, ring[*a]).total_cmp(&distance(first, ring[*b])))
+                .unwrap_or(0);
+
+            let mut keep = douglas_peucker(&ring[..=furthest], tolerance);
+            keep.pop();
+            keep.extend(
+                douglas_peucker(&ring[furthest..], tolerance)
+                    .into_iter()
+                    .map(|index| index + furthest),
+            );
+
+            if keep.len() < SMALLEST {
+                let widest = (1..ring.len() - 1)
+                    .filter(|index| *index != furthest)
+                    .max_by(|a, b| {
+                        let distance =
+                            |i: &usize| segment_distance(ring[*i], first, ring[furthest]);
+                        distance(a).total_cmp(&distance(b))
+                    });
+                keep.extend(widest);
+                keep.sort_unstable();
+            }
+
+            keep
+        }
+    }
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    (b.x - a.x).hypot(b.y - a.y)
+}
+
+/// The distance from the point to the closest point on the segment.
+fn segment_distance(point: Point, start: Point, end: Point) -> f64 {
+    let (dx, dy) = (end.x - start.x, end.y - start.y);
+    let length_sq = dx * dx + dy * dy;
+    if length_sq == 0. {
+        return distance(point, start);
+    }
+
+    let t = (((point.x - start.x) * dx + (point.y - start.y) * dy) / length_sq).clamp(0., 1.);
+    distance(
+        point,
+        Point {
+            x: start.x + t * dx,
+            y: start.y + t * dy,
+        },
+    )
+}
+
+/// Indices of the points to keep, always the first and the last.
+fn douglas_peucker(points: &[Point], tolerance: f64) -> Vec<usize> {
+    let n = points.len();
+    if n <= 2 {
+        return (0..n).collect();
+    }
+
+    let mut keep = vec![false; n];
+    (keep[0], keep[n - 1]) = (true, true);
+
+    let mut segments = vec![(0, n - 1)];
+    while let Some((start, end)) = segments.pop() {
+        let furthest = (start + 1..end)
+            .map(|i| (i, segment_distance(points[i], points[start], points[end])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        if let Some((i, d)) = furthest {
+            if d > tolerance {
+                keep[i] = true;
+                segments.extend([(start, i), (i, end)]);
+            }
+        }
+    }
+
+    (0..n).filter(|i| keep[*i]).collect()
+}
+
+/// A point which may be dropped, smallest area first.
+#[derive(PartialEq)]
+struct Candidate {
+    area: f64,
+    index: usize,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .area
+            .total_cmp(&self.area)
+            .then(other.index.cmp(&self.index))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Indices of the points to keep, always the first and the last and at least `smallest` points.
+fn visvalingam_whyatt(points: &[Point], tolerance: f64, smallest: usize) -> Vec<usize> {
+    let n = points.len();
+    if n <= smallest.max(2) {
+        return (0..n).collect();
+    }
+
+    let min_area = tolerance * tolerance;
+    let triangle = |a: usize, b: usize, c: usize| cross(points[a], points[b], points[c]).abs() / 2.;
+
+    let mut previous: Vec<usize> = (0..n).map(|i| i.saturating_sub(1)).collect();
+    let mut next: Vec<usize> = (0..n).map(|i| (i + 1).min(n - 1)).collect();
+    let mut dropped = vec![false; n];
+
+    // The ends are always kept
+    let mut areas: Vec<f64> = (0..n)
+        .map(|i| match i {
+            0 => f64::INFINITY,
+            i if i == n - 1 => f64::INFINITY,
+            i => triangle(i - 1, i, i + 1),
+        })
+        .collect();
+    let mut candidates: BinaryHeap<Candidate> = (1..n - 1)
+        .map(|index| Candidate {
+            area: areas[index],
+            index,
+        })
+        .collect();
+
+    let mut remaining = n;
+    while let Some(Candidate { area, index }) = candidates.pop() {
+        // Left behind when the area changed
+        if dropped[index] || area != areas[index] {
+            continue;
+        }
+        if area >= min_area || remaining <= smallest {
+            break;
+        }
+
+        dropped[index] = true;
+        remaining -= 1;
+
+        let (before, after) = (previous[index], next[index]);
+        next[before] = after;
+        previous[after] = before;
+
+        for neighbour in [before, after] {
+            if neighbour == 0 || neighbour == n - 1 {
+                continue;
+            }
+
+            // Never less than the area of the dropped point, so points are dropped in order
+            areas[neighbour] = triangle(previous[neighbour], neighbour, next[neighbour]).max(area);
+            candidates.push(Candidate {
+                area: areas[neighbour],
+                index: neighbour,
+            });
+        }
+    }
+
+    (0..n).filter(|i| !dropped[*i]).collect()
+}
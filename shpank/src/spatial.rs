@@ -1,10 +1,11 @@
-use std::{path::Path, str::FromStr};
+use std::{fs, path::Path, str::FromStr};
 
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    dbase::{DbaseFile, DbaseRecord},
-    parse::{self, Error, Result},
+    crs::Crs,
+    dbase::{DbaseFile, DbaseRecord, DeletedRecords},
+    parse::{self, Error, Recovery, Result},
     shape::{Shape, ShpFile, ShpRecord},
 };
 
@@ -14,22 +15,92 @@ use crate::{
 pub struct Spatial {
     pub shp: ShpFile,
     pub dbf: DbaseFile,
+
+    /// Positions in the files (starting at 0) of record pairs left out
+    /// because the `.dbf` row was marked as deleted
+    pub deleted: Vec<usize>,
+
+    /// From the `.prj` file next to the `.shp`, if there is one which can be parsed
+    pub crs: Option<Crs>,
+
+    /// Problems which did not stop the files from being read,
+    /// e.g. a `.prj` file which can't be parsed
+    pub warnings: Vec<String>,
 }
 
 impl Spatial {
     pub fn new<P: AsRef<Path>>(shp: P, dbf: P) -> Result<Self> {
-        let shp = parse::Parser::parse_shp_file(shp)?;
-        let dbf = parse::Parser::parse_dbf_file(dbf)?;
+        Self::with_recovery(shp, dbf, Recovery::Strict)
+    }
+
+    /// Like [`Spatial::new`], but corrupt `.shp` records may be skipped.
+    /// The `.dbf` rows of skipped records are removed as well to keep the records paired.
+    pub fn with_recovery<P: AsRef<Path>>(shp: P, dbf: P, recovery: Recovery) -> Result<Self> {
+        let prj = shp.as_ref().with_extension("prj");
+        // Lossily, since names in other encodings than UTF-8 are not needed to parse it
+        let prj = prj.is_file().then(|| fs::read(prj)).transpose()?;
+        let prj = prj.map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+
+        let dbf = parse::Parser::parse_dbf_file_with(dbf, DeletedRecords::IncludeFlagged)?;
+        // Every row has a record, so all of them are known to be lost if parsing has to stop
+        let shp = parse::Parser::new_shp(shp, recovery)?
+            .with_num_records(dbf.records.len())
+            .impl_parse_shp()?;
+
+        Self::pair(shp, dbf, prj)
+    }
+
+    /// Pairs up the records, see [`Spatial::with_recovery`].
+    /// The `.dbf` must be parsed with [`DeletedRecords::IncludeFlagged`].
+    ///
+    /// The shapes can be used without knowing their coordinate system,
+    /// so a `.prj` which can't be parsed gives a warning rather than an error.
+    /// Warnings from parsing the `.dbf` are kept as well.
+    pub(crate) fn pair(mut shp: ShpFile, mut dbf: DbaseFile, prj: Option<String>) -> Result<Self> {
+        let rows: Vec<_> = dbf
+            .records
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| shp.dropped.binary_search(index).is_err())
+            .collect();
 
         let shp_num = shp.records.len();
-        let dbf_num = dbf.records.len();
+        let dbf_num = rows.len();
         if shp_num != dbf_num {
             return Err(Error::UnexpectedData(format!(
                 "Shapefile # records not equal to dBASE: {shp_num} vs {dbf_num}"
             )));
         }
 
-        Ok(Self { shp, dbf })
+        // A deleted row means the shape it describes is deleted too
+        let mut deleted = vec![];
+        let mut shp_records = Vec::with_capacity(shp_num);
+        let mut dbf_records = Vec::with_capacity(dbf_num);
+        for ((index, row), record) in rows.into_iter().zip(shp.records) {
+            if row.deleted {
+                deleted.push(index);
+            } else {
+                shp_records.push(record);
+                dbf_records.push(row);
+            }
+        }
+        shp.records = shp_records;
+        dbf.records = dbf_records;
+
+        let mut warnings = std::mem::take(&mut dbf.warnings);
+        let crs = prj.and_then(|prj| {
+            prj.parse::<Crs>()
+                .map_err(|e| warnings.push(format!("Ignoring the .prj file: {e}")))
+                .ok()
+        });
+
+        Ok(Self {
+            shp,
+            dbf,
+            deleted,
+            crs,
+            warnings,
+        })
     }
 
     pub fn into_objects(self) -> Vec<Object> {
@@ -47,8 +118,9 @@ impl Spatial {
         self.records()
             .map(|(shp, dbf)| Object {
                 shape: shp.shape.clone(),
-                fclass: FromStr::from_str(&dbf.entries[fclass_idx]).expect("expected known fclass"),
-                name: dbf.entries[name_idx].clone(),
+                fclass: FromStr::from_str(dbf.entries[fclass_idx].as_str().unwrap_or_default())
+                    .expect("expected known fclass"),
+                name: dbf.entries[name_idx].to_string(),
             })
             .collect()
     }
@@ -66,11 +138,16 @@ impl Spatial {
             .expect("dBASE without name unhandled");
 
         self.records()
-            .filter(|(_, dbf)| !dbf.entries[name_idx].is_empty())
+            .filter(|(_, dbf)| {
+                dbf.entries[name_idx]
+                    .as_str()
+                    .is_some_and(|name| !name.is_empty())
+            })
             .map(|(shp, dbf)| Object {
                 shape: shp.shape.clone(),
-                fclass: FromStr::from_str(&dbf.entries[fclass_idx]).expect("expected known fclass"),
-                name: dbf.entries[name_idx].clone(),
+                fclass: FromStr::from_str(dbf.entries[fclass_idx].as_str().unwrap_or_default())
+                    .expect("expected known fclass"),
+                name: dbf.entries[name_idx].to_string(),
             })
             .collect()
     }
@@ -81,7 +158,7 @@ impl Spatial {
         self.shp.records.len()
     }
 
-    pub fn records(&self) -> RecordsIterator {
+    pub fn records(&self) -> RecordsIterator<'_> {
         RecordsIterator {
             spatial: self,
             index: 0,
@@ -118,6 +195,65 @@ impl<'a> Iterator for RecordsIterator<'a> {
     }
 }
 
+/// Pairs up `.shp` and `.dbf` records streamed one at a time,
+/// such as from [`parse::Parser::shp_records`] and [`parse::Parser::dbf_records`].
+///
+/// Unlike [`Iterator::zip`] a record left over in either file is an error,
+/// after which the iterator ends.
+pub struct RecordPairs<S, D> {
+    shp: S,
+    dbf: D,
+    num_pairs: usize,
+    done: bool,
+}
+
+impl<S, D> RecordPairs<S, D>
+where
+    S: Iterator<Item = Result<ShpRecord>>,
+    D: Iterator<Item = Result<DbaseRecord>>,
+{
+    pub fn new(shp: S, dbf: D) -> Self {
+        Self {
+            shp,
+            dbf,
+            num_pairs: 0,
+            done: false,
+        }
+    }
+}
+
+impl<S, D> Iterator for RecordPairs<S, D>
+where
+    S: Iterator<Item = Result<ShpRecord>>,
+    D: Iterator<Item = Result<DbaseRecord>>,
+{
+    type Item = Result<(ShpRecord, DbaseRecord)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let num_pairs = self.num_pairs;
+        let more = |file| {
+            Error::UnexpectedData(format!(
+                "Shapefile # records not equal to dBASE: the {file} has more than {num_pairs} records"
+            ))
+        };
+
+        let pair = match (self.shp.next(), self.dbf.next()) {
+            (None, None) => None,
+            (Some(shp), Some(dbf)) => Some(shp.and_then(|shp| Ok((shp, dbf?)))),
+            (Some(_), None) => Some(Err(more(".shp"))),
+            (None, Some(_)) => Some(Err(more(".dbf"))),
+        };
+
+        self.done = !matches!(pair, Some(Ok(_)));
+        self.num_pairs += 1;
+        pair
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Fclass {
     Airport,
@@ -0,0 +1,240 @@
+//! Ear clipping triangulation of polygons with holes.
+
+/// An indexed triangle list.
+#[derive(Debug, Clone, Default)]
+pub struct TriangleMesh {
+    pub positions: Vec<[f64; 3]>,
+
+    /// Three indices into `positions` per triangle
+    pub indices: Vec<u32>,
+}
+
+type Vertex = [f64; 2];
+
+/// Twice the signed area of the triangle, positive if counter-clockwise.
+fn cross(a: Vertex, b: Vertex, c: Vertex) -> f64 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+/// Twice the signed area of the ring, positive if counter-clockwise.
+pub fn signed_area(ring: &[Vertex]) -> f64 {
+    (0..ring.len())
+        .map(|i| {
+            let [x0, y0] = ring[i];
+            let [x1, y1] = ring[(i + 1) % ring.len()];
+            x0 * y1 - x1 * y0
+        })
+        .sum()
+}
+
+fn inside_triangle(p: Vertex, a: Vertex, b: Vertex, c: Vertex) -> bool {
+    cross(a, b, p) >= 0. && cross(b, c, p) >= 0. && cross(c, a, p) >= 0.
+}
+
+/// Whether the segments `a-b` and `c-d` cross each other,
+/// not counting touching at endpoints.
+fn segments_cross(a: Vertex, b: Vertex, c: Vertex, d: Vertex) -> bool {
+    let d1 = cross(c, d, a);
+    let d2 = cross(c, d, b);
+    let d3 = cross(a, b, c);
+    let d4 = cross(a, b, d);
+
+    ((d1 > 0. && d2 < 0.) || (d1 < 0. && d2 > 0.)) && ((d3 > 0. && d4 < 0.) || (d3 < 0. && d4 > 0.))
+}
+
+/// Index into the concatenated input rings along with the position.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    index: usize,
+    position: Vertex,
+}
+
+fn nodes(ring: &[Vertex], offset: usize, counter_clockwise: bool) -> Vec<Node> {
+    let mut nodes: Vec<_> = ring
+        .iter()
+        .enumerate()
+        .map(|(i, position)| Node {
+            index: offset + i,
+            position: *position,
+        })
+        .collect();
+
+    if (signed_area(ring) > 0.) != counter_clockwise {
+        nodes.reverse();
+    }
+
+    nodes
+}
+
+/// Connects the hole to the outer ring via a bridge from the hole's rightmost vertex
+/// to the closest outer vertex it can see, turning both into a single ring.
+fn bridge_hole(outer: &mut Vec<Node>, hole: &[Node], other_holes: &[Vec<Node>]) {
+    let Some(start) =
+        (0..hole.len()).max_by(|a, b| hole[*a].position[0].total_cmp(&hole[*b].position[0]))
+    else {
+        return;
+    };
+    let m = hole[start].position;
+
+    let edges = |ring: &[Node]| -> Vec<(Vertex, Vertex)> {
+        (0..ring.len())
+            .map(|i| (ring[i].position, ring[(i + 1) % ring.len()].position))
+            .collect()
+    };
+    let mut blockers = edges(outer);
+    blockers.extend(edges(hole));
+    for other in other_holes {
+        blockers.extend(edges(other));
+    }
+
+    let distance = |p: Vertex| (p[0] - m[0]).powi(2) + (p[1] - m[1]).powi(2);
+    let mut candidates: Vec<usize> = (0..outer.len()).collect();
+    candidates
+        .sort_by(|a, b| distance(outer[*a].position).total_cmp(&distance(outer[*b].position)));
+
+    let visible = candidates.iter().copied().find(|candidate| {
+        let p = outer[*candidate].position;
+        !blockers.iter().any(|(a, b)| segments_cross(m, p, *a, *b))
+    });
+    let Some(bridge) = visible.or(candidates.first().copied()) else {
+        return;
+    };
+
+    // outer[..=bridge], hole from start around back to start, outer[bridge..]
+    let mut spliced = Vec::with_capacity(outer.len() + hole.len() + 2);
+    spliced.extend_from_slice(&outer[..=bridge]);
+    spliced.extend((0..=hole.len()).map(|i| hole[(start + i) % hole.len()]));
+    spliced.extend_from_slice(&outer[bridge..]);
+
+    *outer = spliced;
+}
+
+fn clip_ears(mut ring: Vec<Node>) -> Vec<[usize; 3]> {
+    let mut triangles = Vec::with_capacity(ring.len().saturating_sub(2));
+
+    while ring.len() > 3 {
+        let n = ring.len();
+
+        let ear = (0..n).find(|i| {
+            let (a, b, c) = (ring[(i + n - 1) % n], ring[*i], ring[(i + 1) % n]);
+            let (pa, pb, pc) = (a.position, b.position, c.position);
+
+            if cross(pa, pb, pc) <= 0. {
+                return false;
+            }
+
+            !ring.iter().any(|other| {
+                let p = other.position;
+                p != pa && p != pb && p != pc && inside_triangle(p, pa, pb, pc)
+            })
+        });
+
+        match ear {
+            Some(i) => {
+                let (a, b, c) = (ring[(i + n - 1) % n], ring[i], ring[(i + 1) % n]);
+                triangles.push([a.index, b.index, c.index]);
+                ring.remove(i);
+            }
+            // Degenerate input (e.g. collinear or self-intersecting) may have no ears left,
+            // drop a vertex to always terminate
+            None => {
+                ring.remove(0);
+            }
+        }
+    }
+
+    if let [a, b, c] = ring[..] {
+        if cross(a.position, b.position, c.position) > 0. {
+            triangles.push([a.index, b.index, c.index]);
+        }
+    }
+
+    triangles
+}
+
+/// Triangulates a polygon given as an exterior ring and holes.
+/// Rings should not repeat their first vertex at the end.
+///
+/// The returned triangles index into the rings as if they were concatenated,
+/// exterior first.
+/// They are wound the same way as the exterior ring.
+pub fn triangulate_polygon(exterior: &[Vertex], holes: &[Vec<Vertex>]) -> Vec<[usize; 3]> {
+    if exterior.len() < 3 {
+        return vec![];
+    }
+
+    let mut outer = nodes(exterior, 0, true);
+
+    let mut offset = exterior.len();
+    let mut hole_nodes = vec![];
+    for hole in holes {
+        if hole.len() >= 3 {
+            hole_nodes.push(nodes(hole, offset, false));
+        }
+        offset += hole.len();
+    }
+
+    // Rightmost holes first, so bridges from later holes can use earlier ones
+    hole_nodes.sort_by(|a, b| {
+        let max_x = |ring: &[Node]| {
+            ring.iter()
+                .map(|n| n.position[0])
+                .fold(f64::NEG_INFINITY, f64::max)
+        };
+        max_x(b).total_cmp(&max_x(a))
+    });
+
+    for i in 0..hole_nodes.len() {
+        bridge_hole(&mut outer, &hole_nodes[i], &hole_nodes[i + 1..]);
+    }
+
+    let mut triangles = clip_ears(outer);
+
+    if signed_area(exterior) < 0. {
+        for [_, b, c] in &mut triangles {
+            std::mem::swap(b, c);
+        }
+    }
+
+    triangles
+}
+
+/// Newell's method: The (unnormalized) normal of a possibly non-planar ring.
+fn newell_normal(ring: &[[f64; 3]]) -> [f64; 3] {
+    let mut normal = [0.; 3];
+
+    for i in 0..ring.len() {
+        let [x0, y0, z0] = ring[i];
+        let [x1, y1, z1] = ring[(i + 1) % ring.len()];
+
+        normal[0] += (y0 - y1) * (z0 + z1);
+        normal[1] += (z0 - z1) * (x0 + x1);
+        normal[2] += (x0 - x1) * (y0 + y1);
+    }
+
+    normal
+}
+
+/// Triangulates a polygon in 3D by projecting it onto the
+/// axis aligned plane it is the most parallel to.
+///
+/// See [`triangulate_polygon`].
+pub fn triangulate_polygon_3d(exterior: &[[f64; 3]], holes: &[Vec<[f64; 3]>]) -> Vec<[usize; 3]> {
+    let normal = newell_normal(exterior).map(f64::abs);
+
+    let project: fn(&[f64; 3]) -> Vertex = if normal[2] >= normal[0] && normal[2] >= normal[1] {
+        |[x, y, _]| [*x, *y]
+    } else if normal[1] >= normal[0] {
+        |[x, _, z]| [*z, *x]
+    } else {
+        |[_, y, z]| [*y, *z]
+    };
+
+    let exterior: Vec<_> = exterior.iter().map(project).collect();
+    let holes: Vec<Vec<_>> = holes
+        .iter()
+        .map(|hole| hole.iter().map(project).collect())
+        .collect();
+
+    triangulate_polygon(&exterior, &holes)
+}
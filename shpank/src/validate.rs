@@ -0,0 +1,469 @@
+//! Finding problems in polygons and lines, and repairing them.
+//!
+//! Data exported from OpenStreetMap, such as the Geofabrik downloads, has rings which are not closed,
+//! repeat points, go out to a point and straight back, or cross themselves.
+//! These trip up triangulation and the measures in [`crate::geometry`].
+//!
+//! Polygons and lines are checked, with or without Z and M values.
+//! Only [`Polygon`] and [`PolyLine`] shapes are repaired, see [`Shape::can_repair`].
+
+use std::fmt;
+
+use crate::{
+    rings::{ring_within, signed_area, Orientation},
+    shape::{part_ranges, MinimumBoundingRectangle, Point, PolyLine, Polygon, Shape},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Problem {
+    /// The point is the same as the one before it
+    DuplicatePoint,
+
+    /// The last point of the ring is not the same as the first
+    UnclosedRing,
+
+    /// Rings need three distinct points, and lines two
+    TooFewPoints,
+
+    /// The ring goes out to the point and straight back, enclosing nothing
+    Spike,
+
+    /// The edge starting at the point crosses another edge of the shape
+    SelfIntersection {
+        other_part: usize,
+        other_vertex: usize,
+        at: Point,
+    },
+
+    /// Outer rings should be clockwise and holes counter-clockwise.
+    /// A ring inside an odd number of other rings is a hole.
+    WrongOrientation,
+}
+
+/// A problem found by [`Shape::validate`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Issue {
+    /// Starting at 1
+    pub record_number: i32,
+
+    /// Index of the ring or line among the parts of the shape
+    pub part: usize,
+
+    /// Index of the point within the part
+    pub vertex: usize,
+
+    pub problem: Problem,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            record_number,
+            part,
+            vertex,
+            problem,
+        } = self;
+
+        write!(f, "record {record_number}, part {part}, point {vertex}: ")?;
+        match problem {
+            Problem::DuplicatePoint => write!(f, "duplicate point"),
+            Problem::UnclosedRing => write!(f, "ring is not closed"),
+            Problem::TooFewPoints => write!(f, "too few points"),
+            Problem::Spike => write!(f, "spike"),
+            Problem::SelfIntersection {
+                other_part,
+                other_vertex,
+                at,
+            } => write!(
+                f,
+                "crosses part {other_part}, point {other_vertex} at ({}, {})",
+                at.x, at.y
+            ),
+            Problem::WrongOrientation => write!(f, "wrong orientation"),
+        }
+    }
+}
+
+impl Shape {
+    /// The problems with a polygon or line.
+    /// The record number is only used to fill in the issues.
+    pub fn validate(&self, record_number: i32) -> Vec<Issue> {
+        let problems = match self {
+            Shape::Polygon(polygon) => polygon_problems(&polygon.parts, &polygon.points),
+            Shape::PolygonZ(polygon) => polygon_problems(&polygon.parts, &polygon.points),
+            Shape::PolygonM(polygon) => polygon_problems(&polygon.parts, &polygon.points),
+            Shape::PolyLine(line) => line_problems(&line.parts, &line.points),
+            Shape::PolylineZ(line) => line_problems(&line.parts, &line.points),
+            Shape::PolylineM(line) => line_problems(&line.parts, &line.points),
+            _ => vec![],
+        };
+
+        problems
+            .into_iter()
+            .map(|(part, vertex, problem)| Issue {
+                record_number,
+                part,
+                vertex,
+                problem,
+            })
+            .collect()
+    }
+
+    /// Whether [`Shape::repair`] repairs the problems found by [`Shape::validate`].
+    /// Polygons and lines with Z or M values are not repaired,
+    /// since the values of the points a repair adds where rings cross are not known.
+    pub fn can_repair(&self) -> bool {
+        !matches!(
+            self,
+            Shape::PolygonZ(_) | Shape::PolygonM(_) | Shape::PolylineZ(_) | Shape::PolylineM(_)
+        )
+    }
+
+    /// Repairs polygons and lines, see [`Polygon::repair`] and [`PolyLine::repair`].
+    /// Shapes left without any points become [`Shape::Null`].
+    /// Other shapes are left as they are, see [`Shape::can_repair`].
+    pub fn repair(&mut self) {
+        let empty = match self {
+            Shape::Polygon(polygon) => {
+                polygon.repair();
+                polygon.points.is_empty()
+            }
+            Shape::PolyLine(line) => {
+                line.repair();
+                line.points.is_empty()
+            }
+            _ => false,
+        };
+
+        if empty {
+            *self = Shape::Null;
+        }
+    }
+}
+
+impl Polygon {
+    /// Closes rings, drops duplicate points and spikes, and drops rings with too few points.
+    /// Rings crossing themselves are split where they cross, e.g. a bow-tie becomes two triangles.
+    /// Then rings are oriented by whether they are holes.
+    ///
+    /// Rings crossing other rings are left as they are.
+    pub fn repair(&mut self) {
+        let mut rings: Vec<Vec<Point>> = part_ranges(&self.parts, self.points.len())
+            .flat_map(|range| repair_ring(open_ring(&self.points[range])))
+            .collect();
+
+        for index in 0..rings.len() {
+            let depth = rings
+                .iter()
+                .enumerate()
+                .filter(|(other, ring)| *other != index && ring_within(&rings[index], ring))
+                .count();
+            let expected = match depth % 2 {
+                0 => Orientation::Clockwise,
+                _ => Orientation::CounterClockwise,
+            };
+
+            if Orientation::of(&rings[index]) != expected {
+                rings[index].reverse();
+            }
+        }
+
+        for ring in &mut rings {
+            ring.push(ring[0]);
+        }
+        (self.parts, self.points) = join(rings);
+        if let Some(mbr) = MinimumBoundingRectangle::from_points(&self.points) {
+            self.mbr = mbr;
+        }
+    }
+}
+
+impl PolyLine {
+    /// Drops duplicate points, and lines with too few points.
+    pub fn repair(&mut self) {
+        let lines: Vec<_> = part_ranges(&self.parts, self.points.len())
+            .map(|range| dedup(&self.points[range]))
+            .filter(|line| line.len() >= 2)
+            .collect();
+
+        (self.parts, self.points) = join(lines);
+        if let Some(mbr) = MinimumBoundingRectangle::from_points(&self.points) {
+            self.mbr = mbr;
+        }
+    }
+}
+
+/// Parts and points for the shape made up of the given parts.
+pub(crate) fn join<T>(parts: impl IntoIterator<Item = Vec<T>>) -> (Vec<i32>, Vec<T>) {
+    let mut starts = vec![];
+    let mut points = vec![];
+
+    for part in parts {
+        starts.push(points.len() as i32);
+        points.extend(part);
+    }
+
+    (starts, points)
+}
+
+fn dedup(points: &[Point]) -> Vec<Point> {
+    let mut points = points.to_vec();
+    points.dedup();
+    points
+}
+
+/// The distinct points of the ring, without the first point repeated at the end.
+pub(crate) fn open_ring(ring: &[Point]) -> Vec<Point> {
+    let mut ring = dedup(ring);
+    while ring.len() > 1 && ring.first() == ring.last() {
+        ring.pop();
+    }
+    ring
+}
+
+/// Twice the signed area of the triangle, positive if counter-clockwise.
+pub(crate) fn cross(a: Point, b: Point, c: Point) -> f64 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+/// Whether the vertex is the tip of a spike, with both neighbours in the same direction from it.
+fn is_spike(previous: Point, vertex: Point, next: Point) -> bool {
+    let dot = (previous.x - vertex.x) * (next.x - vertex.x)
+        + (previous.y - vertex.y) * (next.y - vertex.y);
+
+    cross(previous, vertex, next) == 0. && dot > 0.
+}
+
+/// Where the edges cross, if they do at a point inside both.
+/// Edges which only touch or run along each other do not cross.
+fn crossing([a, b]: [Point; 2], [c, d]: [Point; 2]) -> Option<Point> {
+    let (abc, abd) = (cross(a, b, c), cross(a, b, d));
+    let (cda, cdb) = (cross(c, d, a), cross(c, d, b));
+
+    if abc * abd < 0. && cda * cdb < 0. {
+        let t = cda / (cda - cdb);
+        Some(Point {
+            x: a.x + t * (b.x - a.x),
+            y: a.y + t * (b.y - a.y),
+        })
+    } else {
+        None
+    }
+}
+
+/// Removes spikes, then splits the ring where it crosses itself.
+/// Takes and gives open rings.
+fn repair_ring(mut ring: Vec<Point>) -> Vec<Vec<Point>> {
+    // Removing a spike may reveal another, e.g. a spike along several points
+    while let Some(tip) = (0..ring.len()).find(|i| {
+        let n = ring.len();
+        n >= 3 && is_spike(ring[(i + n - 1) % n], ring[*i], ring[(i + 1) % n])
+    }) {
+        ring.remove(tip);
+        ring = open_ring(&ring);
+    }
+
+    if ring.len() < 3 {
+        return vec![];
+    }
+
+    let crossed = crossings(&[&ring]).first().copied();
+
+    // Checked after splitting, since a symmetric bow-tie has no area
+    let Some(([(_, i), (_, j)], at)) = crossed else {
+        return match signed_area(&ring) == 0. {
+            true => vec![],
+            false => vec![ring],
+        };
+    };
+
+    // One ring goes around to the crossing and continues after the other edge,
+    // the other is the loop in between. Both have fewer points than before.
+    let mut first = ring[..=i].to_vec();
+    first.push(at);
+    first.extend_from_slice(&ring[j + 1..]);
+
+    let mut second = vec![at];
+    second.extend_from_slice(&ring[i + 1..=j]);
+
+    let mut rings = repair_ring(open_ring(&first));
+    rings.extend(repair_ring(open_ring(&second)));
+    rings
+}
+
+/// The part, the point within the part, and what is wrong there.
+pub(crate) type Found = (usize, usize, Problem);
+
+fn line_problems(parts: &[i32], points: &[Point]) -> Vec<Found> {
+    let mut found = vec![];
+
+    for (part, range) in part_ranges(parts, points.len()).enumerate() {
+        let line = &points[range];
+        found.extend(duplicates(line).map(|vertex| (part, vertex, Problem::DuplicatePoint)));
+
+        if dedup(line).len() < 2 {
+            found.push((part, 0, Problem::TooFewPoints));
+        }
+    }
+
+    found
+}
+
+fn duplicates(points: &[Point]) -> impl Iterator<Item = usize> + '_ {
+    (1..points.len()).filter(|i| points[*i] == points[i - 1])
+}
+
+/// A ring without repeated points, along with the index of each point in the part.
+struct Distinct {
+    part: usize,
+    points: Vec<Point>,
+    vertices: Vec<usize>,
+}
+
+pub(crate) fn polygon_problems(parts: &[i32], points: &[Point]) -> Vec<Found> {
+    let mut found = vec![];
+    let mut rings = vec![];
+
+    for (part, range) in part_ranges(parts, points.len()).enumerate() {
+        let ring = &points[range];
+        found.extend(duplicates(ring).map(|vertex| (part, vertex, Problem::DuplicatePoint)));
+
+        if ring.len() > 1 && ring.first() != ring.last() {
+            found.push((part, ring.len() - 1, Problem::UnclosedRing));
+        }
+
+        let mut distinct = Distinct {
+            part,
+            points: vec![],
+            vertices: vec![],
+        };
+        for (vertex, point) in ring.iter().enumerate() {
+            if distinct.points.last() != Some(point) {
+                distinct.points.push(*point);
+                distinct.vertices.push(vertex);
+            }
+        }
+        while distinct.points.len() > 1 && distinct.points.first() == distinct.points.last() {
+            distinct.points.pop();
+            distinct.vertices.pop();
+        }
+
+        if distinct.points.len() < 3 {
+            found.push((part, 0, Problem::TooFewPoints));
+            continue;
+        }
+
+        let n = distinct.points.len();
+        for i in 0..n {
+            let [previous, vertex, next] =
+                [(i + n - 1) % n, i, (i + 1) % n].map(|i| distinct.points[i]);
+            if is_spike(previous, vertex, next) {
+                found.push((part, distinct.vertices[i], Problem::Spike));
+            }
+        }
+
+        rings.push(distinct);
+    }
+
+    for (index, ring) in rings.iter().enumerate() {
+        if signed_area(&ring.points) == 0. {
+            continue;
+        }
+
+        let depth = rings
+            .iter()
+            .enumerate()
+            .filter(|(other, other_ring)| {
+                *other != index && ring_within(&ring.points, &other_ring.points)
+            })
+            .count();
+        let expected = match depth % 2 {
+            0 => Orientation::Clockwise,
+            _ => Orientation::CounterClockwise,
+        };
+
+        if Orientation::of(&ring.points) != expected {
+            found.push((ring.part, 0, Problem::WrongOrientation));
+        }
+    }
+
+    found.extend(self_intersections(&rings));
+    found
+}
+
+fn self_intersections(rings: &[Distinct]) -> Vec<Found> {
+    let points: Vec<&[Point]> = rings.iter().map(|ring| &ring.points[..]).collect();
+
+    crossings(&points)
+        .into_iter()
+        .map(|([(a, a_index), (b, b_index)], at)| {
+            let (ring_a, ring_b) = (&rings[a], &rings[b]);
+            (
+                ring_a.part,
+                ring_a.vertices[a_index],
+                Problem::SelfIntersection {
+                    other_part: ring_b.part,
+                    other_vertex: ring_b.vertices[b_index],
+                    at,
+                },
+            )
+        })
+        .collect()
+}
+
+/// Two edges which cross, each given by its ring and the index of its first point,
+/// and where they cross.
+type Crossing = ([(usize, usize); 2], Point);
+
+/// Edges of the open rings which cross, found by sweeping along X
+/// so only edges overlapping in X are compared.
+/// The edge which comes first in the shape is given first, and the crossings are in that order.
+fn crossings(rings: &[&[Point]]) -> Vec<Crossing> {
+    struct Edge {
+        ring: usize,
+        index: usize,
+        points: [Point; 2],
+        x: (f64, f64),
+    }
+
+    let mut edges: Vec<Edge> = rings
+        .iter()
+        .enumerate()
+        .flat_map(|(ring_index, ring)| {
+            let n = ring.len();
+            (0..n).map(move |index| {
+                let points = [ring[index], ring[(index + 1) % n]];
+                Edge {
+                    ring: ring_index,
+                    index,
+                    points,
+                    x: (points[0].x.min(points[1].x), points[0].x.max(points[1].x)),
+                }
+            })
+        })
+        .collect();
+    edges.sort_by(|a, b| a.x.0.total_cmp(&b.x.0));
+
+    let neighbours = |a: &Edge, b: &Edge| {
+        let n = rings[a.ring].len();
+        a.ring == b.ring && (a.index.abs_diff(b.index) == 1 || a.index.abs_diff(b.index) == n - 1)
+    };
+
+    let mut found = vec![];
+    for (i, a) in edges.iter().enumerate() {
+        for b in edges[i + 1..].iter().take_while(|b| b.x.0 <= a.x.1) {
+            if neighbours(a, b) {
+                continue;
+            }
+
+            if let Some(at) = crossing(a.points, b.points) {
+                // Reported on the edge which comes first in the shape
+                let (a, b) = ((a.ring, a.index), (b.ring, b.index));
+                found.push(([a.min(b), a.max(b)], at));
+            }
+        }
+    }
+
+    found.sort_by_key(|(edges, _)| *edges);
+    found
+}
@@ -0,0 +1,407 @@
+//! Borrowed access to the bytes of a `.shp` file, typically memory mapped.
+//!
+//! Nothing is decoded up front: Records are found by walking the record headers
+//! (or looked up via the `.shx` index), and bounding boxes, parts and points
+//! are read from the underlying bytes only when asked for.
+
+use std::{fs::File, ops::Deref, path::Path};
+
+use memmap2::Mmap;
+
+use crate::{
+    parse::{Error, Parser, Result},
+    shape::{MinimumBoundingRectangle, Point, Shape, ShapeType, ShpHeader, ShpLength},
+    shx::ShxRecord,
+};
+
+const HEADER_BYTES: usize = 100;
+
+/// A `.shp` file mapped into memory.
+pub struct ShpMmap {
+    mmap: Mmap,
+}
+
+impl ShpMmap {
+    /// Maps the file read-only.
+    ///
+    /// The file must not be modified by anyone while it is mapped,
+    /// since the views handed out assume the bytes do not change.
+    pub fn open<P: AsRef<Path>>(shp_path: P) -> Result<Self> {
+        let file = File::open(shp_path.as_ref())?;
+        // SAFETY: See above, we can't protect against other processes changing the file
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        Ok(Self { mmap })
+    }
+
+    pub fn view(&self) -> Result<ShpView<'_>> {
+        ShpView::new(&self.mmap)
+    }
+}
+
+impl Deref for ShpMmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.mmap
+    }
+}
+
+/// A view over the bytes of a whole `.shp` file.
+#[derive(Debug, Clone)]
+pub struct ShpView<'a> {
+    bytes: &'a [u8],
+    header: ShpHeader,
+}
+
+impl<'a> ShpView<'a> {
+    /// Only the file header is parsed.
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        let header = Parser::with_reader(bytes).parse_header()?;
+
+        Ok(Self { bytes, header })
+    }
+
+    pub fn header(&self) -> &ShpHeader {
+        &self.header
+    }
+
+    /// Walks the records from the start of the file.
+    /// Stops after the first error, since the position of the next record is unknown then.
+    pub fn records(&self) -> RecordRefs<'a> {
+        RecordRefs {
+            bytes: self.bytes,
+            offset: HEADER_BYTES,
+            end: self.header.file_length.num_bytes(),
+            failed: false,
+        }
+    }
+
+    /// The record at the location given by an entry in the `.shx` index.
+    pub fn record_at(&self, entry: &ShxRecord) -> Result<RecordRef<'a>> {
+        let record = RecordRef::new(self.bytes, entry.offset.num_bytes())?;
+
+        if record.content_length != entry.content_length.0 {
+            return Err(Error::RecordLengthMismatch {
+                record_number: record.record_number,
+                offset: record.offset,
+                expected: entry.content_length.num_bytes(),
+                actual: ShpLength(record.content_length).num_bytes(),
+            });
+        }
+
+        Ok(record)
+    }
+}
+
+/// Iterator over the records of a [`ShpView`].
+#[derive(Debug, Clone)]
+pub struct RecordRefs<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    end: usize,
+    failed: bool,
+}
+
+impl<'a> Iterator for RecordRefs<'a> {
+    type Item = Result<RecordRef<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.offset >= self.end {
+            return None;
+        }
+
+        let record = RecordRef::new(self.bytes, self.offset);
+        match &record {
+            Ok(record) => self.offset += record.num_bytes(),
+            Err(_) => self.failed = true,
+        }
+
+        Some(record)
+    }
+}
+
+/// Where the parts and points of a record are, relative to the start of the content.
+struct Layout {
+    num_parts: usize,
+    parts_at: usize,
+    num_points: usize,
+    num_points_at: usize,
+    points_at: usize,
+}
+
+/// A single record of a [`ShpView`], borrowing its bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct RecordRef<'a> {
+    /// From the start of the file to the record header
+    offset: usize,
+    record_number: i32,
+
+    /// In 16-bit words
+    content_length: i32,
+
+    /// The record header and content
+    record: &'a [u8],
+    content: &'a [u8],
+}
+
+impl<'a> RecordRef<'a> {
+    fn new(bytes: &'a [u8], offset: usize) -> Result<Self> {
+        let header = read::<8>(bytes, 0, offset)?;
+        let record_number = i32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let content_length = i32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+
+        let content_bytes = usize::try_from(content_length).map_err(|_| Error::BadLength {
+            offset: offset + 4,
+            length: content_length,
+        })? * 2;
+
+        let start = offset + ShxRecord::RECORD_HEADER_BYTES;
+        let record = bytes
+            .get(offset..start + content_bytes)
+            .ok_or(Error::Truncated {
+                offset: start,
+                needed: content_bytes,
+            })?;
+
+        Ok(Self {
+            offset,
+            record_number,
+            content_length,
+            record,
+            content: &record[ShxRecord::RECORD_HEADER_BYTES..],
+        })
+    }
+
+    /// Starting at 1
+    pub fn record_number(&self) -> i32 {
+        self.record_number
+    }
+
+    /// From the start of the file to the record header.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Number of bytes the record takes up, including its header.
+    pub fn num_bytes(&self) -> usize {
+        self.record.len()
+    }
+
+    /// The raw record content, after the record header.
+    pub fn content(&self) -> &'a [u8] {
+        self.content
+    }
+
+    /// From the start of the file to the given position in the content.
+    fn file_offset(&self, at: usize) -> usize {
+        self.offset + ShxRecord::RECORD_HEADER_BYTES + at
+    }
+
+    fn integer(&self, at: usize) -> Result<i32> {
+        read::<4>(self.content, self.file_offset(0), at).map(i32::from_le_bytes)
+    }
+
+    fn double(&self, at: usize) -> Result<f64> {
+        read::<8>(self.content, self.file_offset(0), at).map(f64::from_le_bytes)
+    }
+
+    pub fn shape_type(&self) -> Result<ShapeType> {
+        self.integer(0)?.try_into()
+    }
+
+    /// The bounding box as stored in the record, without looking at the points.
+    /// Points have a bounding box of zero size, and null shapes have none.
+    pub fn mbr(&self) -> Result<Option<MinimumBoundingRectangle>> {
+        Ok(match self.shape_type()? {
+            ShapeType::Null => None,
+            ShapeType::Point | ShapeType::PointZ | ShapeType::PointM => {
+                let (x, y) = (self.double(4)?, self.double(12)?);
+                Some(MinimumBoundingRectangle { x: x..x, y: y..y })
+            }
+            _ => {
+                let [x_min, y_min, x_max, y_max] = [4, 12, 20, 28].map(|at| self.double(at));
+                Some(MinimumBoundingRectangle {
+                    x: x_min?..x_max?,
+                    y: y_min?..y_max?,
+                })
+            }
+        })
+    }
+
+    fn count(&self, at: usize) -> Result<usize> {
+        let count = self.integer(at)?;
+        usize::try_from(count).map_err(|_| Error::BadCount {
+            offset: self.file_offset(at),
+            count,
+        })
+    }
+
+    fn layout(&self) -> Result<Layout> {
+        let shape_type = self.shape_type()?;
+
+        Ok(match shape_type {
+            ShapeType::Null => Layout {
+                num_parts: 0,
+                parts_at: 4,
+                num_points: 0,
+                num_points_at: 0,
+                points_at: 4,
+            },
+            ShapeType::Point | ShapeType::PointZ | ShapeType::PointM => Layout {
+                num_parts: 0,
+                parts_at: 4,
+                num_points: 1,
+                num_points_at: 0,
+                points_at: 4,
+            },
+            ShapeType::MultiPoint | ShapeType::MultiPointZ | ShapeType::MultiPointM => Layout {
+                num_parts: 0,
+                parts_at: 40,
+                num_points: self.count(36)?,
+                num_points_at: 36,
+                points_at: 40,
+            },
+            ShapeType::PolyLine
+            | ShapeType::Polygon
+            | ShapeType::PolylineZ
+            | ShapeType::PolygonZ
+            | ShapeType::PolylineM
+            | ShapeType::PolygonM
+            | ShapeType::MultiPatch => {
+                let num_parts = self.count(36)?;
+                let num_points = self.count(40)?;
+
+                // Multipatches have the part types after the parts
+                let per_part = match shape_type {
+                    ShapeType::MultiPatch => 8,
+                    _ => 4,
+                };
+                let points_at = num_parts
+                    .checked_mul(per_part)
+                    .and_then(|bytes| bytes.checked_add(44))
+                    .filter(|at| *at <= self.content.len())
+                    .ok_or(Error::BadCount {
+                        offset: self.file_offset(36),
+                        count: num_parts as i32,
+                    })?;
+
+                Layout {
+                    num_parts,
+                    parts_at: 44,
+                    num_points,
+                    num_points_at: 40,
+                    points_at,
+                }
+            }
+        })
+    }
+
+    /// The index of the first point of each part.
+    /// Empty for shapes without parts.
+    pub fn parts(&self) -> Result<Parts<'a>> {
+        let layout = self.layout()?;
+
+        Ok(Parts {
+            bytes: &self.content[layout.parts_at..layout.parts_at + 4 * layout.num_parts],
+        })
+    }
+
+    /// The x and y of the points, decoded as they are read.
+    pub fn points(&self) -> Result<Points<'a>> {
+        let layout = self.layout()?;
+
+        let bytes = layout
+            .num_points
+            .checked_mul(16)
+            .and_then(|len| self.content.get(layout.points_at..layout.points_at + len))
+            .ok_or(Error::BadCount {
+                offset: self.file_offset(layout.num_points_at),
+                count: layout.num_points as i32,
+            })?;
+
+        Ok(Points { bytes })
+    }
+
+    /// Decodes the whole shape into owned data, see [`Parser::parse_record`].
+    pub fn shape(&self) -> Result<Shape> {
+        Parser::with_reader_at(self.record, self.offset)
+            .parse_record()
+            .map(|record| record.shape)
+    }
+}
+
+/// Part indices borrowed from a [`RecordRef`].
+#[derive(Debug, Clone, Copy)]
+pub struct Parts<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Parts<'a> {
+    pub fn len(&self) -> usize {
+        self.bytes.len() / 4
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<i32> {
+        read::<4>(self.bytes, 0, index.checked_mul(4)?)
+            .ok()
+            .map(i32::from_le_bytes)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = i32> + 'a {
+        self.bytes
+            .chunks_exact(4)
+            .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+/// Points borrowed from a [`RecordRef`].
+#[derive(Debug, Clone, Copy)]
+pub struct Points<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Points<'a> {
+    pub fn len(&self) -> usize {
+        self.bytes.len() / 16
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<Point> {
+        read::<16>(self.bytes, 0, index.checked_mul(16)?)
+            .ok()
+            .map(point)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Point> + 'a {
+        self.bytes
+            .chunks_exact(16)
+            .map(|b| point(std::array::from_fn(|i| b[i])))
+    }
+}
+
+fn point(bytes: [u8; 16]) -> Point {
+    let (x, y) = bytes.split_at(8);
+    Point {
+        x: f64::from_le_bytes(std::array::from_fn(|i| x[i])),
+        y: f64::from_le_bytes(std::array::from_fn(|i| y[i])),
+    }
+}
+
+/// Reads `N` bytes at `at`, where `base` is the file offset of `bytes` for errors.
+fn read<const N: usize>(bytes: &[u8], base: usize, at: usize) -> Result<[u8; N]> {
+    at.checked_add(N)
+        .and_then(|end| bytes.get(at..end))
+        .map(|b| std::array::from_fn(|i| b[i]))
+        .ok_or(Error::Truncated {
+            offset: base + at,
+            needed: N,
+        })
+}
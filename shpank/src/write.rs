@@ -0,0 +1,841 @@
+//! Writing Shapefiles.
+//!
+//! Bounding boxes and Z/M ranges are computed from the points when writing,
+//! so shapes which have been filtered or clipped don't need them updated first.
+//! The attributes are written to the `.dbf` separately, by a [`DbaseWriter`].
+
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+    ops::Range,
+    path::Path,
+    time::SystemTime,
+};
+
+use crate::{
+    dbase::{
+        decode_field, DbaseDate, DbaseHeader, DbaseRecord, DbaseValue, FieldDescriptor, FieldType,
+    },
+    encoding::Encoding,
+    parse::{Error, Result},
+    shape::{
+        Measure, Measures, MinimumBoundingRectangle, PatchType, Point, Shape, ShapeType, ShpHeader,
+        ShpLength, ShpRecord, NO_DATA,
+    },
+    shx::ShxRecord,
+};
+
+const HEADER_BYTES: usize = 100;
+
+/// Writes a `.shp` file along with its `.shx` index.
+///
+/// Placeholder headers are written up front, the real ones by [`ShpWriter::finish`]
+/// once the extent of all the shapes is known.
+pub struct ShpWriter<W> {
+    shp: W,
+    shx: W,
+    shape_type: ShapeType,
+
+    /// Size of the `.shp` so far
+    shp_bytes: usize,
+    num_records: usize,
+
+    mbr: Option<MinimumBoundingRectangle>,
+    z_range: Option<Range<f64>>,
+    m_range: Option<Range<f64>>,
+}
+
+impl ShpWriter<BufWriter<File>> {
+    /// Creates the `.shp` file and the `.shx` file next to it.
+    pub fn create<P: AsRef<Path>>(shp_path: P, shape_type: ShapeType) -> Result<Self> {
+        let shp = File::create(shp_path.as_ref())?;
+        let shx = File::create(shp_path.as_ref().with_extension("shx"))?;
+
+        Self::new(BufWriter::new(shp), BufWriter::new(shx), shape_type)
+    }
+}
+
+impl<W> ShpWriter<W>
+where
+    W: Write + Seek,
+{
+    /// All shapes written must be of the given type, or null shapes.
+    pub fn new(mut shp: W, mut shx: W, shape_type: ShapeType) -> Result<Self> {
+        shp.seek(SeekFrom::Start(0))?;
+        shp.write_all(&[0; HEADER_BYTES])?;
+        shx.seek(SeekFrom::Start(0))?;
+        shx.write_all(&[0; HEADER_BYTES])?;
+
+        Ok(Self {
+            shp,
+            shx,
+            shape_type,
+            shp_bytes: HEADER_BYTES,
+            num_records: 0,
+            mbr: None,
+            z_range: None,
+            m_range: None,
+        })
+    }
+
+    pub fn shape_type(&self) -> ShapeType {
+        self.shape_type
+    }
+
+    pub fn num_records(&self) -> usize {
+        self.num_records
+    }
+
+    pub fn write_record(&mut self, record: &ShpRecord) -> Result<()> {
+        self.write_shape(&record.shape)
+    }
+
+    /// Appends the shape as the next record.
+    /// The bounding box and ranges stored in the shape are ignored.
+    pub fn write_shape(&mut self, shape: &Shape) -> Result<()> {
+        let actual = shape.shape_type();
+        if actual != self.shape_type && actual != ShapeType::Null {
+            return Err(Error::ShapeTypeMismatch {
+                expected: self.shape_type,
+                actual,
+            });
+        }
+        check_shape(shape)?;
+
+        let content = Content::encode(shape);
+        let offset = self.shp_bytes;
+        let record_bytes = ShxRecord::RECORD_HEADER_BYTES + content.bytes.len();
+        let shp_bytes = words(offset + record_bytes)?;
+
+        let record_number = self.num_records as i32 + 1;
+        let content_length = (content.bytes.len() / 2) as i32;
+
+        self.shp.write_all(&record_number.to_be_bytes())?;
+        self.shp.write_all(&content_length.to_be_bytes())?;
+        self.shp.write_all(&content.bytes)?;
+
+        self.shx.write_all(&((offset / 2) as i32).to_be_bytes())?;
+        self.shx.write_all(&content_length.to_be_bytes())?;
+
+        self.shp_bytes = shp_bytes.num_bytes();
+        self.num_records += 1;
+
+        self.mbr = union(self.mbr.take(), content.mbr, |a, b| a.union(&b));
+        self.z_range = union(self.z_range.take(), content.z_range, range_union);
+        self.m_range = union(self.m_range.take(), content.m_range, range_union);
+
+        Ok(())
+    }
+
+    /// Writes the headers of both files and hands back the writers.
+    pub fn finish(mut self) -> Result<(W, W)> {
+        let header = ShpHeader {
+            file_code: ShpHeader::FILE_CODE,
+            file_length: words(self.shp_bytes)?,
+            version: 1000,
+            shape_type: self.shape_type,
+            mbr: self.mbr.take().unwrap_or(MinimumBoundingRectangle {
+                x: 0.0..0.0,
+                y: 0.0..0.0,
+            }),
+            z_range: self.z_range.take().unwrap_or(0.0..0.0),
+            m_range: self.m_range.take().unwrap_or(0.0..0.0),
+        };
+
+        self.shp.seek(SeekFrom::Start(0))?;
+        self.shp.write_all(&header_bytes(&header))?;
+        self.shp.flush()?;
+
+        let shx_header = ShpHeader {
+            file_length: words(HEADER_BYTES + 8 * self.num_records)?,
+            ..header
+        };
+        self.shx.seek(SeekFrom::Start(0))?;
+        self.shx.write_all(&header_bytes(&shx_header))?;
+        self.shx.flush()?;
+
+        Ok((self.shp, self.shx))
+    }
+}
+
+/// Lengths are stored as signed 16-bit word counts, which limits the size of files.
+fn words(bytes: usize) -> Result<ShpLength> {
+    i32::try_from(bytes / 2)
+        .map(ShpLength)
+        .map_err(|_| Error::FileTooLarge { bytes })
+}
+
+fn union<T>(a: Option<T>, b: Option<T>, f: impl FnOnce(T, T) -> T) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(f(a, b)),
+        (a, b) => a.or(b),
+    }
+}
+
+fn range_union(a: Range<f64>, b: Range<f64>) -> Range<f64> {
+    a.start.min(b.start)..a.end.max(b.end)
+}
+
+/// The smallest range containing all the values, `None` if there are none.
+fn range_of(values: impl IntoIterator<Item = f64>) -> Option<Range<f64>> {
+    values.into_iter().map(|v| v..v).reduce(range_union)
+}
+
+/// Checks that the parts, Z and M values of the shape agree with its points,
+/// since the reader rejects shapes where they don't.
+fn check_shape(shape: &Shape) -> Result<()> {
+    let m_len = |m: Option<&Measures>| m.map(|m| m.values.len());
+
+    #[rustfmt::skip]
+    let (parts, num_part_types, points, num_z, num_m): (&[i32], _, &[Point], _, _) = match shape {
+        Shape::Null | Shape::Point(_) | Shape::PointZ(_) | Shape::PointM(_) => return Ok(()),
+        Shape::MultiPoint(m) => (&[], None, &m.points, None, None),
+        Shape::MultiPointZ(m) => (&[], None, &m.points, Some(m.z.len()), m_len(m.m.as_ref())),
+        Shape::MultiPointM(m) => (&[], None, &m.points, None, Some(m.m.values.len())),
+        Shape::PolyLine(l) => (&l.parts, None, &l.points, None, None),
+        Shape::PolylineZ(l) => (&l.parts, None, &l.points, Some(l.z.len()), m_len(l.m.as_ref())),
+        Shape::PolylineM(l) => (&l.parts, None, &l.points, None, Some(l.m.values.len())),
+        Shape::Polygon(p) => (&p.parts, None, &p.points, None, None),
+        Shape::PolygonZ(p) => (&p.parts, None, &p.points, Some(p.z.len()), m_len(p.m.as_ref())),
+        Shape::PolygonM(p) => (&p.parts, None, &p.points, None, Some(p.m.values.len())),
+        Shape::MultiPatch(p) => (
+            &p.parts,
+            Some(p.part_types.len()),
+            &p.points,
+            Some(p.z.len()),
+            m_len(p.m.as_ref()),
+        ),
+    };
+
+    let bad = |reason: String| {
+        Err(Error::BadShape {
+            shape_type: shape.shape_type(),
+            reason,
+        })
+    };
+    let num_points = points.len();
+
+    if i32::try_from(num_points).is_err() || i32::try_from(parts.len()).is_err() {
+        return bad("too many parts or points".into());
+    }
+    let in_bounds = parts
+        .iter()
+        .all(|part| usize::try_from(*part).is_ok_and(|part| part <= num_points));
+    if !in_bounds || parts.windows(2).any(|w| w[0] > w[1]) {
+        return bad(format!(
+            "part indices must be increasing and within the {num_points} points"
+        ));
+    }
+
+    for (count, what, expected, of) in [
+        (num_part_types, "part types", parts.len(), "parts"),
+        (num_z, "Z values", num_points, "points"),
+        (num_m, "M values", num_points, "points"),
+    ] {
+        match count {
+            Some(count) if count != expected => {
+                return bad(format!("{count} {what} for {expected} {of}"))
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn header_bytes(header: &ShpHeader) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_BYTES);
+
+    bytes.extend(header.file_code.to_be_bytes());
+    // Unused; 5 integers
+    bytes.extend([0; 20]);
+    bytes.extend(header.file_length.0.to_be_bytes());
+    bytes.extend(header.version.to_le_bytes());
+    bytes.extend((header.shape_type as i32).to_le_bytes());
+
+    let MinimumBoundingRectangle { x, y } = &header.mbr;
+    for value in [x.start, y.start, x.end, y.end] {
+        bytes.extend(value.to_le_bytes());
+    }
+    for value in [
+        header.z_range.start,
+        header.z_range.end,
+        header.m_range.start,
+        header.m_range.end,
+    ] {
+        bytes.extend(value.to_le_bytes());
+    }
+
+    bytes
+}
+
+/// The content of a record, along with the extent of what was written.
+#[derive(Default)]
+struct Content {
+    bytes: Vec<u8>,
+    mbr: Option<MinimumBoundingRectangle>,
+    z_range: Option<Range<f64>>,
+    m_range: Option<Range<f64>>,
+}
+
+impl Content {
+    fn encode(shape: &Shape) -> Self {
+        let mut content = Self::default();
+        content.integer(shape.shape_type() as i32);
+
+        match shape {
+            Shape::Null => {}
+            Shape::Point(point) => content.point(*point),
+            Shape::PolyLine(line) => content.multipart(&line.parts, None, &line.points),
+            Shape::Polygon(polygon) => content.multipart(&polygon.parts, None, &polygon.points),
+            Shape::MultiPoint(multipoint) => content.multipoint(&multipoint.points),
+            Shape::PointZ(point) => {
+                content.point(Point {
+                    x: point.x,
+                    y: point.y,
+                });
+                content.double(point.z);
+                content.z_range = Some(point.z..point.z);
+                // Optional for Z shapes, so only written if there is one
+                if point.m.is_some() {
+                    content.measure(point.m);
+                }
+            }
+            Shape::PolylineZ(line) => {
+                content.multipart(&line.parts, None, &line.points);
+                content.z(&line.z);
+                content.optional_measures(line.m.as_ref());
+            }
+            Shape::PolygonZ(polygon) => {
+                content.multipart(&polygon.parts, None, &polygon.points);
+                content.z(&polygon.z);
+                content.optional_measures(polygon.m.as_ref());
+            }
+            Shape::MultiPointZ(multipoint) => {
+                content.multipoint(&multipoint.points);
+                content.z(&multipoint.z);
+                content.optional_measures(multipoint.m.as_ref());
+            }
+            Shape::PointM(point) => {
+                content.point(Point {
+                    x: point.x,
+                    y: point.y,
+                });
+                content.measure(point.m);
+            }
+            Shape::PolylineM(line) => {
+                content.multipart(&line.parts, None, &line.points);
+                content.measures(&line.m);
+            }
+            Shape::PolygonM(polygon) => {
+                content.multipart(&polygon.parts, None, &polygon.points);
+                content.measures(&polygon.m);
+            }
+            Shape::MultiPointM(multipoint) => {
+                content.multipoint(&multipoint.points);
+                content.measures(&multipoint.m);
+            }
+            Shape::MultiPatch(patch) => {
+                content.multipart(&patch.parts, Some(&patch.part_types), &patch.points);
+                content.z(&patch.z);
+                content.optional_measures(patch.m.as_ref());
+            }
+        }
+
+        content
+    }
+
+    fn integer(&mut self, value: i32) {
+        self.bytes.extend(value.to_le_bytes());
+    }
+
+    fn double(&mut self, value: f64) {
+        self.bytes.extend(value.to_le_bytes());
+    }
+
+    fn range(&mut self, range: &Range<f64>) {
+        self.double(range.start);
+        self.double(range.end);
+    }
+
+    fn point(&mut self, point: Point) {
+        self.double(point.x);
+        self.double(point.y);
+        self.mbr = MinimumBoundingRectangle::from_points([&point]);
+    }
+
+    fn bounding_box(&mut self, points: &[Point]) {
+        self.mbr = MinimumBoundingRectangle::from_points(points);
+
+        // Empty shapes have an all zero bounding box
+        let MinimumBoundingRectangle { x, y } =
+            self.mbr.clone().unwrap_or(MinimumBoundingRectangle {
+                x: 0.0..0.0,
+                y: 0.0..0.0,
+            });
+        for value in [x.start, y.start, x.end, y.end] {
+            self.double(value);
+        }
+    }
+
+    fn multipoint(&mut self, points: &[Point]) {
+        self.bounding_box(points);
+        self.integer(points.len() as i32);
+
+        for point in points {
+            self.double(point.x);
+            self.double(point.y);
+        }
+    }
+
+    fn multipart(&mut self, parts: &[i32], part_types: Option<&[PatchType]>, points: &[Point]) {
+        self.bounding_box(points);
+        self.integer(parts.len() as i32);
+        self.integer(points.len() as i32);
+
+        for part in parts {
+            self.integer(*part);
+        }
+        for part_type in part_types.unwrap_or_default() {
+            self.integer(*part_type as i32);
+        }
+        for point in points {
+            self.double(point.x);
+            self.double(point.y);
+        }
+    }
+
+    fn z(&mut self, z: &[f64]) {
+        self.z_range = range_of(z.iter().copied());
+        self.range(&self.z_range.clone().unwrap_or(0.0..0.0));
+
+        for value in z {
+            self.double(*value);
+        }
+    }
+
+    fn measure(&mut self, m: Measure) {
+        self.double(m.unwrap_or(NO_DATA));
+        self.m_range = m.map(|m| m..m);
+    }
+
+    fn measures(&mut self, m: &Measures) {
+        self.m_range = range_of(m.values.iter().flatten().copied());
+        self.range(&self.m_range.clone().unwrap_or(NO_DATA..NO_DATA));
+
+        for value in &m.values {
+            self.double(value.unwrap_or(NO_DATA));
+        }
+    }
+
+    fn optional_measures(&mut self, m: Option<&Measures>) {
+        if let Some(m) = m {
+            self.measures(m);
+        }
+    }
+}
+
+/// Writes a `.dbf` file with the given fields.
+///
+/// Only the dBASE III+/IV layout and its text based field types can be written,
+/// which is what Shapefiles use.
+/// Values must have the type the field says, and numbers must fit the field length.
+/// Text which is too long is cut short.
+pub struct DbaseWriter<W> {
+    writer: W,
+    header: DbaseHeader,
+}
+
+impl DbaseWriter<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(dbf_path: P, fields: Vec<FieldDescriptor>) -> Result<Self> {
+        Self::new(BufWriter::new(File::create(dbf_path.as_ref())?), fields)
+    }
+}
+
+impl<W> DbaseWriter<W>
+where
+    W: Write + Seek,
+{
+    /// The last update is set to today, and text is written as UTF-8.
+    /// The file is a Visual FoxPro file if any field has a type only it has.
+    pub fn new(writer: W, fields: Vec<FieldDescriptor>) -> Result<Self> {
+        let has_memo = fields
+            .iter()
+            .any(|field| matches!(field.type_, FieldType::Memo));
+        let visual_foxpro = fields.iter().any(|field| {
+            matches!(
+                field.type_,
+                FieldType::Integer
+                    | FieldType::Double
+                    | FieldType::Currency
+                    | FieldType::DateTime
+                    | FieldType::NullFlags
+            )
+        });
+
+        let header = DbaseHeader {
+            // dBASE III, with the memo bit if needed
+            flags: match (visual_foxpro, has_memo) {
+                (true, _) => 0x30,
+                (false, true) => 0x83,
+                (false, false) => 0x03,
+            },
+            yy: 0,
+            mm: 0,
+            dd: 0,
+            num_records: 0,
+            header_bytes: 0,
+            record_bytes: 0,
+            language_driver: 0,
+            encoding: Encoding::Utf8,
+            incomplete_transaction: false,
+            encrypted: false,
+            has_mdx: false,
+            fields,
+            // Visual FoxPro files have room for a database container after the fields
+            backlink: if visual_foxpro { vec![0; 263] } else { vec![] },
+        };
+
+        Ok(Self::with_header(writer, &header)?.with_date(today()))
+    }
+
+    /// Keeps the version, date, encoding etc. of an existing header,
+    /// such as the one of a parsed file.
+    /// The number of records and the record size are set by the writer.
+    pub fn with_header(mut writer: W, header: &DbaseHeader) -> Result<Self> {
+        if header.is_dbase7() {
+            return Err(Error::UnexpectedData(
+                "dBASE 7 files can't be written".into(),
+            ));
+        }
+
+        for field in &header.fields {
+            check_field(field, header.is_visual_foxpro())?;
+        }
+
+        let record_bytes = 1 + header
+            .fields
+            .iter()
+            .map(|field| field.field_length)
+            .sum::<usize>();
+        let header_bytes = header
+            .header_bytes
+            .max(32 + 32 * header.fields.len() + 1 + header.backlink.len());
+
+        if u16::try_from(record_bytes).is_err() || u16::try_from(header_bytes).is_err() {
+            return Err(Error::UnexpectedData(format!(
+                "Too many fields: {record_bytes} bytes per record and {header_bytes} header bytes"
+            )));
+        }
+
+        writer.seek(SeekFrom::Start(0))?;
+        writer.write_all(&vec![0; header_bytes])?;
+
+        Ok(Self {
+            writer,
+            header: DbaseHeader {
+                num_records: 0,
+                header_bytes,
+                record_bytes,
+                ..header.clone()
+            },
+        })
+    }
+
+    /// Encodes text with the given encoding, and sets the language driver to match.
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.header.encoding = encoding;
+        self.header.language_driver = encoding.language_driver().unwrap_or_default();
+        self
+    }
+
+    /// Sets the date of the last update.
+    pub fn with_date(mut self, date: DbaseDate) -> Self {
+        self.header.yy = date.year.saturating_sub(1900).min(255) as u8;
+        self.header.mm = date.month;
+        self.header.dd = date.day;
+        self
+    }
+
+    pub fn header(&self) -> &DbaseHeader {
+        &self.header
+    }
+
+    pub fn num_records(&self) -> usize {
+        self.header.num_records
+    }
+
+    /// Appends a record which is not deleted.
+    pub fn write_values(&mut self, entries: &[DbaseValue]) -> Result<()> {
+        self.write_record(&DbaseRecord {
+            deleted: false,
+            entries: entries.to_vec(),
+            raw: vec![],
+        })
+    }
+
+    /// Appends the record, with the deletion flag set if it is deleted.
+    ///
+    /// Values which are unchanged since parsing are written as the bytes they were parsed from,
+    /// see [`DbaseRecord::raw`], so parsed files are written back byte for byte.
+    /// A memo replaced by its text still refers to its block in the original memo file.
+    /// Memos can't be written from text alone.
+    pub fn write_record(&mut self, record: &DbaseRecord) -> Result<()> {
+        if record.entries.len() != self.header.fields.len() {
+            return Err(Error::UnexpectedData(format!(
+                "Record has {} values, but there are {} fields",
+                record.entries.len(),
+                self.header.fields.len()
+            )));
+        }
+        if u32::try_from(self.header.num_records + 1).is_err() {
+            return Err(Error::UnexpectedData("Too many records".into()));
+        }
+
+        let mut bytes = Vec::with_capacity(self.header.record_bytes);
+        bytes.push(if record.deleted { b'*' } else { b' ' });
+
+        for (index, (field, value)) in self.header.fields.iter().zip(&record.entries).enumerate() {
+            let raw = record
+                .raw
+                .iter()
+                .find(|(at, raw)| *at == index && raw.len() == field.field_length)
+                .map(|(_, raw)| raw.clone());
+
+            let encoded = match (field.type_, value, raw) {
+                (FieldType::Memo, DbaseValue::Text(_), Some(raw)) => Some(raw),
+                (_, _, Some(raw))
+                    if decode_field(&self.header, field, &raw)?.as_ref() == Some(value) =>
+                {
+                    Some(raw)
+                }
+                _ => encode_value(self.header.encoding, field, value),
+            };
+            bytes.extend(encoded.ok_or_else(|| Error::ValueDoesNotFit {
+                field: field.name.clone(),
+                value: format!("{value:?}"),
+            })?);
+        }
+
+        self.writer.write_all(&bytes)?;
+        self.header.num_records += 1;
+
+        Ok(())
+    }
+
+    /// Writes the end of file marker and the header, and hands back the writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.write_all(&[0x1A])?;
+
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(&dbase_header_bytes(&self.header))?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+fn today() -> DbaseDate {
+    let days = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() / (24 * 60 * 60));
+
+    // 2440588 is 1970-01-01
+    DbaseDate::from_julian_day(days as i64 + 2440588).unwrap_or(DbaseDate {
+        year: 1970,
+        month: 1,
+        day: 1,
+    })
+}
+
+fn check_field(field: &FieldDescriptor, visual_foxpro: bool) -> Result<()> {
+    let bad = |reason: &str| {
+        Err(Error::BadFieldDescriptor {
+            name: field.name.clone(),
+            reason: reason.into(),
+        })
+    };
+
+    if !field.name.is_ascii() || field.name.is_empty() || field.name.len() > 11 {
+        return bad("names must be 1 to 11 ASCII characters");
+    }
+    if !(1..=255).contains(&field.field_length) {
+        return bad("the length must be 1 to 255 bytes");
+    }
+
+    match field.type_ {
+        FieldType::Integer
+        | FieldType::Double
+        | FieldType::Currency
+        | FieldType::DateTime
+        | FieldType::NullFlags
+            if !visual_foxpro =>
+        {
+            bad("the type is only in Visual FoxPro files")
+        }
+        FieldType::Integer if field.field_length == 4 => Ok(()),
+        FieldType::Double | FieldType::Currency | FieldType::DateTime
+            if field.field_length == 8 =>
+        {
+            Ok(())
+        }
+        FieldType::Character
+        | FieldType::Numeric
+        | FieldType::FloatingPoint
+        | FieldType::NullFlags => Ok(()),
+        FieldType::Date if field.field_length == 8 => Ok(()),
+        FieldType::Logical if field.field_length == 1 => Ok(()),
+        FieldType::Memo if field.field_length == 4 || field.field_length == 10 => Ok(()),
+        FieldType::Date
+        | FieldType::Logical
+        | FieldType::Memo
+        | FieldType::Integer
+        | FieldType::Double
+        | FieldType::Currency
+        | FieldType::DateTime => bad("the length is wrong for the type"),
+        type_ => Err(Error::UnsupportedFieldType(type_)),
+    }
+}
+
+fn dbase_header_bytes(header: &DbaseHeader) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(header.header_bytes);
+
+    bytes.extend([header.flags, header.yy, header.mm, header.dd]);
+    bytes.extend((header.num_records as u32).to_le_bytes());
+    bytes.extend((header.header_bytes as u16).to_le_bytes());
+    bytes.extend((header.record_bytes as u16).to_le_bytes());
+    // Reserved
+    bytes.extend([0; 2]);
+    bytes.push(header.incomplete_transaction.into());
+    bytes.push(header.encrypted.into());
+    // Multi-user processing
+    bytes.extend([0; 12]);
+    bytes.push(header.has_mdx.into());
+    bytes.push(header.language_driver);
+    bytes.extend([0; 2]);
+
+    for field in &header.fields {
+        let mut name = [0; 11];
+        name[..field.name.len()].copy_from_slice(field.name.as_bytes());
+        bytes.extend(name);
+        bytes.push(field.type_ as u8);
+        // Visual FoxPro: Where the field is within the record, not needed to read it
+        bytes.extend([0; 4]);
+        bytes.push(field.field_length as u8);
+        bytes.push(field.decimal_count);
+        bytes.push(field.flags);
+
+        // The work area is byte 20, the second byte of the autoincrement value, see the parser
+        let [a, _, b, c] = field.autoincrement_next.to_le_bytes();
+        bytes.extend([a, field.work_area, b, c, field.autoincrement_step]);
+        // Reserved
+        bytes.extend([0; 7]);
+        bytes.push(field.indexed.into());
+    }
+    bytes.push(0x0D);
+    bytes.extend(&header.backlink);
+    bytes.resize(header.header_bytes, 0);
+
+    bytes
+}
+
+/// How a [`DbaseValue::Null`] is written:
+/// `?` for logicals, zeros for binary memos and date times, and spaces for the rest.
+fn null_bytes(field: &FieldDescriptor) -> Vec<u8> {
+    let mut bytes = vec![b' '; field.field_length];
+    match (field.type_, bytes.first_mut()) {
+        (FieldType::Logical, Some(first)) => *first = b'?',
+        (FieldType::Memo, _) if field.field_length == 4 => bytes.fill(0),
+        (FieldType::DateTime | FieldType::NullFlags, _) => bytes.fill(0),
+        _ => {}
+    }
+    bytes
+}
+
+/// The bytes of a value in a field, or `None` if the value does not fit the field.
+pub(crate) fn encode_value(
+    encoding: Encoding,
+    field: &FieldDescriptor,
+    value: &DbaseValue,
+) -> Option<Vec<u8>> {
+    let width = field.field_length;
+    let decimals = field.decimal_count as usize;
+
+    let text = match (field.type_, value) {
+        (FieldType::Character, DbaseValue::Text(text)) => {
+            let mut bytes = encoding.encode(text)?.into_owned();
+            if bytes.len() > width {
+                let mut end = width;
+                // Don't cut a UTF-8 character in half
+                if encoding == Encoding::Utf8 {
+                    while !text.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                }
+                bytes.truncate(end);
+            }
+            bytes.resize(width, b' ');
+            return Some(bytes);
+        }
+        // Visual FoxPro only has nulls for these with the null flags
+        (FieldType::Integer | FieldType::Double | FieldType::Currency, DbaseValue::Null) => {
+            return None
+        }
+        (_, DbaseValue::Null) => return Some(null_bytes(field)),
+        (FieldType::Integer, DbaseValue::Integer(v)) => {
+            return Some(i32::try_from(*v).ok()?.to_le_bytes().to_vec())
+        }
+        (FieldType::Double, DbaseValue::Integer(v)) => {
+            return Some((*v as f64).to_le_bytes().to_vec())
+        }
+        (FieldType::Double, DbaseValue::Decimal(v) | DbaseValue::Float(v)) => {
+            return Some(v.to_le_bytes().to_vec())
+        }
+        (FieldType::Currency, DbaseValue::Integer(v)) => {
+            return Some(v.checked_mul(10_000)?.to_le_bytes().to_vec())
+        }
+        (FieldType::Currency, DbaseValue::Decimal(v) | DbaseValue::Float(v)) => {
+            // Saturates at the ends of the range, which are then checked against
+            let ten_thousandths = (v * 10_000.).round() as i64;
+            if !v.is_finite() || ten_thousandths == i64::MIN || ten_thousandths == i64::MAX {
+                return None;
+            }
+            return Some(ten_thousandths.to_le_bytes().to_vec());
+        }
+        (FieldType::DateTime, DbaseValue::DateTime(date_time)) => {
+            let day = i32::try_from(date_time.date.julian_day()).ok()?;
+            let mut bytes = day.to_le_bytes().to_vec();
+            bytes.extend(date_time.millis.to_le_bytes());
+            return Some(bytes);
+        }
+        (FieldType::Numeric | FieldType::FloatingPoint, DbaseValue::Integer(v)) => match decimals {
+            0 => format!("{v:>width$}"),
+            _ => format!("{:>width$.decimals$}", *v as f64),
+        },
+        (
+            FieldType::Numeric | FieldType::FloatingPoint,
+            DbaseValue::Decimal(v) | DbaseValue::Float(v),
+        ) if v.is_finite() => match decimals {
+            // Written by writers which put decimals in fields declared without them
+            0 => format!("{v:>width$}"),
+            _ => format!("{v:>width$.decimals$}"),
+        },
+        (FieldType::Date, DbaseValue::Date(date)) => {
+            format!("{:04}{:02}{:02}", date.year, date.month, date.day)
+        }
+        (FieldType::Logical, DbaseValue::Bool(v)) => if *v { "T" } else { "F" }.to_string(),
+        (FieldType::Memo, DbaseValue::Memo(block)) if width == 4 => {
+            return Some(block.to_le_bytes().to_vec())
+        }
+        (FieldType::Memo, DbaseValue::Memo(block)) => format!("{block:>width$}"),
+        _ => return None,
+    };
+
+    if text.len() > width {
+        return None;
+    }
+
+    let mut bytes = text.into_bytes();
+    bytes.resize(width, b' ');
+    Some(bytes)
+}
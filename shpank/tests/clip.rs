@@ -0,0 +1,217 @@
+mod common;
+
+use common::{layout, polygon};
+use shpank::{
+    clip::Region,
+    parse::Error,
+    rings::Orientation,
+    shape::{
+        Measures, MinimumBoundingRectangle, MultiPoint, Point, PolyLine, PolygonM, Shape, ShapeType,
+    },
+};
+
+fn rectangle(x: std::ops::Range<f64>, y: std::ops::Range<f64>) -> Region {
+    Region::Rectangle(MinimumBoundingRectangle { x, y })
+}
+
+#[test]
+fn points_and_lines_to_rectangle() {
+    let region = rectangle(0.0..10.0, 0.0..10.0);
+
+    assert!(Shape::Point(Point { x: 10., y: 5. })
+        .clip(&region)
+        .unwrap()
+        .is_some());
+    assert!(Shape::Point(Point { x: 11., y: 5. })
+        .clip(&region)
+        .unwrap()
+        .is_none());
+
+    let (_, points, mbr) = layout(&[&[(1., 1.), (-1., 1.), (5., 5.)]]);
+    let Some(Shape::MultiPoint(multi)) = Shape::MultiPoint(MultiPoint { mbr, points })
+        .clip(&region)
+        .unwrap()
+    else {
+        panic!("expected a multipoint");
+    };
+    assert_eq!(
+        multi.points,
+        [Point { x: 1., y: 1. }, Point { x: 5., y: 5. }]
+    );
+    assert_eq!(multi.mbr.x, 1.0..5.0);
+
+    // In, out over the top and back in, then along the bottom edge
+    let (parts, points, mbr) = layout(&[&[(5., 5.), (5., 15.), (8., 5.), (8., 0.), (12., 0.)]]);
+    let Some(Shape::PolyLine(line)) = Shape::PolyLine(PolyLine { mbr, parts, points })
+        .clip(&region)
+        .unwrap()
+    else {
+        panic!("expected a line");
+    };
+    assert_eq!(line.parts, [0, 2]);
+    assert_eq!(
+        line.points,
+        [
+            Point { x: 5., y: 5. },
+            Point { x: 5., y: 10. },
+            Point { x: 6.5, y: 10. },
+            Point { x: 8., y: 5. },
+            Point { x: 8., y: 0. },
+            Point { x: 10., y: 0. },
+        ]
+    );
+
+    // Wholly outside
+    let (parts, points, mbr) = layout(&[&[(20., 20.), (30., 30.)]]);
+    assert!(Shape::PolyLine(PolyLine { mbr, parts, points })
+        .clip(&region)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn concave_polygon_with_hole_to_rectangle() {
+    // A U-shape, clockwise, with a hole in its base
+    let shape = Shape::Polygon(polygon(&[
+        &[
+            (0., 0.),
+            (0., 10.),
+            (3., 10.),
+            (3., 4.),
+            (7., 4.),
+            (7., 10.),
+            (10., 10.),
+            (10., 0.),
+            (0., 0.),
+        ],
+        &[(4., 1.), (6., 1.), (6., 3.), (4., 3.), (4., 1.)],
+    ]));
+    assert_eq!(shape.area(), 100. - 24. - 4.);
+
+    // Through both arms of the U and the hole
+    let clipped = shape
+        .clip(&rectangle(-5.0..15.0, 2.0..8.0))
+        .unwrap()
+        .unwrap();
+    assert_eq!(clipped.area(), 60. - 16. - 2.);
+    let Shape::Polygon(polygon) = &clipped else {
+        panic!("expected a polygon");
+    };
+    assert!(polygon.rings().all(|ring| ring.first() == ring.last()));
+    assert_eq!(polygon.mbr.y, 2.0..8.0);
+    assert_eq!(
+        Orientation::of(polygon.rings().next().unwrap()),
+        Orientation::Clockwise
+    );
+
+    // Inside the gap of the U
+    assert!(shape
+        .clip(&rectangle(4.0..6.0, 5.0..9.0))
+        .unwrap()
+        .is_none());
+
+    // Wholly inside is kept as it is
+    let whole = shape
+        .clip(&rectangle(-1.0..11.0, -1.0..11.0))
+        .unwrap()
+        .unwrap();
+    assert_eq!(whole.area(), shape.area());
+}
+
+#[test]
+fn polygon_to_polygon() {
+    // A square with a square hole
+    let subject = Shape::Polygon(polygon(&[
+        &[(0., 0.), (0., 4.), (4., 4.), (4., 0.), (0., 0.)],
+        &[(1., 1.), (3., 1.), (3., 3.), (1., 3.), (1., 1.)],
+    ]));
+
+    // A diamond centred on the corner at the origin
+    let diamond = Region::Polygon(polygon(&[&[
+        (-2., 0.),
+        (0., 2.),
+        (2., 0.),
+        (0., -2.),
+        (-2., 0.),
+    ]]));
+    assert!((subject.clip(&diamond).unwrap().unwrap().area() - 2.).abs() < 1e-5);
+
+    // A concave region, an L which covers the left and bottom of the square
+    let l = Region::Polygon(polygon(&[&[
+        (-1., -1.),
+        (-1., 5.),
+        (2., 5.),
+        (2., 2.),
+        (5., 2.),
+        (5., -1.),
+        (-1., -1.),
+    ]]));
+    let clipped = subject.clip(&l).unwrap().unwrap();
+    assert!((clipped.area() - (16. - 4. - 4. + 1.)).abs() < 1e-6);
+    assert!(clipped.validate(1).is_empty());
+
+    // Shares the edges of the square, so the region is nudged to clip it
+    let same = Region::Polygon(polygon(&[&[
+        (0., 0.),
+        (0., 4.),
+        (4., 4.),
+        (4., 0.),
+        (0., 0.),
+    ]]));
+    assert!((subject.clip(&same).unwrap().unwrap().area() - 12.).abs() < 1e-5);
+
+    // The points of the nudged region are put back where they were
+    let square = Shape::Polygon(polygon(&[&[
+        (0., 0.),
+        (0., 4.),
+        (4., 4.),
+        (4., 0.),
+        (0., 0.),
+    ]]));
+    let triangle = Region::Polygon(polygon(&[&[(0., 0.), (0., 4.), (2., 2.), (0., 0.)]]));
+    let clipped = square.clip(&triangle).unwrap().unwrap();
+    assert!((clipped.area() - 4.).abs() < 1e-5);
+    let Shape::Polygon(clipped) = clipped else {
+        panic!("expected a polygon");
+    };
+    assert!(clipped.points.contains(&Point { x: 2., y: 2. }));
+
+    // Inside the hole
+    let hole = Region::Polygon(polygon(&[&[
+        (1.5, 1.5),
+        (1.5, 2.5),
+        (2.5, 2.5),
+        (2.5, 1.5),
+        (1.5, 1.5),
+    ]]));
+    assert!(subject.clip(&hole).unwrap().is_none());
+
+    // Lines are cut where they cross the rings of the region
+    let (parts, points, mbr) = layout(&[&[(-3., 1.), (3., 1.)]]);
+    let line = Shape::PolyLine(PolyLine { mbr, parts, points })
+        .clip(&diamond)
+        .unwrap()
+        .unwrap();
+    assert_eq!(line.length(), 2.);
+}
+
+#[test]
+fn unsupported() {
+    let (parts, points, mbr) = layout(&[&[(0., 0.), (0., 1.), (1., 1.), (0., 0.)]]);
+    let shape = Shape::PolygonM(PolygonM {
+        mbr,
+        parts,
+        m: Measures {
+            range: None,
+            values: vec![None; points.len()],
+        },
+        points,
+    });
+
+    assert!(matches!(
+        shape.clip(&rectangle(0.0..1.0, 0.0..1.0)),
+        Err(Error::UnsupportedClip(ShapeType::PolygonM))
+    ));
+    assert!(!ShapeType::PolygonM.can_clip());
+    assert!(ShapeType::PointM.can_clip());
+}
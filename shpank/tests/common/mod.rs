@@ -0,0 +1,203 @@
+//! Builders for the bytes of Shapefiles, and for shapes, shared between tests.
+#![allow(dead_code)]
+
+use shpank::{
+    dbase::FieldType,
+    shape::{MinimumBoundingRectangle, Point, Polygon, ShapeType},
+};
+
+pub fn points(coordinates: &[(f64, f64)]) -> Vec<Point> {
+    coordinates.iter().map(|&(x, y)| Point { x, y }).collect()
+}
+
+/// Parts, points and bounding box of the shape made up of the given parts.
+pub fn layout(parts: &[&[(f64, f64)]]) -> (Vec<i32>, Vec<Point>, MinimumBoundingRectangle) {
+    let parts: Vec<_> = parts.iter().map(|part| points(part)).collect();
+    layout_of(&parts)
+}
+
+/// Like [`layout`], for parts which are already points.
+pub fn layout_of(parts: &[Vec<Point>]) -> (Vec<i32>, Vec<Point>, MinimumBoundingRectangle) {
+    let mut starts = vec![];
+    let mut points = vec![];
+    for part in parts {
+        starts.push(points.len() as i32);
+        points.extend_from_slice(part);
+    }
+    let mbr = MinimumBoundingRectangle::from_points(&points).unwrap();
+
+    (starts, points, mbr)
+}
+
+pub fn polygon(rings: &[&[(f64, f64)]]) -> Polygon {
+    let (parts, points, mbr) = layout(rings);
+    Polygon { mbr, parts, points }
+}
+
+/// Like [`polygon`], for rings which are already points.
+pub fn polygon_of(rings: &[Vec<Point>]) -> Polygon {
+    let (parts, points, mbr) = layout_of(rings);
+    Polygon { mbr, parts, points }
+}
+
+/// Builds the bytes of a single `.shp` record (header + content).
+pub struct RecordBuilder {
+    content: Vec<u8>,
+}
+
+impl RecordBuilder {
+    pub fn new(shape_type: ShapeType) -> Self {
+        Self {
+            content: (shape_type as i32).to_le_bytes().to_vec(),
+        }
+    }
+
+    pub fn integer(mut self, value: i32) -> Self {
+        self.content.extend(value.to_le_bytes());
+        self
+    }
+
+    pub fn double(mut self, value: f64) -> Self {
+        self.content.extend(value.to_le_bytes());
+        self
+    }
+
+    pub fn doubles(self, values: &[f64]) -> Self {
+        values.iter().fold(self, |builder, v| builder.double(*v))
+    }
+
+    pub fn points(self, points: &[(f64, f64)]) -> Self {
+        points
+            .iter()
+            .fold(self, |builder, (x, y)| builder.double(*x).double(*y))
+    }
+
+    pub fn build(self) -> Vec<u8> {
+        let mut bytes = 1i32.to_be_bytes().to_vec();
+        bytes.extend((self.content.len() as i32 / 2).to_be_bytes());
+        bytes.extend(self.content);
+        bytes
+    }
+}
+
+/// A `.shp` file with a header followed by the given records.
+pub fn shp_file(records: &[Vec<u8>]) -> Vec<u8> {
+    let length = 100 + records.iter().map(Vec::len).sum::<usize>();
+
+    let mut bytes = 0x0000270ai32.to_be_bytes().to_vec();
+    bytes.extend([0; 20]);
+    bytes.extend((length as i32 / 2).to_be_bytes());
+    bytes.extend(1000i32.to_le_bytes());
+    bytes.extend((ShapeType::Point as i32).to_le_bytes());
+    bytes.extend([0; 64]);
+
+    for record in records {
+        bytes.extend(record);
+    }
+
+    bytes
+}
+
+/// The `.shx` file indexing the given records of a [`shp_file`].
+pub fn shx_file(records: &[Vec<u8>]) -> Vec<u8> {
+    let length = 100 + 8 * records.len();
+
+    let mut bytes = shp_file(&[])[..100].to_vec();
+    bytes[24..28].copy_from_slice(&(length as i32 / 2).to_be_bytes());
+
+    let mut offset = 100;
+    for record in records {
+        bytes.extend((offset as i32 / 2).to_be_bytes());
+        bytes.extend((record.len() as i32 / 2 - 4).to_be_bytes());
+        offset += record.len();
+    }
+
+    bytes
+}
+
+/// Builds the bytes of a `.dbf` file.
+pub struct DbfBuilder {
+    fields: Vec<(&'static str, FieldType, u8, u8)>,
+    records: Vec<(u8, Vec<Vec<u8>>)>,
+    language_driver: u8,
+    version: u8,
+    backlink: bool,
+}
+
+impl DbfBuilder {
+    pub fn new() -> Self {
+        Self {
+            fields: vec![],
+            records: vec![],
+            language_driver: 0,
+            version: 0x03,
+            backlink: false,
+        }
+    }
+
+    pub fn field(mut self, name: &'static str, type_: FieldType, length: u8, decimals: u8) -> Self {
+        self.fields.push((name, type_, length, decimals));
+        self
+    }
+
+    pub fn language_driver(mut self, id: u8) -> Self {
+        self.language_driver = id;
+        self
+    }
+
+    /// A Visual FoxPro file, with the 263 byte backlink after the field descriptors.
+    pub fn visual_foxpro(mut self) -> Self {
+        self.version = 0x30;
+        self.backlink = true;
+        self
+    }
+
+    pub fn record_with_flag(mut self, flag: u8, values: &[&[u8]]) -> Self {
+        self.records
+            .push((flag, values.iter().map(|v| v.to_vec()).collect()));
+        self
+    }
+
+    pub fn record(self, values: &[&[u8]]) -> Self {
+        self.record_with_flag(b' ', values)
+    }
+
+    pub fn build(self) -> Vec<u8> {
+        let backlink_bytes = if self.backlink { 263 } else { 0 };
+        let header_bytes = 32 + 32 * self.fields.len() + 1 + backlink_bytes;
+        let record_bytes = 1 + self.fields.iter().map(|f| f.2 as usize).sum::<usize>();
+
+        let mut bytes = vec![self.version, 124, 6, 30];
+        bytes.extend((self.records.len() as u32).to_le_bytes());
+        bytes.extend((header_bytes as u16).to_le_bytes());
+        bytes.extend((record_bytes as u16).to_le_bytes());
+        bytes.extend([0; 17]);
+        bytes.push(self.language_driver);
+        bytes.extend([0; 2]);
+
+        for (name, type_, length, decimals) in &self.fields {
+            let mut name_bytes = [0; 11];
+            name_bytes[..name.len()].copy_from_slice(name.as_bytes());
+            bytes.extend(name_bytes);
+            bytes.push(*type_ as u8);
+            bytes.extend([0; 4]);
+            bytes.push(*length);
+            bytes.push(*decimals);
+            bytes.extend([0; 14]);
+        }
+        bytes.push(0x0D);
+        bytes.extend(vec![0; backlink_bytes]);
+
+        for (flag, values) in &self.records {
+            bytes.push(*flag);
+            for ((_, _, length, _), value) in self.fields.iter().zip(values) {
+                let mut value = value.clone();
+                value.resize(*length as usize, b' ');
+                bytes.extend(value);
+            }
+        }
+        bytes.push(0x1A);
+
+        bytes
+    }
+}
@@ -0,0 +1,96 @@
+use shpank::{crs::Crs, parse::Error};
+
+const WGS84: &str = r#"GEOGCS["GCS_WGS_1984",DATUM["D_WGS_1984",SPHEROID["WGS_1984",6378137.0,298.257223563]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]]"#;
+
+const WEB_MERCATOR: &str = r#"PROJCS["WGS_1984_Web_Mercator_Auxiliary_Sphere",GEOGCS["GCS_WGS_1984",DATUM["D_WGS_1984",SPHEROID["WGS_1984",6378137.0,298.257223563]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]],PROJECTION["Mercator_Auxiliary_Sphere"],PARAMETER["False_Easting",0.0],PARAMETER["False_Northing",0.0],PARAMETER["Central_Meridian",0.0],PARAMETER["Standard_Parallel_1",0.0],PARAMETER["Auxiliary_Sphere_Type",0.0],UNIT["Meter",1.0]]"#;
+
+fn utm(datum: &str, spheroid: &str, inverse_flattening: f64, zone: u32) -> String {
+    format!(
+        r#"PROJCS["{datum}_UTM_Zone_{zone}N",GEOGCS["GCS_{datum}",DATUM["D_{datum}",SPHEROID["{spheroid}",6378137.0,{inverse_flattening}]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]],PROJECTION["Transverse_Mercator"],PARAMETER["False_Easting",500000.0],PARAMETER["False_Northing",0.0],PARAMETER["Central_Meridian",{}],PARAMETER["Scale_Factor",0.9996],PARAMETER["Latitude_Of_Origin",0.0],UNIT["Meter",1.0]]"#,
+        zone as f64 * 6. - 183.
+    )
+}
+
+#[test]
+fn geographic() {
+    let crs: Crs = WGS84.parse().unwrap();
+
+    assert!(crs.is_geographic());
+    assert_eq!(crs.name, "GCS_WGS_1984");
+    assert_eq!(crs.datum.name, "D_WGS_1984");
+    assert_eq!(crs.datum.ellipsoid.semi_major_axis, 6378137.);
+    assert_eq!(crs.datum.ellipsoid.inverse_flattening, 298.257223563);
+    assert_eq!(crs.angular_unit.name, "Degree");
+    assert_eq!(crs.epsg, Some(4326));
+}
+
+#[test]
+fn web_mercator() {
+    let crs: Crs = WEB_MERCATOR.parse().unwrap();
+
+    let projection = crs.projection.as_ref().unwrap();
+    assert_eq!(projection.method, "Mercator_Auxiliary_Sphere");
+    assert_eq!(projection.parameters.len(), 5);
+    assert_eq!(projection.linear_unit.factor, 1.);
+    assert_eq!(crs.geographic_name, "GCS_WGS_1984");
+    assert_eq!(crs.epsg, Some(3857));
+}
+
+#[test]
+fn utm_zones() {
+    for zone in [32, 33, 35] {
+        let wgs84: Crs = utm("WGS_1984", "WGS_1984", 298.257223563, zone)
+            .parse()
+            .unwrap();
+        assert_eq!(wgs84.epsg, Some(32600 + zone));
+
+        let etrs89: Crs = utm("ETRS_1989", "GRS_1980", 298.257222101, zone)
+            .parse()
+            .unwrap();
+        assert_eq!(etrs89.epsg, Some(25800 + zone));
+    }
+
+    let crs: Crs = utm("ETRS_1989", "GRS_1980", 298.257222101, 33)
+        .parse()
+        .unwrap();
+    let projection = crs.projection.unwrap();
+    assert_eq!(projection.parameter("central meridian"), Some(15.));
+    assert_eq!(projection.parameter("Scale_Factor"), Some(0.9996));
+}
+
+#[test]
+fn authority() {
+    // OGC style, with an authority, an axis and parentheses
+    let wkt = r#"GEOGCS("WGS 84",
+        DATUM("WGS_1984", SPHEROID("WGS 84", 6378137, 298.257223563, AUTHORITY("EPSG","7030"))),
+        PRIMEM("Greenwich", 0),
+        UNIT("degree", 0.0174532925199433),
+        AXIS("Latitude", NORTH),
+        AUTHORITY("EPSG", "4326"))"#;
+    let crs: Crs = wkt.parse().unwrap();
+    assert_eq!(crs.name, "WGS 84");
+    assert_eq!(crs.epsg, Some(4326));
+
+    // Not one we know
+    let crs: Crs = utm("WGS_1984", "WGS_1984", 298.257223563, 33)
+        .replace("0.9996", "0.9999")
+        .parse()
+        .unwrap();
+    assert_eq!(crs.epsg, None);
+}
+
+#[test]
+fn bad_wkt() {
+    assert!(matches!(
+        Crs::from_wkt(r#"GEOGCS["GCS_WGS_1984",DATUM["D_WGS_1984""#),
+        Err(Error::BadWkt { offset: 40, .. })
+    ));
+    assert!(matches!(
+        Crs::from_wkt(r#"GEOGCS["GCS_WGS_1984",PRIMEM["Greenwich",0.0]]"#),
+        Err(Error::BadWkt { offset: 0, .. })
+    ));
+    assert!(matches!(
+        Crs::from_wkt(r#"GEOCCS["Geocentric"]"#),
+        Err(Error::BadWkt { .. })
+    ));
+}
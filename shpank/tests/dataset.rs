@@ -0,0 +1,206 @@
+mod common;
+
+use std::{io::Write, path::PathBuf};
+
+use common::{shp_file, shx_file, DbfBuilder, RecordBuilder};
+use shpank::{
+    dataset::Dataset,
+    dbase::FieldType,
+    parse::{Error, Recovery},
+    shape::ShapeType,
+    spatial::Spatial,
+};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+const PRJ: &str = r#"GEOGCS["GCS_WGS_1984",DATUM["D_WGS_1984",SPHEROID["WGS_1984",6378137.0,298.257223563]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]]"#;
+
+fn files() -> (Vec<u8>, Vec<u8>) {
+    let point = |x| {
+        RecordBuilder::new(ShapeType::Point)
+            .doubles(&[x, x])
+            .build()
+    };
+    let shp = shp_file(&[point(1.), point(2.)]);
+    let dbf = DbfBuilder::new()
+        .field("name", FieldType::Character, 10, 0)
+        .record(&[b"one"])
+        .record(&[b"two"])
+        .build();
+
+    (shp, dbf)
+}
+
+/// Writes the files to a fresh directory.
+fn write_dir(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("shpank-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for (file_name, contents) in files {
+        std::fs::write(dir.join(file_name), contents).unwrap();
+    }
+
+    dir
+}
+
+#[test]
+fn found_case_insensitively() {
+    let (shp, dbf) = files();
+    let dir = write_dir(
+        "dataset-case",
+        &[
+            ("ROADS.SHP", &shp),
+            ("roads.dbf", &dbf),
+            ("Roads.Prj", PRJ.as_bytes()),
+            ("other.shx", b""),
+        ],
+    );
+
+    let dataset = Dataset::find_next_to(dir.join("roads")).unwrap();
+    assert_eq!(dataset.shp, Some(dir.join("ROADS.SHP")));
+    assert_eq!(dataset.prj, Some(dir.join("Roads.Prj")));
+    assert_eq!(dataset.missing(), ["shx", "cpg"]);
+
+    // Also by the path of one of the files
+    let spatial = Spatial::open(dir.join("roads.dbf")).unwrap();
+    assert_eq!(spatial.records().count(), 2);
+    assert_eq!(
+        spatial.warnings,
+        ["No .shx file found", "No .cpg file found"]
+    );
+    assert_eq!(spatial.crs.unwrap().epsg, Some(4326));
+
+    // A Latin-1 name in the .prj
+    let latin1 = PRJ.replace("GCS_WGS_1984", "GCS_Tr\u{F8}ndelag");
+    let latin1: Vec<u8> = latin1.chars().map(|c| c as u8).collect();
+    std::fs::write(dir.join("Roads.Prj"), latin1).unwrap();
+    let spatial = Spatial::open(dir.join("roads")).unwrap();
+    assert_eq!(spatial.crs.unwrap().epsg, Some(4326));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn missing_dbf() {
+    let (shp, _) = files();
+    let dir = write_dir("dataset-missing", &[("roads.shp", &shp)]);
+
+    match Spatial::open(dir.join("roads")) {
+        Err(Error::MissingFiles { name, missing }) => {
+            assert_eq!(name, "roads");
+            assert_eq!(missing, ["dbf"]);
+        }
+        other => panic!("expected missing files, got {other:?}"),
+    }
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn zip_archive() {
+    let (shp, dbf) = files();
+    let dir = write_dir("dataset-zip", &[]);
+    let zip_path = dir.join("norway-latest-free.shp.zip");
+
+    let mut zip = ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    for (entry, contents) in [
+        ("README", b"not a dataset".as_slice()),
+        ("layer.shp", &shp),
+        ("layer.dbf", &dbf),
+        ("layer.prj", PRJ.as_bytes()),
+        ("layer.cpg", b"UTF-8"),
+        ("other/roads.SHP", b""),
+    ] {
+        zip.start_file(entry, options).unwrap();
+        zip.write_all(contents).unwrap();
+    }
+    zip.finish().unwrap();
+
+    let archive = ZipArchive::new(std::fs::File::open(&zip_path).unwrap()).unwrap();
+    assert_eq!(Dataset::names_in_zip(&archive), ["layer", "roads"]);
+
+    let spatial = Spatial::open_zip(&zip_path, "layer").unwrap();
+    let names: Vec<_> = spatial
+        .records()
+        .map(|(_, dbf)| dbf.entries[0].to_string())
+        .collect();
+    assert_eq!(names, ["one", "two"]);
+    assert_eq!(spatial.warnings, ["No .shx file found"]);
+    assert!(spatial.crs.unwrap().is_geographic());
+
+    assert!(matches!(
+        Spatial::open_zip(&zip_path, "roads"),
+        Err(Error::MissingFiles { .. })
+    ));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn shx_checks_and_finds_records() {
+    let point = |x| {
+        RecordBuilder::new(ShapeType::Point)
+            .doubles(&[x, x])
+            .build()
+    };
+    let records = [point(1.), point(2.), point(3.)];
+    let mut shp = shp_file(&records);
+    // The second record's content length goes past the end of the file
+    let second = 100 + records[0].len();
+    shp[second + 4..second + 8].copy_from_slice(&i32::MAX.to_be_bytes());
+    let dbf = DbfBuilder::new()
+        .field("name", FieldType::Character, 10, 0)
+        .record(&[b"one"])
+        .record(&[b"two"])
+        .record(&[b"three"])
+        .build();
+    let shx = shx_file(&records);
+
+    // Without the index, nothing after the corrupt record can be found
+    let dir = write_dir("dataset-shx", &[("roads.shp", &shp), ("roads.dbf", &dbf)]);
+    let spatial = Spatial::open_with_recovery(dir.join("roads"), Recovery::Lenient).unwrap();
+    assert_eq!(spatial.shp.dropped, [1, 2]);
+
+    std::fs::write(dir.join("roads.shx"), &shx).unwrap();
+    let spatial = Spatial::open_with_recovery(dir.join("roads"), Recovery::Lenient).unwrap();
+    assert_eq!(spatial.shp.dropped, [1]);
+    let names: Vec<_> = spatial
+        .records()
+        .map(|(_, dbf)| dbf.entries[0].to_string())
+        .collect();
+    assert_eq!(names, ["one", "three"]);
+
+    // Also from an archive
+    let zip_path = dir.join("roads.zip");
+    let mut zip = ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+    for (entry, contents) in [
+        ("roads.shp", &shp),
+        ("roads.shx", &shx),
+        ("roads.dbf", &dbf),
+    ] {
+        zip.start_file(entry, SimpleFileOptions::default()).unwrap();
+        zip.write_all(contents).unwrap();
+    }
+    zip.finish().unwrap();
+    let spatial = Spatial::open_zip_with_recovery(&zip_path, "roads", Recovery::Lenient).unwrap();
+    assert_eq!(spatial.shp.dropped, [1]);
+
+    // An index which can't be parsed is left out
+    std::fs::write(dir.join("roads.shx"), b"not an index").unwrap();
+    let spatial = Spatial::open_with_recovery(dir.join("roads"), Recovery::Lenient).unwrap();
+    assert_eq!(spatial.shp.dropped, [1, 2]);
+    assert_eq!(spatial.warnings.len(), 3);
+    assert!(spatial.warnings[2].starts_with("Ignoring the .shx file"));
+    std::fs::write(dir.join("roads.shx"), &shx).unwrap();
+
+    // An index of other records than the .shp has
+    let (shp, dbf) = files();
+    std::fs::write(dir.join("roads.shp"), shp).unwrap();
+    std::fs::write(dir.join("roads.dbf"), dbf).unwrap();
+    assert!(matches!(
+        Spatial::open(dir.join("roads")),
+        Err(Error::UnexpectedData(_))
+    ));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use common::DbfBuilder;
+use shpank::{dbase::FieldType, encoding::Encoding, parse::Parser};
+
+fn names(builder: DbfBuilder) -> Vec<u8> {
+    builder
+        .field("name", FieldType::Character, 12, 0)
+        .record(&[b"Bj\xF8rn\xF8ya"])
+        .build()
+}
+
+fn first_name(parser: Parser<&[u8]>) -> String {
+    let dbf = parser.parse_dbase_file().unwrap();
+    dbf.records[0].entries[0].to_string()
+}
+
+#[test]
+fn language_driver() {
+    let bytes = names(DbfBuilder::new().language_driver(0x57));
+
+    let dbf = Parser::parse_dbf_buffer(&bytes).unwrap();
+    assert_eq!(dbf.header.language_driver, 0x57);
+    assert_eq!(dbf.header.encoding, Encoding::Windows1252);
+    assert_eq!(dbf.records[0].entries[0].to_string(), "Bjørnøya");
+
+    // Nordic DOS
+    let bytes = DbfBuilder::new()
+        .language_driver(0x66)
+        .field("name", FieldType::Character, 12, 0)
+        .record(&[b"\x91r\x9Bn \x86s"])
+        .build();
+    assert_eq!(first_name(Parser::with_reader(&bytes[..])), "ærøn ås");
+}
+
+#[test]
+fn unknown_encoding_is_utf8() {
+    let bytes = DbfBuilder::new()
+        .field("name", FieldType::Character, 12, 0)
+        .record(&["Bjørnøya".as_bytes()])
+        .build();
+    let dbf = Parser::parse_dbf_buffer(&bytes).unwrap();
+    assert_eq!(dbf.records[0].entries[0].to_string(), "Bjørnøya");
+    assert!(dbf.warnings.is_empty());
+
+    // Or Windows-1252 if it isn't
+    let bytes = names(DbfBuilder::new().record(&[b"\x80"]));
+    let dbf = Parser::parse_dbf_buffer(&bytes).unwrap();
+    assert_eq!(dbf.header.encoding, Encoding::Utf8);
+    assert_eq!(dbf.records[0].entries[0].to_string(), "€");
+    assert_eq!(dbf.records[1].entries[0].to_string(), "Bjørnøya");
+    assert_eq!(dbf.warnings.len(), 1);
+}
+
+#[test]
+fn explicit_override() {
+    let bytes = names(DbfBuilder::new().language_driver(0x66));
+    let parser = Parser::with_reader(&bytes[..]).with_encoding(Encoding::Latin1);
+
+    assert_eq!(first_name(parser), "Bjørnøya");
+}
+
+#[test]
+fn cpg_file() {
+    let dir = std::env::temp_dir().join(format!("shpank-cpg-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let dbf_path = dir.join("layer.dbf");
+    std::fs::write(&dbf_path, names(DbfBuilder::new())).unwrap();
+
+    let dbf = Parser::parse_dbf_file(&dbf_path).unwrap();
+    assert_eq!(dbf.warnings.len(), 1);
+
+    std::fs::write(dir.join("layer.cpg"), "ANSI 1252\r\n").unwrap();
+    let dbf = Parser::parse_dbf_file(&dbf_path).unwrap();
+    assert_eq!(dbf.records[0].entries[0].to_string(), "Bjørnøya");
+    assert!(dbf.warnings.is_empty());
+
+    // An unknown name falls back to Latin-1, or the language driver if there is one
+    std::fs::write(dir.join("layer.cpg"), "EBCDIC").unwrap();
+    let dbf = Parser::parse_dbf_file(&dbf_path).unwrap();
+    assert_eq!(dbf.header.encoding, Encoding::Latin1);
+    assert_eq!(dbf.records[0].entries[0].to_string(), "Bjørnøya");
+    assert_eq!(dbf.warnings.len(), 1);
+
+    std::fs::write(&dbf_path, names(DbfBuilder::new().language_driver(0x66))).unwrap();
+    let dbf = Parser::parse_dbf_file(&dbf_path).unwrap();
+    assert_eq!(dbf.header.encoding, Encoding::Cp865);
+    assert_eq!(dbf.warnings.len(), 1);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn cpg_names() {
+    for (name, encoding) in [
+        ("UTF-8", Encoding::Utf8),
+        ("utf8", Encoding::Utf8),
+        ("1252", Encoding::Windows1252),
+        ("windows-1252", Encoding::Windows1252),
+        ("ISO 8859-1", Encoding::Latin1),
+        ("ISO-8859-1", Encoding::Latin1),
+        ("CP865", Encoding::Cp865),
+        ("IBM850", Encoding::Cp850),
+    ] {
+        assert_eq!(name.parse::<Encoding>().unwrap(), encoding, "{name}");
+    }
+
+    assert!("EBCDIC".parse::<Encoding>().is_err());
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use common::{points, polygon};
+use shpank::{
+    geometry::{geodesic_distance, geodesic_ring_area},
+    rings::ring_contains,
+    shape::{MinimumBoundingRectangle, Point, PolyLine, Shape},
+};
+
+fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{actual} != {expected}"
+    );
+}
+
+#[test]
+fn polygon_with_hole() {
+    let shape = Shape::Polygon(polygon(&[
+        &[(0., 0.), (0., 4.), (4., 4.), (4., 0.), (0., 0.)],
+        &[(1., 1.), (2., 1.), (2., 2.), (1., 2.), (1., 1.)],
+    ]));
+
+    assert_eq!(shape.area(), 15.);
+    assert_eq!(shape.perimeter(), 20.);
+    assert_eq!(shape.length(), 0.);
+
+    // The hole pulls the centroid up and to the right
+    let centroid = shape.centroid().unwrap();
+    assert_close(centroid.x, (16. * 2. - 1.5) / 15., 1e-12);
+    assert_close(centroid.y, (16. * 2. - 1.5) / 15., 1e-12);
+}
+
+#[test]
+fn lines() {
+    let shape = Shape::PolyLine(PolyLine {
+        mbr: MinimumBoundingRectangle {
+            x: 0.0..3.0,
+            y: 0.0..4.0,
+        },
+        parts: vec![0, 2],
+        points: points(&[(0., 0.), (3., 4.), (0., 0.), (0., 1.), (1., 1.)]),
+    });
+
+    assert_eq!(shape.length(), 7.);
+    assert_eq!(shape.area(), 0.);
+
+    let centroid = shape.centroid().unwrap();
+    assert_close(centroid.x, (5. * 1.5 + 0.5) / 7., 1e-12);
+    assert_close(centroid.y, (5. * 2. + 0.5 + 1.) / 7., 1e-12);
+
+    // The only vertex which is not a line end
+    let on_line = shape.point_on_surface().unwrap();
+    assert_eq!((on_line.x, on_line.y), (0., 1.));
+}
+
+#[test]
+fn point_on_surface_of_u_shape() {
+    let exterior = [
+        (0., 0.),
+        (0., 3.),
+        (1., 3.),
+        (1., 1.),
+        (2., 1.),
+        (2., 3.),
+        (3., 3.),
+        (3., 0.),
+        (0., 0.),
+    ];
+    let shape = Shape::Polygon(polygon(&[&exterior]));
+
+    let centroid = shape.centroid().unwrap();
+    assert!(!ring_contains(&points(&exterior), centroid));
+
+    let inside = shape.point_on_surface().unwrap();
+    assert!(ring_contains(&points(&exterior), inside));
+}
+
+#[test]
+fn geodesic() {
+    // Along the equator a degree is the semi-major axis times the angle
+    let equator = geodesic_distance(Point { x: 0., y: 0. }, Point { x: 1., y: 0. });
+    assert_close(equator, 6_378_137. * 1f64.to_radians(), 1e-6);
+
+    let quarter_meridian = geodesic_distance(Point { x: 0., y: 0. }, Point { x: 0., y: 90. });
+    assert_close(quarter_meridian, 10_001_965.729, 1e-3);
+
+    // Flinders Peak to Buninyong, from Vincenty's formulae
+    let distance = geodesic_distance(
+        Point {
+            x: 144.424_867_89,
+            y: -37.951_033_42,
+        },
+        Point {
+            x: 143.926_495_53,
+            y: -37.652_821_14,
+        },
+    );
+    assert_close(distance, 54_972.271, 1e-2);
+
+    // A small square in Oslo, compared with the area between its parallels
+    let square = points(&[
+        (10.7, 59.9),
+        (10.7, 59.901),
+        (10.701, 59.901),
+        (10.701, 59.9),
+        (10.7, 59.9),
+    ]);
+    assert_close(geodesic_ring_area(&square), 6_235.369_546_8, 1e-6);
+
+    // Edges a degree long bulge a little away from the parallels
+    let shape = Shape::Polygon(polygon(&[&[
+        (0., 0.),
+        (0., 1.),
+        (1., 1.),
+        (1., 0.),
+        (0., 0.),
+    ]]));
+    assert_close(
+        shape.geodesic_area(),
+        12_308_463_894.,
+        1e-4 * 12_308_463_894.,
+    );
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use std::io::Cursor;
+
+use common::DbfBuilder;
+use shpank::{
+    dbase::{DbaseValue, FieldType},
+    memo::{MemoFile, MemoKind},
+    parse::{Error, Parser},
+};
+
+/// A memo file with the given header, and the memos in the blocks after it.
+fn memo_file(header: Vec<u8>, block_size: usize, blocks: &[&[u8]]) -> Vec<u8> {
+    let mut bytes = header;
+    bytes.resize(block_size, 0);
+    for block in blocks {
+        let start = bytes.len();
+        bytes.extend(*block);
+        bytes.resize(start + block.len().div_ceil(block_size) * block_size, 0);
+    }
+    bytes
+}
+
+#[test]
+fn dbase3() {
+    let long = [b'x'; 600];
+    let mut spanning = long.to_vec();
+    spanning.extend([0x1A, 0x1A]);
+
+    // Blocks 1 and 2 to 3
+    let bytes = memo_file(
+        vec![],
+        512,
+        &[b"Stavkirke fra 1200-tallet\x1A\x1A", &spanning],
+    );
+    let mut memo = MemoFile::new(Cursor::new(bytes), MemoKind::Dbt).unwrap();
+
+    assert_eq!(memo.block_size(), 512);
+    assert_eq!(memo.read(1).unwrap().unwrap(), b"Stavkirke fra 1200-tallet");
+    assert_eq!(memo.read(2).unwrap().unwrap(), long);
+}
+
+#[test]
+fn dbase3_short_last_memo() {
+    // Files don't have to end with a whole block
+    let mut bytes = memo_file(vec![], 512, &[b"Urnes\x1A\x1A"]);
+    bytes.extend(b"abc\x1A\x1A");
+    let mut memo = MemoFile::new(Cursor::new(bytes.clone()), MemoKind::Dbt).unwrap();
+    assert_eq!(memo.read(2).unwrap().unwrap(), b"abc");
+
+    // Nor end the memo
+    bytes.truncate(bytes.len() - 2);
+    let mut memo = MemoFile::new(Cursor::new(bytes), MemoKind::Dbt).unwrap();
+    assert_eq!(memo.read(2).unwrap().unwrap(), b"abc");
+    assert!(matches!(
+        memo.read(3),
+        Err(Error::BadMemoBlock { block: 3 })
+    ));
+}
+
+#[test]
+fn dbase4() {
+    let mut header = vec![0; 24];
+    header[20..22].copy_from_slice(&64u16.to_le_bytes());
+
+    let mut block = vec![0xFF, 0xFF, 0x08, 0x00];
+    block.extend((8 + 5u32).to_le_bytes());
+    block.extend(b"Fjord");
+
+    let bytes = memo_file(header, 64, &[&block]);
+    let mut memo = MemoFile::new(Cursor::new(bytes), MemoKind::Dbt).unwrap();
+
+    assert_eq!(memo.block_size(), 64);
+    assert_eq!(memo.read(1).unwrap().unwrap(), b"Fjord");
+    assert!(matches!(
+        memo.read(5),
+        Err(Error::BadMemoBlock { block: 5 })
+    ));
+}
+
+#[test]
+fn foxpro() {
+    let mut header = vec![0; 8];
+    header[6..8].copy_from_slice(&32u16.to_be_bytes());
+
+    let text = [&1u32.to_be_bytes()[..], &4u32.to_be_bytes(), b"Bre!"].concat();
+    let picture = [&0u32.to_be_bytes()[..], &2u32.to_be_bytes(), &[1, 2]].concat();
+
+    let bytes = memo_file(header, 32, &[&text, &picture]);
+    let mut memo = MemoFile::new(Cursor::new(bytes), MemoKind::Fpt).unwrap();
+
+    assert_eq!(memo.read(1).unwrap().unwrap(), b"Bre!");
+    assert!(memo.read(2).unwrap().is_none());
+}
+
+#[test]
+fn resolved_next_to_dbf() {
+    let dir = std::env::temp_dir().join(format!("shpank-memo-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let dbf = DbfBuilder::new()
+        .field("name", FieldType::Character, 8, 0)
+        .field("notes", FieldType::Memo, 10, 0)
+        .record(&[b"Urnes", b"         1"])
+        .record(&[b"Borgund", b"          "])
+        .build();
+    let dbt = memo_file(vec![], 512, &[b"Eldste stavkirke\x1A\x1A"]);
+
+    let dbf_path = dir.join("churches.dbf");
+    std::fs::write(&dbf_path, &dbf).unwrap();
+
+    // Without the memo file only the block is known
+    let unresolved = Parser::parse_dbf_file(&dbf_path).unwrap();
+    assert_eq!(unresolved.records[0].entries[1], DbaseValue::Memo(1));
+
+    std::fs::write(dir.join("churches.dbt"), &dbt).unwrap();
+    let resolved = Parser::parse_dbf_file(&dbf_path).unwrap();
+    assert_eq!(
+        resolved.records[0].entries[1],
+        DbaseValue::Text("Eldste stavkirke".into())
+    );
+    assert_eq!(resolved.records[1].entries[1], DbaseValue::Null);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,301 @@
+mod common;
+
+use common::DbfBuilder;
+use shpank::{
+    dbase::{DbaseDate, DbaseDateTime, DbaseValue, DeletedRecords, FieldType},
+    parse::{Error, Parser},
+};
+
+fn example() -> DbfBuilder {
+    DbfBuilder::new()
+        .field("osm_id", FieldType::Character, 10, 0)
+        .field("code", FieldType::Numeric, 4, 0)
+        .field("name", FieldType::Character, 20, 0)
+}
+
+#[test]
+fn parse_records() {
+    let bytes = example()
+        .record(&[b"1234", b"2001", b"Oslo"])
+        .record(&[b"5678", b"2002", b"Bergen"])
+        .build();
+
+    let dbf = Parser::parse_dbf_buffer(&bytes).unwrap();
+
+    assert_eq!(dbf.header.num_records, 2);
+    assert_eq!(dbf.header.index_of("NAME"), Some(2));
+    assert_eq!(
+        dbf.records[1].entries,
+        [
+            DbaseValue::Text("5678".into()),
+            DbaseValue::Integer(2002),
+            DbaseValue::Text("Bergen".into())
+        ]
+    );
+}
+
+#[test]
+fn bad_header_terminator() {
+    let mut bytes = example().build();
+    bytes[32 * 4] = 0x0A;
+
+    let err = Parser::parse_dbf_buffer(&bytes).unwrap_err();
+    assert!(matches!(
+        err,
+        Error::BadHeaderTerminator {
+            offset: 128,
+            found: 0x0A
+        }
+    ));
+}
+
+#[test]
+fn deleted_record_is_an_error() {
+    let bytes = example()
+        .record(&[b"1234", b"2001", b"Oslo"])
+        .record_with_flag(b'*', &[b"5678", b"2002", b"Bergen"])
+        .build();
+
+    let err = Parser::parse_dbf_buffer_with(&bytes, DeletedRecords::Error).unwrap_err();
+    assert!(matches!(err, Error::DeletedRecord { offset: 164 }));
+}
+
+#[test]
+fn deleted_records_policy() {
+    let bytes = example()
+        .record(&[b"1234", b"2001", b"Oslo"])
+        .record_with_flag(b'*', &[b"5678", b"2002", b"Bergen"])
+        .record(&[b"9012", b"2003", b"Trondheim"])
+        .build();
+
+    let flagged = Parser::parse_dbf_buffer(&bytes).unwrap();
+    let deleted: Vec<_> = flagged.records.iter().map(|r| r.deleted).collect();
+    assert_eq!(deleted, [false, true, false]);
+
+    let skipped = Parser::parse_dbf_buffer_with(&bytes, DeletedRecords::Skip).unwrap();
+    let names: Vec<_> = skipped
+        .records
+        .iter()
+        .map(|r| r.entries[2].to_string())
+        .collect();
+    assert_eq!(names, ["Oslo", "Trondheim"]);
+}
+
+#[test]
+fn truncated() {
+    let bytes = example().record(&[b"1234", b"2001", b"Oslo"]).build();
+
+    let err = Parser::parse_dbf_buffer(&bytes[..150]).unwrap_err();
+    assert!(matches!(err, Error::Truncated { offset: 144, .. }));
+}
+
+#[test]
+fn streaming_records() {
+    let bytes = example()
+        .record(&[b"1234", b"2001", b"Oslo"])
+        .record(&[b"5678", b"2002", b"Bergen"])
+        .build();
+
+    let mut parser = Parser::with_reader(&bytes[..]);
+    let header = parser.parse_dbase_header().unwrap();
+    let names: Vec<_> = parser
+        .dbf_records(&header)
+        .map(|record| record.unwrap().entries[2].to_string())
+        .collect();
+
+    assert_eq!(names, ["Oslo", "Bergen"]);
+    assert_eq!(
+        parser.num_bytes_read(),
+        header.dbase_num_bytes_header_and_records()
+    );
+}
+
+#[test]
+fn typed_values() {
+    let bytes = DbfBuilder::new()
+        .field("maxspeed", FieldType::Numeric, 5, 0)
+        .field("area", FieldType::Numeric, 8, 2)
+        .field("ratio", FieldType::FloatingPoint, 10, 3)
+        .field("opened", FieldType::Date, 8, 0)
+        .field("oneway", FieldType::Logical, 1, 0)
+        .field("notes", FieldType::Memo, 10, 0)
+        .record(&[
+            b"   80",
+            b"  123.45",
+            b" 1.500e-1",
+            b"19700101",
+            b"T",
+            b"         7",
+        ])
+        .record(&[
+            b"     ",
+            b"        ",
+            b"          ",
+            b"        ",
+            b"?",
+            b"          ",
+        ])
+        .build();
+
+    let dbf = Parser::parse_dbf_buffer(&bytes).unwrap();
+
+    assert_eq!(
+        dbf.records[0].entries,
+        [
+            DbaseValue::Integer(80),
+            DbaseValue::Decimal(123.45),
+            DbaseValue::Float(0.15),
+            DbaseValue::Date(DbaseDate {
+                year: 1970,
+                month: 1,
+                day: 1
+            }),
+            DbaseValue::Bool(true),
+            DbaseValue::Memo(7),
+        ]
+    );
+    assert!(dbf.records[1].entries.iter().all(DbaseValue::is_null));
+}
+
+#[test]
+fn bad_value() {
+    let bytes = DbfBuilder::new()
+        .field("maxspeed", FieldType::Numeric, 5, 0)
+        .record(&[b"fast!"])
+        .build();
+
+    let err = Parser::parse_dbf_buffer(&bytes).unwrap_err();
+    assert!(matches!(
+        err,
+        Error::BadValue {
+            offset: 66,
+            type_: FieldType::Numeric,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn field_descriptor() {
+    // dBASE IV, in work area 3 and with an .mdx tag
+    let mut descriptor = b"lanes\0\0\0\0\0\0N".to_vec();
+    descriptor.extend([0; 4]);
+    descriptor.extend([2, 0]);
+    descriptor.extend([0, 0, 3, 0, 0, 0]);
+    descriptor.extend([0; 7]);
+    descriptor.push(1);
+    assert_eq!(descriptor[20], 3);
+
+    let field = Parser::with_reader(&descriptor[..])
+        .parse_dbase_field_descriptor()
+        .unwrap();
+
+    assert_eq!(field.name, "lanes");
+    assert_eq!((field.field_length, field.decimal_count), (2, 0));
+    assert_eq!(field.work_area, 3);
+    assert!(field.indexed);
+}
+
+#[test]
+fn visual_foxpro() {
+    let mut date_time = 2440588i32.to_le_bytes().to_vec();
+    date_time.extend(3_661_000u32.to_le_bytes());
+
+    let bytes = DbfBuilder::new()
+        .visual_foxpro()
+        .field("population", FieldType::Integer, 4, 0)
+        .field("length", FieldType::Double, 8, 0)
+        .field("price", FieldType::Currency, 8, 4)
+        .field("updated", FieldType::DateTime, 8, 0)
+        .field("a_long_name", FieldType::Character, 3, 0)
+        .record(&[
+            &(-42i32).to_le_bytes(),
+            &1.25f64.to_le_bytes(),
+            &123_4567i64.to_le_bytes(),
+            &date_time,
+            b"abc",
+        ])
+        .build();
+
+    let dbf = Parser::parse_dbf_buffer(&bytes).unwrap();
+
+    assert!(dbf.header.is_visual_foxpro());
+    assert_eq!(dbf.header.fields.len(), 5);
+    assert_eq!(dbf.header.fields[2].decimal_count, 4);
+    assert_eq!(dbf.header.index_of("a_long_name"), Some(4));
+    assert_eq!(
+        dbf.records[0].entries,
+        [
+            DbaseValue::Integer(-42),
+            DbaseValue::Float(1.25),
+            DbaseValue::Decimal(123.4567),
+            DbaseValue::DateTime(DbaseDateTime {
+                date: DbaseDate {
+                    year: 1970,
+                    month: 1,
+                    day: 1
+                },
+                millis: 3_661_000
+            }),
+            DbaseValue::Text("abc".into()),
+        ]
+    );
+    assert_eq!(dbf.records[0].entries[3].to_string(), "1970-01-01T01:01:01");
+}
+
+#[test]
+fn dbase7() {
+    let ordered_i32 = |v: i32| (v ^ i32::MIN).to_be_bytes();
+    let ordered_f64 = |v: f64| {
+        let bits = v.to_bits();
+        match v.is_sign_negative() {
+            true => !bits,
+            false => bits | 1 << 63,
+        }
+        .to_be_bytes()
+    };
+
+    let fields: [(&str, u8, u8); 3] = [("id", b'+', 4), ("depth", b'O', 8), ("seen", b'@', 8)];
+    let header_bytes = 68 + 48 * fields.len() + 1;
+
+    let mut bytes = vec![0x04, 124, 6, 30];
+    bytes.extend(1u32.to_le_bytes());
+    bytes.extend((header_bytes as u16).to_le_bytes());
+    bytes.extend(21u16.to_le_bytes());
+    bytes.extend([0; 20]);
+    bytes.extend([0; 36]);
+    for (name, type_, length) in fields {
+        let mut descriptor = [0; 48];
+        descriptor[..name.len()].copy_from_slice(name.as_bytes());
+        descriptor[32] = type_;
+        descriptor[33] = length;
+        bytes.extend(descriptor);
+    }
+    bytes.push(0x0D);
+
+    bytes.push(b' ');
+    bytes.extend(ordered_i32(7));
+    bytes.extend(ordered_f64(-12.5));
+    bytes.extend(ordered_f64((2440589. * 86_400_000.) + 1000.));
+    bytes.push(0x1A);
+
+    let dbf = Parser::parse_dbf_buffer(&bytes).unwrap();
+
+    assert!(dbf.header.is_dbase7());
+    assert_eq!(dbf.header.index_of("depth"), Some(1));
+    assert_eq!(
+        dbf.records[0].entries,
+        [
+            DbaseValue::Integer(7),
+            DbaseValue::Float(-12.5),
+            DbaseValue::DateTime(DbaseDateTime {
+                date: DbaseDate {
+                    year: 1970,
+                    month: 1,
+                    day: 2
+                },
+                millis: 1000
+            }),
+        ]
+    );
+}
@@ -0,0 +1,430 @@
+mod common;
+
+use common::{shp_file, shx_file, RecordBuilder};
+use shpank::{
+    parse::{Error, Parser, Recovery},
+    shape::{Point, Shape, ShapeType},
+};
+
+fn parse(bytes: &[u8]) -> Shape {
+    let mut parser = Parser::with_reader(bytes);
+    let record = parser.parse_record().unwrap();
+    assert_eq!(parser.num_bytes_read(), bytes.len());
+
+    record.shape
+}
+
+fn xy(points: &[Point]) -> Vec<(f64, f64)> {
+    points.iter().map(|p| (p.x, p.y)).collect()
+}
+
+#[test]
+fn multipoint() {
+    let points = [(1., 2.), (3., 4.), (-5., 6.5)];
+    let bytes = RecordBuilder::new(ShapeType::MultiPoint)
+        .doubles(&[-5., 2., 3., 6.5])
+        .integer(points.len() as i32)
+        .points(&points)
+        .build();
+
+    let Shape::MultiPoint(multipoint) = parse(&bytes) else {
+        panic!("expected a multipoint");
+    };
+
+    assert_eq!(multipoint.mbr.x, -5.0..3.0);
+    assert_eq!(multipoint.mbr.y, 2.0..6.5);
+    assert_eq!(xy(&multipoint.points), points);
+}
+
+#[test]
+fn polyline_z_without_measures() {
+    let points = [(0., 0.), (1., 0.), (1., 1.)];
+    let bytes = RecordBuilder::new(ShapeType::PolylineZ)
+        .doubles(&[0., 0., 1., 1.])
+        .integer(1)
+        .integer(points.len() as i32)
+        .integer(0)
+        .points(&points)
+        .doubles(&[10., 30.])
+        .doubles(&[10., 20., 30.])
+        .build();
+
+    let Shape::PolylineZ(polyline) = parse(&bytes) else {
+        panic!("expected a polyline z");
+    };
+
+    assert_eq!(polyline.parts, [0]);
+    assert_eq!(polyline.z_range, 10.0..30.0);
+    assert_eq!(
+        polyline.points_3d().collect::<Vec<_>>(),
+        [[0., 0., 10.], [1., 0., 20.], [1., 1., 30.]]
+    );
+    assert!(polyline.m.is_none());
+}
+
+#[test]
+fn multipoint_z_with_measures() {
+    let points = [(1., 2.), (3., 4.)];
+    let bytes = RecordBuilder::new(ShapeType::MultiPointZ)
+        .doubles(&[1., 2., 3., 4.])
+        .integer(points.len() as i32)
+        .points(&points)
+        .doubles(&[5., 6.])
+        .doubles(&[5., 6.])
+        .doubles(&[100., 200.])
+        .doubles(&[100., 200.])
+        .build();
+
+    let Shape::MultiPointZ(multipoint) = parse(&bytes) else {
+        panic!("expected a multipoint z");
+    };
+
+    assert_eq!(xy(&multipoint.points), points);
+    assert_eq!(multipoint.z, [5., 6.]);
+    let m = multipoint.m.unwrap();
+    assert_eq!(m.range, Some(100.0..200.0));
+    assert_eq!(m.values, [Some(100.), Some(200.)]);
+}
+
+#[test]
+fn point_z_optional_measure() {
+    let without = RecordBuilder::new(ShapeType::PointZ)
+        .doubles(&[1., 2., 3.])
+        .build();
+    let with = RecordBuilder::new(ShapeType::PointZ)
+        .doubles(&[1., 2., 3., 4.])
+        .build();
+
+    let Shape::PointZ(point) = parse(&without) else {
+        panic!("expected a point z");
+    };
+    assert_eq!((point.x, point.y, point.z, point.m), (1., 2., 3., None));
+
+    let Shape::PointZ(point) = parse(&with) else {
+        panic!("expected a point z");
+    };
+    assert_eq!((point.x, point.y, point.z, point.m), (1., 2., 3., Some(4.)));
+}
+
+#[test]
+fn polyline_m_no_data() {
+    let points = [(0., 0.), (10., 0.), (10., 10.)];
+    let bytes = RecordBuilder::new(ShapeType::PolylineM)
+        .doubles(&[0., 0., 10., 10.])
+        .integer(1)
+        .integer(points.len() as i32)
+        .integer(0)
+        .points(&points)
+        .doubles(&[0., 2.])
+        .doubles(&[0., 1., -1e39])
+        .build();
+
+    let Shape::PolylineM(polyline) = parse(&bytes) else {
+        panic!("expected a polyline m");
+    };
+
+    assert_eq!(polyline.m.range, Some(0.0..2.0));
+    assert_eq!(polyline.m.values, [Some(0.), Some(1.), None]);
+}
+
+#[test]
+fn polyline_m_interpolate() {
+    // Two parts, kilometre posts along each
+    let points = [(0., 0.), (10., 0.), (10., 10.), (100., 0.), (100., 20.)];
+    let bytes = RecordBuilder::new(ShapeType::PolylineM)
+        .doubles(&[0., 0., 100., 20.])
+        .integer(2)
+        .integer(points.len() as i32)
+        .integer(0)
+        .integer(3)
+        .points(&points)
+        .doubles(&[0., 40.])
+        .doubles(&[0., 1., 2., 20., 40.])
+        .build();
+
+    let Shape::PolylineM(polyline) = parse(&bytes) else {
+        panic!("expected a polyline m");
+    };
+
+    let p = polyline.point_at_measure(1.5).unwrap();
+    assert_eq!((p.x, p.y), (10., 5.));
+
+    let p = polyline.point_at_measure(30.).unwrap();
+    assert_eq!((p.x, p.y), (100., 10.));
+
+    // Between parts there is no line
+    assert!(polyline.point_at_measure(10.).is_none());
+
+    assert_eq!(polyline.measure_at_distance(0, 15.), Some(1.5));
+    assert_eq!(polyline.measure_at_distance(1, 5.), Some(25.));
+    assert_eq!(polyline.measure_at_distance(1, 25.), None);
+    assert_eq!(polyline.measure_at_distance(2, 0.), None);
+}
+
+fn triangle_area(positions: &[[f64; 3]], indices: &[u32]) -> f64 {
+    indices
+        .chunks(3)
+        .map(|t| {
+            let [a, b, c] = [0, 1, 2].map(|i| positions[t[i] as usize]);
+            ((b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])) / 2.
+        })
+        .sum()
+}
+
+#[test]
+fn multipatch_triangulate() {
+    use shpank::shape::PatchType;
+
+    let strip = [(0., 0.), (0., 1.), (1., 0.), (1., 1.)];
+    // Clockwise outer ring, counter-clockwise hole, both closed
+    let outer = [(0., 0.), (0., 4.), (4., 4.), (4., 0.), (0., 0.)];
+    let inner = [(1., 1.), (2., 1.), (2., 2.), (1., 2.), (1., 1.)];
+
+    let points: Vec<_> = strip.iter().chain(&outer).chain(&inner).copied().collect();
+
+    let bytes = RecordBuilder::new(ShapeType::MultiPatch)
+        .doubles(&[0., 0., 4., 4.])
+        .integer(3)
+        .integer(points.len() as i32)
+        .integer(0)
+        .integer(4)
+        .integer(9)
+        .integer(PatchType::TriangleStrip as i32)
+        .integer(PatchType::OuterRing as i32)
+        .integer(PatchType::InnerRing as i32)
+        .points(&points)
+        .doubles(&[7., 7.])
+        .doubles(&vec![7.; points.len()])
+        .build();
+
+    let Shape::MultiPatch(patch) = parse(&bytes) else {
+        panic!("expected a multipatch");
+    };
+
+    assert_eq!(
+        patch.part_types,
+        [
+            PatchType::TriangleStrip,
+            PatchType::OuterRing,
+            PatchType::InnerRing
+        ]
+    );
+    assert!(patch.m.is_none());
+
+    let mesh = patch.triangulate();
+    assert_eq!(mesh.positions.len(), points.len());
+    assert!(mesh.positions.iter().all(|p| p[2] == 7.));
+
+    let (strip, rings) = mesh.indices.split_at(6);
+    assert_eq!(strip, [0, 1, 2, 2, 1, 3]);
+
+    // Clockwise like the outer ring, and the hole is left out
+    assert_eq!(triangle_area(&mesh.positions, rings), -(16. - 1.));
+    assert!(rings.iter().all(|i| (4..points.len() as u32).contains(i)));
+}
+
+#[test]
+fn record_length_mismatch() {
+    let mut bytes = RecordBuilder::new(ShapeType::Point)
+        .doubles(&[1., 2.])
+        .build();
+    // Claim the content is 4 bytes longer than it is, followed by another record
+    bytes[4..8].copy_from_slice(&12i32.to_be_bytes());
+    bytes.extend(RecordBuilder::new(ShapeType::Null).build());
+
+    let err = Parser::with_reader(&bytes[..]).parse_record().unwrap_err();
+    assert!(matches!(
+        err,
+        Error::RecordLengthMismatch {
+            record_number: 1,
+            offset: 0,
+            expected: 24,
+            actual: 20
+        }
+    ));
+}
+
+#[test]
+fn truncated() {
+    let bytes = RecordBuilder::new(ShapeType::Point)
+        .doubles(&[1., 2.])
+        .build();
+
+    let err = Parser::with_reader(&bytes[..bytes.len() - 3])
+        .parse_record()
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::Truncated {
+            offset: 20,
+            needed: 8
+        }
+    ));
+}
+
+#[test]
+fn bad_counts() {
+    let negative = RecordBuilder::new(ShapeType::MultiPoint)
+        .doubles(&[0., 0., 0., 0.])
+        .integer(-1)
+        .build();
+    let too_many = RecordBuilder::new(ShapeType::MultiPoint)
+        .doubles(&[0., 0., 0., 0.])
+        .integer(1_000_000)
+        .points(&[(0., 0.)])
+        .build();
+
+    for bytes in [negative, too_many] {
+        let err = Parser::with_reader(&bytes[..]).parse_record().unwrap_err();
+        assert!(matches!(err, Error::BadCount { offset: 44, .. }));
+    }
+}
+
+#[test]
+fn bad_parts() {
+    let bytes = RecordBuilder::new(ShapeType::PolyLine)
+        .doubles(&[0., 0., 1., 1.])
+        .integer(2)
+        .integer(2)
+        .integer(0)
+        .integer(3)
+        .points(&[(0., 0.), (1., 1.)])
+        .build();
+
+    let err = Parser::with_reader(&bytes[..]).parse_record().unwrap_err();
+    assert!(matches!(err, Error::BadParts { offset: 52 }));
+}
+
+#[test]
+fn lenient_skips_corrupt_records() {
+    let point = |x| {
+        RecordBuilder::new(ShapeType::Point)
+            .doubles(&[x, x])
+            .build()
+    };
+    let mut corrupt = point(2.);
+    // Not a shape type
+    corrupt[8] = 99;
+
+    let bytes = shp_file(&[point(1.), corrupt, point(3.)]);
+
+    assert!(Parser::parse_shp_buffer(&bytes).is_err());
+
+    let shp = Parser::parse_shp_buffer_with(&bytes, Recovery::Lenient).unwrap();
+    let xs: Vec<_> = shp
+        .records
+        .iter()
+        .map(|record| match record.shape {
+            Shape::Point(p) => p.x,
+            _ => panic!("expected points"),
+        })
+        .collect();
+
+    assert_eq!(xs, [1., 3.]);
+    assert_eq!(shp.dropped, [1]);
+    assert_eq!(shp.diagnostics.len(), 1);
+    assert_eq!(shp.diagnostics[0].record_index, 1);
+    assert_eq!(shp.diagnostics[0].offset, 100 + 28);
+}
+
+#[test]
+fn lenient_stops_at_truncation() {
+    let point = |x| {
+        RecordBuilder::new(ShapeType::Point)
+            .doubles(&[x, x])
+            .build()
+    };
+    let mut bytes = shp_file(&[point(1.), point(2.)]);
+    bytes.truncate(bytes.len() - 4);
+
+    let shp = Parser::parse_shp_buffer_with(&bytes, Recovery::Lenient).unwrap();
+
+    assert_eq!(shp.records.len(), 1);
+    assert_eq!(shp.dropped, [1]);
+    assert_eq!(shp.diagnostics[0].record_number, Some(1));
+}
+
+#[test]
+fn lenient_resyncs_with_index() {
+    let point = |x| {
+        RecordBuilder::new(ShapeType::Point)
+            .doubles(&[x, x])
+            .build()
+    };
+    let records = [point(1.), point(2.), point(3.)];
+    let mut bytes = shp_file(&records);
+    // The second record's content length goes past the end of the file
+    let second = 100 + records[0].len();
+    bytes[second + 4..second + 8].copy_from_slice(&i32::MAX.to_be_bytes());
+
+    let parse = |parser: Parser<&[u8]>| {
+        let mut parser = parser.with_recovery(Recovery::Lenient);
+        let mut records = parser.shp_records().unwrap();
+        let xs: Vec<_> = records
+            .by_ref()
+            .map(|record| match record.unwrap().shape {
+                Shape::Point(p) => p.x,
+                _ => panic!("expected points"),
+            })
+            .collect();
+        (xs, records.dropped().to_vec())
+    };
+
+    // Without the index the rest of the file is lost
+    let (xs, dropped) = parse(Parser::with_reader(&bytes[..]).with_num_records(3));
+    assert_eq!(xs, [1.]);
+    assert_eq!(dropped, [1, 2]);
+
+    let shx = Parser::parse_shx_buffer(&shx_file(&records)).unwrap();
+    let (xs, dropped) = parse(Parser::with_reader(&bytes[..]).with_index(&shx));
+    assert_eq!(xs, [1., 3.]);
+    assert_eq!(dropped, [1]);
+}
+
+#[test]
+fn lenient_huge_content_length() {
+    let point = RecordBuilder::new(ShapeType::Point)
+        .doubles(&[1., 1.])
+        .build();
+    let mut bytes = shp_file(&[point]);
+    // Both the file and the record claim to be almost 4 GiB
+    bytes[24..28].copy_from_slice(&i32::MAX.to_be_bytes());
+    bytes[100 + 4..100 + 8].copy_from_slice(&(i32::MAX - 100).to_be_bytes());
+
+    let shp = Parser::parse_shp_buffer_with(&bytes, Recovery::Lenient).unwrap();
+    assert!(shp.records.is_empty());
+    assert_eq!(shp.dropped, [0]);
+    assert!(shp.diagnostics[0].problem.starts_with("Truncated"));
+}
+
+#[test]
+fn streaming_records() {
+    let point = |x| {
+        RecordBuilder::new(ShapeType::Point)
+            .doubles(&[x, x])
+            .build()
+    };
+    let mut corrupt = point(2.);
+    corrupt[8] = 99;
+    let bytes = shp_file(&[point(1.), corrupt, point(3.)]);
+
+    let mut parser = Parser::with_reader(&bytes[..]);
+    let mut records = parser.shp_records().unwrap();
+    assert!(records.next().unwrap().is_ok());
+    assert!(records.next().unwrap().is_err());
+    // Strict parsing does not continue past an error
+    assert!(records.next().is_none());
+
+    let mut parser = Parser::with_reader(&bytes[..]).with_recovery(Recovery::Lenient);
+    let mut records = parser.shp_records().unwrap();
+    let xs: Vec<_> = records
+        .by_ref()
+        .map(|record| match record.unwrap().shape {
+            Shape::Point(p) => p.x,
+            _ => panic!("expected points"),
+        })
+        .collect();
+    assert_eq!(xs, [1., 3.]);
+    assert_eq!(records.dropped(), [1]);
+    assert_eq!(parser.diagnostics().len(), 1);
+}
@@ -0,0 +1,98 @@
+use shpank::{
+    project::{CoordinateSystem, Spheroid, Transform},
+    shape::{MinimumBoundingRectangle, Point, PolyLine, Shape},
+};
+
+fn assert_close(actual: [f64; 3], expected: [f64; 3], tolerance: f64) {
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < tolerance, "{actual:?} != {expected:?}");
+    }
+}
+
+#[test]
+fn web_mercator() {
+    let mercator = CoordinateSystem::WebMercator;
+
+    assert_close(mercator.project([0., 0., 0.]), [0., 0., 0.], 1e-9);
+    assert_close(
+        mercator.project([180., 85.051_128_779_806_59, 0.]),
+        [20_037_508.342_789_244, 20_037_508.342_789_244, 0.],
+        1e-6,
+    );
+
+    let oslo = [10.75, 59.91, 0.];
+    assert_close(mercator.unproject(mercator.project(oslo)), oslo, 1e-12);
+}
+
+#[test]
+fn utm() {
+    let zone_32 = CoordinateSystem::utm(32, true, Spheroid::WGS84);
+
+    // On the central meridian northing is the scaled meridian arc
+    assert_close(zone_32.project([9., 0., 0.]), [500_000., 0., 0.], 1e-6);
+    assert_close(
+        zone_32.project([9., 60., 0.]),
+        [500_000., 6_651_411.190, 0.],
+        1e-3,
+    );
+
+    // Round trips, also near the edges of the zones used in Norway
+    for (zone, point) in [
+        (32, [4.5, 58.0, 10.]),
+        (33, [15.0, 68.5, 0.]),
+        (33, [17.9, 78.2, 0.]),
+        (35, [29.9, 70.1, 0.]),
+    ] {
+        for spheroid in [Spheroid::WGS84, Spheroid::GRS80] {
+            let system = CoordinateSystem::utm(zone, true, spheroid);
+            // The series is good to about a millimetre, 1e-8 degrees
+            assert_close(system.unproject(system.project(point)), point, 1e-8);
+        }
+    }
+
+    assert_eq!(
+        "EPSG:25833".parse::<CoordinateSystem>().unwrap(),
+        CoordinateSystem::utm(33, true, Spheroid::GRS80)
+    );
+    assert_eq!("utm32n".parse::<CoordinateSystem>().unwrap(), zone_32);
+}
+
+#[test]
+fn local_enu() {
+    let origin = [10.75, 59.91, 100.];
+    let enu = CoordinateSystem::LocalEnu { origin };
+
+    assert_close(enu.project(origin), [0., 0., 0.], 1e-6);
+
+    // A hundredth of a degree north is about 1.1 km, and slightly below the plane
+    let [east, north, up] = enu.project([10.75, 59.92, 100.]);
+    assert!(east.abs() < 1e-6);
+    assert!((north - 1_114.).abs() < 2., "{north}");
+    assert!(up < 0. && up > -1.);
+
+    let point = [10.8, 59.95, 250.];
+    assert_close(enu.unproject(enu.project(point)), point, 1e-6);
+}
+
+#[test]
+fn shapes() {
+    let mut line = Shape::PolyLine(PolyLine {
+        mbr: MinimumBoundingRectangle {
+            x: 9.0..10.0,
+            y: 60.0..61.0,
+        },
+        parts: vec![0],
+        points: vec![Point { x: 9., y: 60. }, Point { x: 10., y: 61. }],
+    });
+
+    let transform = Transform::new(CoordinateSystem::Geographic, "32632".parse().unwrap());
+    transform.shape(&mut line);
+
+    let Shape::PolyLine(line) = line else {
+        panic!("expected a polyline");
+    };
+    assert!((line.points[0].x - 500_000.).abs() < 1e-6);
+    assert!((line.points[0].y - 6_651_411.190).abs() < 1e-3);
+    assert_eq!(line.mbr.x.start, line.points[0].x);
+    assert_eq!(line.mbr.y.end, line.points[1].y);
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use common::polygon_of;
+use shpank::{
+    rings::{signed_area, Orientation},
+    shape::{Point, Polygon},
+};
+
+/// A closed square ring, clockwise unless `counter_clockwise`.
+fn square(x: f64, y: f64, size: f64, counter_clockwise: bool) -> Vec<Point> {
+    let mut ring = vec![
+        Point { x, y },
+        Point { x, y: y + size },
+        Point {
+            x: x + size,
+            y: y + size,
+        },
+        Point { x: x + size, y },
+        Point { x, y },
+    ];
+    if counter_clockwise {
+        ring.reverse();
+    }
+    ring
+}
+
+/// The first point of the exterior and interiors of each polygon.
+fn starts(polygon: &Polygon) -> Vec<(usize, Vec<usize>)> {
+    polygon
+        .polygons()
+        .into_iter()
+        .map(|rings| {
+            let interiors = rings.interiors.iter().map(|ring| ring.start).collect();
+            (rings.exterior.start, interiors)
+        })
+        .collect()
+}
+
+#[test]
+fn orientation() {
+    let outer = square(0., 0., 2., false);
+    assert_eq!(signed_area(&outer), -4.);
+    assert_eq!(Orientation::of(&outer), Orientation::Clockwise);
+
+    // Open rings too
+    let hole = square(0., 0., 2., true);
+    assert_eq!(signed_area(&hole[..4]), 4.);
+    assert_eq!(Orientation::of(&hole[..4]), Orientation::CounterClockwise);
+}
+
+#[test]
+fn holes_belong_to_the_smallest_outer_ring() {
+    // A lake with an island, which has a pond, next to a separate field
+    let polygon = polygon_of(&[
+        square(0., 0., 10., false),
+        square(20., 0., 5., false),
+        square(1., 1., 8., true),
+        square(2., 2., 6., false),
+        square(3., 3., 1., true),
+    ]);
+
+    assert_eq!(polygon.rings().count(), 5);
+    assert_eq!(
+        starts(&polygon),
+        [(0, vec![10]), (5, vec![]), (15, vec![20])]
+    );
+}
+
+#[test]
+fn hole_touching_its_outer_ring() {
+    // A counter-clockwise diamond with its left corner on the outer ring
+    let hole = [(0., 5.), (3., 2.), (6., 5.), (3., 8.), (0., 5.)]
+        .map(|(x, y)| Point { x, y })
+        .to_vec();
+    let polygon = polygon_of(&[square(0., 0., 10., false), hole]);
+
+    assert_eq!(starts(&polygon), [(0, vec![5])]);
+}
+
+#[test]
+fn holes_without_outer_ring_are_outer_rings() {
+    let polygon = polygon_of(&[square(0., 0., 1., true), square(5., 5., 1., true)]);
+
+    assert_eq!(starts(&polygon), [(0, vec![]), (5, vec![])]);
+}
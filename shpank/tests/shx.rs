@@ -0,0 +1,127 @@
+mod common;
+
+use std::io::Cursor;
+
+use common::{shp_file, shx_file, RecordBuilder};
+use shpank::{
+    parse::{Error, Parser},
+    shape::{Shape, ShapeType},
+    shx::ShpReader,
+};
+
+fn records() -> Vec<Vec<u8>> {
+    vec![
+        RecordBuilder::new(ShapeType::Point)
+            .doubles(&[1., 1.])
+            .build(),
+        RecordBuilder::new(ShapeType::MultiPoint)
+            .doubles(&[0., 0., 2., 2.])
+            .integer(2)
+            .points(&[(0., 0.), (2., 2.)])
+            .build(),
+        RecordBuilder::new(ShapeType::Point)
+            .doubles(&[3., 3.])
+            .build(),
+    ]
+}
+
+#[test]
+fn parse_index() {
+    let shx = Parser::parse_shx_buffer(&shx_file(&records())).unwrap();
+
+    let offsets: Vec<_> = shx.records.iter().map(|r| r.offset.num_bytes()).collect();
+    let lengths: Vec<_> = shx.records.iter().map(|r| r.num_bytes()).collect();
+
+    assert_eq!(offsets, [100, 128, 208]);
+    assert_eq!(lengths, [28, 80, 28]);
+}
+
+#[test]
+fn random_access() {
+    let records = records();
+    let shx = Parser::parse_shx_buffer(&shx_file(&records)).unwrap();
+    let mut reader = ShpReader::new(Cursor::new(shp_file(&records)), shx).unwrap();
+
+    assert_eq!(reader.num_records(), 3);
+
+    let Shape::Point(point) = reader.read_record(2).unwrap().shape else {
+        panic!("expected a point");
+    };
+    assert_eq!(point.x, 3.);
+
+    let Shape::MultiPoint(multipoint) = reader.read_record(1).unwrap().shape else {
+        panic!("expected a multipoint");
+    };
+    assert_eq!(multipoint.points.len(), 2);
+
+    assert!(matches!(
+        reader.read_record(3),
+        Err(Error::RecordOutOfRange {
+            index: 3,
+            num_records: 3
+        })
+    ));
+}
+
+#[test]
+fn corrupt_record_does_not_affect_others() {
+    let mut records = records();
+    // Not a shape type
+    records[1][8] = 99;
+
+    let shx = Parser::parse_shx_buffer(&shx_file(&records)).unwrap();
+    let mut reader = ShpReader::new(Cursor::new(shp_file(&records)), shx).unwrap();
+
+    assert!(reader.read_record(1).is_err());
+    assert!(reader.read_record(2).is_ok());
+}
+
+#[test]
+fn parallel_keeps_order() {
+    let records: Vec<_> = (0..25)
+        .map(|i| {
+            RecordBuilder::new(ShapeType::Point)
+                .doubles(&[i as f64, 0.])
+                .build()
+        })
+        .collect();
+    let shp = shp_file(&records);
+    let shx = Parser::parse_shx_buffer(&shx_file(&records)).unwrap();
+
+    for threads in [0, 1, 2, 7, 100] {
+        let parsed = Parser::parse_shp_buffer_parallel(&shp, &shx, threads).unwrap();
+
+        let xs: Vec<_> = parsed
+            .records
+            .iter()
+            .map(|record| match record.shape {
+                Shape::Point(p) => p.x,
+                _ => panic!("expected points"),
+            })
+            .collect();
+
+        assert_eq!(xs, (0..25).map(|i| i as f64).collect::<Vec<_>>());
+    }
+
+    // Outdated indexes
+    let mut overlapping = shx.clone();
+    overlapping.records.swap(3, 4);
+    let mut too_long = shx.clone();
+    too_long.records[24].content_length.0 += 2;
+    for shx in [overlapping, too_long] {
+        assert!(matches!(
+            Parser::parse_shp_buffer_parallel(&shp, &shx, 2),
+            Err(Error::UnexpectedData(_))
+        ));
+    }
+
+    // The last record's 20 content bytes start after its header at 100 + 24 * 28 + 8
+    let err = Parser::parse_shp_buffer_parallel(&shp[..shp.len() - 5], &shx, 2).unwrap_err();
+    assert!(matches!(
+        err,
+        Error::Truncated {
+            offset: 780,
+            needed: 20
+        }
+    ));
+}
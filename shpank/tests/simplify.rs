@@ -0,0 +1,201 @@
+mod common;
+
+use common::{points, polygon_of};
+use shpank::{
+    rings::{Orientation, PolygonRings},
+    shape::{Measures, MinimumBoundingRectangle, Point, PolyLine, PolylineZ, Shape},
+    simplify::Algorithm,
+};
+
+/// A clockwise circle of the given number of points, with the first point repeated.
+fn circle(radius: f64, n: usize) -> Vec<Point> {
+    (0..=n)
+        .map(|i| {
+            let angle = -((i % n) as f64) * std::f64::consts::TAU / n as f64;
+            Point {
+                x: radius * angle.cos(),
+                y: radius * angle.sin(),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn lines() {
+    // Two wiggly legs of an L
+    let zigzag = points(&[
+        (0., 0.),
+        (1., 0.1),
+        (2., -0.1),
+        (3., 0.),
+        (3.1, 1.),
+        (2.9, 2.),
+        (3., 3.),
+    ]);
+    let line = PolyLine {
+        mbr: MinimumBoundingRectangle::from_points(&zigzag).unwrap(),
+        parts: vec![0],
+        points: zigzag,
+    };
+
+    for algorithm in [Algorithm::DouglasPeucker, Algorithm::VisvalingamWhyatt] {
+        let simplified = line.simplify(algorithm, 0.5);
+        assert_eq!(
+            simplified.points,
+            points(&[(0., 0.), (3., 0.), (3., 3.)]),
+            "{algorithm:?}"
+        );
+        assert_eq!(simplified.mbr.x, 0.0..3.0);
+    }
+
+    // Nothing is below the tolerance
+    assert_eq!(
+        line.simplify(Algorithm::DouglasPeucker, 0.01).points.len(),
+        7
+    );
+}
+
+#[test]
+fn z_values_follow_their_points() {
+    let zigzag = points(&[
+        (0., 0.),
+        (1., 0.1),
+        (2., -0.1),
+        (3., 0.),
+        (3.1, 1.),
+        (3., 3.),
+    ]);
+    let line = PolylineZ {
+        mbr: MinimumBoundingRectangle::from_points(&zigzag).unwrap(),
+        parts: vec![0],
+        points: zigzag,
+        z_range: 0.0..0.0,
+        z: vec![5., 9., -4., 6., 1., 7.],
+        m: Some(Measures {
+            range: None,
+            values: vec![Some(0.), Some(1.), None, Some(3.), Some(4.), None],
+        }),
+    };
+
+    let simplified = line.simplify(Algorithm::DouglasPeucker, 0.5);
+    assert_eq!(simplified.points, points(&[(0., 0.), (3., 0.), (3., 3.)]));
+    assert_eq!(simplified.z, [5., 6., 7.]);
+    assert_eq!(simplified.z_range, 5.0..7.0);
+
+    let m = simplified.m.unwrap();
+    assert_eq!(m.values, [Some(0.), Some(3.), None]);
+    assert_eq!(m.range, Some(0.0..3.0));
+}
+
+#[test]
+fn rings_stay_closed_and_valid() {
+    let circle = polygon_of(&[circle(100., 360)]);
+
+    for algorithm in [Algorithm::DouglasPeucker, Algorithm::VisvalingamWhyatt] {
+        let mut previous = 361;
+        for tolerance in [0.1, 1., 10., 1000.] {
+            let simplified = circle.simplify(algorithm, tolerance);
+            let points = &simplified.points;
+
+            assert!(points.len() <= previous, "{algorithm:?} at {tolerance}");
+            assert!(points.len() >= 4, "{algorithm:?} at {tolerance}");
+            assert_eq!(points.first(), points.last());
+            assert_eq!(Orientation::of(points), Orientation::Clockwise);
+
+            let shape = Shape::Polygon(simplified.clone());
+            assert!(shape.validate(1).is_empty(), "{algorithm:?} at {tolerance}");
+            previous = points.len();
+        }
+    }
+}
+
+#[test]
+fn holes_stay_inside() {
+    // The bottom of the outer ring bulges out, enough to hold a hole
+    let outer = points(&[
+        (0., 0.),
+        (0., 10.),
+        (10., 10.),
+        (10., 0.),
+        (5., -2.),
+        (0., 0.),
+    ]);
+    let hole = points(&[
+        (4.5, -1.5),
+        (5.5, -1.5),
+        (5.5, -0.5),
+        (4.5, -0.5),
+        (4.5, -1.5),
+    ]);
+    let bulge = Point { x: 5., y: -2. };
+
+    // Without the hole the bulge is simplified away
+    let simplified =
+        polygon_of(std::slice::from_ref(&outer)).simplify(Algorithm::DouglasPeucker, 3.);
+    assert!(!simplified.points.contains(&bulge));
+
+    let simplified = polygon_of(&[outer, hole]).simplify(Algorithm::DouglasPeucker, 3.);
+    assert!(simplified.points.contains(&bulge));
+    assert!(Shape::Polygon(simplified.clone()).validate(1).is_empty());
+
+    let polygons = simplified.polygons();
+    assert!(matches!(
+        &polygons[..],
+        [PolygonRings { interiors, .. }] if interiors.len() == 1
+    ));
+}
+
+#[test]
+fn problems_are_compared_by_part() {
+    // As in `holes_stay_inside`
+    let outer = points(&[
+        (0., 0.),
+        (0., 10.),
+        (10., 10.),
+        (10., 0.),
+        (5., -2.),
+        (0., 0.),
+    ]);
+    let hole = points(&[
+        (4.5, -1.5),
+        (5.5, -1.5),
+        (5.5, -0.5),
+        (4.5, -0.5),
+        (4.5, -1.5),
+    ]);
+    // A tiny loop along the bottom edge crosses itself, and is simplified away
+    let looped = points(&[
+        (20., 0.),
+        (20., 10.),
+        (30., 10.),
+        (30., 0.),
+        (26., 0.),
+        (24.9, 0.1),
+        (25.1, 0.1),
+        (24., 0.),
+        (20., 0.),
+    ]);
+    let bulge = Point { x: 5., y: -2. };
+
+    let original = polygon_of(&[outer, hole, looped]);
+    assert_eq!(Shape::Polygon(original.clone()).validate(1).len(), 1);
+
+    // Losing the bulge leaves the hole outside, which is as many problems as before,
+    // but not the same one
+    let simplified = original.simplify(Algorithm::DouglasPeucker, 3.);
+    assert!(simplified.points.contains(&bulge));
+    assert!(Shape::Polygon(simplified).validate(1).is_empty());
+}
+
+#[test]
+fn algorithm_names() {
+    assert_eq!(
+        "dp".parse::<Algorithm>().unwrap(),
+        Algorithm::DouglasPeucker
+    );
+    assert_eq!(
+        "Visvalingam-Whyatt".parse::<Algorithm>().unwrap(),
+        Algorithm::VisvalingamWhyatt
+    );
+    assert!("bezier".parse::<Algorithm>().is_err());
+}
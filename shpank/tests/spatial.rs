@@ -0,0 +1,156 @@
+mod common;
+
+use std::path::PathBuf;
+
+use common::{shp_file, DbfBuilder, RecordBuilder};
+use shpank::{
+    dbase::FieldType,
+    parse::{Parser, Recovery},
+    shape::Shape,
+    shape::ShapeType,
+    spatial::{RecordPairs, Spatial},
+};
+
+/// Writes the files to a fresh directory, returning the `.shp` and `.dbf` paths.
+fn write_pair(name: &str, shp: &[u8], dbf: &[u8]) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("shpank-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let (shp_path, dbf_path) = (dir.join("layer.shp"), dir.join("layer.dbf"));
+    std::fs::write(&shp_path, shp).unwrap();
+    std::fs::write(&dbf_path, dbf).unwrap();
+
+    (shp_path, dbf_path)
+}
+
+#[test]
+fn deleted_rows_remove_their_shapes() {
+    let point = |x| {
+        RecordBuilder::new(ShapeType::Point)
+            .doubles(&[x, x])
+            .build()
+    };
+    let shp = shp_file(&[point(1.), point(2.), point(3.)]);
+    let dbf = DbfBuilder::new()
+        .field("name", FieldType::Character, 10, 0)
+        .record(&[b"one"])
+        .record_with_flag(b'*', &[b"two"])
+        .record(&[b"three"])
+        .build();
+
+    let (shp, dbf) = write_pair("deleted", &shp, &dbf);
+    let spatial = Spatial::new(&shp, &dbf).unwrap();
+
+    assert_eq!(spatial.deleted, [1]);
+    let pairs: Vec<_> = spatial
+        .records()
+        .map(|(shp, dbf)| match shp.shape {
+            Shape::Point(p) => (p.x, dbf.entries[0].to_string()),
+            _ => panic!("expected points"),
+        })
+        .collect();
+    assert_eq!(pairs, [(1., "one".into()), (3., "three".into())]);
+
+    std::fs::remove_dir_all(shp.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn lenient_drops_rows_of_lost_records() {
+    let point = |x| {
+        RecordBuilder::new(ShapeType::Point)
+            .doubles(&[x, x])
+            .build()
+    };
+    let mut shp = shp_file(&[point(1.), point(2.), point(3.)]);
+    // Not a record header, and without a .shx the records after it can't be found
+    shp[100 + 28 + 4..100 + 28 + 8].copy_from_slice(&(-1i32).to_be_bytes());
+    let dbf = DbfBuilder::new()
+        .field("name", FieldType::Character, 10, 0)
+        .record(&[b"one"])
+        .record(&[b"two"])
+        .record(&[b"three"])
+        .build();
+
+    let (shp, dbf) = write_pair("lost", &shp, &dbf);
+    let spatial = Spatial::with_recovery(&shp, &dbf, Recovery::Lenient).unwrap();
+
+    assert_eq!(spatial.shp.dropped, [1, 2]);
+    assert_eq!(spatial.dbf.records.len(), 1);
+    assert_eq!(spatial.dbf.records[0].entries[0].to_string(), "one");
+
+    std::fs::remove_dir_all(shp.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn crs_from_prj() {
+    let shp = shp_file(&[RecordBuilder::new(ShapeType::Point)
+        .doubles(&[10.75, 59.91])
+        .build()]);
+    let dbf = DbfBuilder::new()
+        .field("name", FieldType::Character, 10, 0)
+        .record(&[b"Oslo"])
+        .build();
+
+    let (shp, dbf) = write_pair("crs", &shp, &dbf);
+    assert_eq!(Spatial::new(&shp, &dbf).unwrap().crs, None);
+
+    std::fs::write(
+        shp.with_extension("prj"),
+        r#"GEOGCS["GCS_WGS_1984",DATUM["D_WGS_1984",SPHEROID["WGS_1984",6378137.0,298.257223563]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]]"#,
+    )
+    .unwrap();
+    let crs = Spatial::new(&shp, &dbf).unwrap().crs.unwrap();
+    assert_eq!(crs.epsg, Some(4326));
+
+    // The shapes are still usable without it
+    std::fs::write(shp.with_extension("prj"), "GEOGCS[").unwrap();
+    let spatial = Spatial::new(&shp, &dbf).unwrap();
+    assert!(spatial.crs.is_none());
+    assert_eq!(spatial.warnings.len(), 1);
+    assert_eq!(spatial.records().count(), 1);
+
+    // Nor if it isn't UTF-8
+    std::fs::write(shp.with_extension("prj"), b"GEOGCS[\"Tr\xF8ndelag\"").unwrap();
+    let spatial = Spatial::new(&shp, &dbf).unwrap();
+    assert!(spatial.crs.is_none());
+    assert_eq!(spatial.warnings.len(), 1);
+
+    std::fs::remove_dir_all(shp.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn streamed_pairs_with_mismatched_counts() {
+    let point = |x| {
+        RecordBuilder::new(ShapeType::Point)
+            .doubles(&[x, x])
+            .build()
+    };
+    let dbf = |rows: &[&[u8]]| {
+        rows.iter()
+            .fold(
+                DbfBuilder::new().field("name", FieldType::Character, 10, 0),
+                |builder, row| builder.record(&[row]),
+            )
+            .build()
+    };
+
+    // Pairs up to the shorter file, then an error for the record left over in either
+    for (shp, dbf, longer) in [
+        (shp_file(&[point(1.), point(2.)]), dbf(&[b"one"]), ".shp"),
+        (shp_file(&[point(1.)]), dbf(&[b"one", b"two"]), ".dbf"),
+    ] {
+        let mut shp_parser = Parser::with_reader(&shp[..]);
+        let mut dbf_parser = Parser::with_reader(&dbf[..]);
+        let header = dbf_parser.parse_dbase_header().unwrap();
+        let mut pairs = RecordPairs::new(
+            shp_parser.shp_records().unwrap(),
+            dbf_parser.dbf_records(&header),
+        );
+
+        let (_, row) = pairs.next().unwrap().unwrap();
+        assert_eq!(row.entries[0].to_string(), "one");
+        let error = pairs.next().unwrap().unwrap_err().to_string();
+        assert!(error.contains(longer), "{error}");
+        assert!(pairs.next().is_none());
+    }
+}
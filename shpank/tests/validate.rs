@@ -0,0 +1,180 @@
+mod common;
+
+use common::{layout, polygon};
+use shpank::{
+    rings::Orientation,
+    shape::{Point, PolyLine, PolygonZ, Shape},
+    validate::{Issue, Problem},
+};
+
+fn problems(shape: &Shape) -> Vec<(usize, usize, Problem)> {
+    shape
+        .validate(7)
+        .into_iter()
+        .map(|issue| {
+            assert_eq!(issue.record_number, 7);
+            (issue.part, issue.vertex, issue.problem)
+        })
+        .collect()
+}
+
+#[test]
+fn ring_problems() {
+    // Not closed, with a duplicate point and a spike out to (4, 2)
+    let mut shape = Shape::Polygon(polygon(&[&[
+        (0., 0.),
+        (0., 2.),
+        (0., 2.),
+        (4., 2.),
+        (2., 2.),
+        (2., 0.),
+    ]]));
+
+    assert_eq!(
+        problems(&shape),
+        [
+            (0, 2, Problem::DuplicatePoint),
+            (0, 5, Problem::UnclosedRing),
+            (0, 3, Problem::Spike),
+        ]
+    );
+    assert_eq!(
+        shape.validate(7)[1].to_string(),
+        "record 7, part 0, point 5: ring is not closed"
+    );
+
+    shape.repair();
+    assert!(problems(&shape).is_empty());
+    assert_eq!(shape.area(), 4.);
+    let Shape::Polygon(polygon) = &shape else {
+        panic!("expected a polygon");
+    };
+    assert_eq!(polygon.points.len(), 5);
+    assert_eq!(polygon.mbr.x, 0.0..2.0);
+}
+
+#[test]
+fn z_shapes_are_checked_not_repaired() {
+    // Not closed
+    let polygon = polygon(&[&[(0., 0.), (0., 2.), (2., 2.), (2., 0.)]]);
+    let mut shape = Shape::PolygonZ(PolygonZ {
+        mbr: polygon.mbr,
+        parts: polygon.parts,
+        z: vec![5.; polygon.points.len()],
+        points: polygon.points,
+        z_range: 5.0..5.0,
+        m: None,
+    });
+
+    assert_eq!(problems(&shape), [(0, 3, Problem::UnclosedRing)]);
+    assert!(!shape.can_repair());
+
+    shape.repair();
+    assert_eq!(problems(&shape).len(), 1);
+}
+
+#[test]
+fn bow_tie() {
+    // The diagonals cross
+    let mut shape = Shape::Polygon(polygon(&[&[
+        (0., 0.),
+        (0., 2.),
+        (2., 0.),
+        (2., 2.),
+        (0., 0.),
+    ]]));
+
+    assert_eq!(
+        problems(&shape),
+        [(
+            0,
+            1,
+            Problem::SelfIntersection {
+                other_part: 0,
+                other_vertex: 3,
+                at: Point { x: 1., y: 1. },
+            }
+        )]
+    );
+
+    shape.repair();
+    assert!(problems(&shape).is_empty());
+    let Shape::Polygon(polygon) = &shape else {
+        panic!("expected a polygon");
+    };
+    assert_eq!(polygon.parts, [0, 4]);
+    assert!(polygon
+        .rings()
+        .all(|ring| Orientation::of(ring) == Orientation::Clockwise));
+    assert_eq!(shape.area(), 2.);
+}
+
+#[test]
+fn orientation_by_nesting() {
+    // Counter-clockwise outer ring, clockwise hole
+    let mut shape = Shape::Polygon(polygon(&[
+        &[(0., 0.), (4., 0.), (4., 4.), (0., 4.), (0., 0.)],
+        &[(1., 1.), (1., 2.), (2., 2.), (2., 1.), (1., 1.)],
+    ]));
+
+    assert_eq!(
+        problems(&shape),
+        [
+            (0, 0, Problem::WrongOrientation),
+            (1, 0, Problem::WrongOrientation),
+        ]
+    );
+
+    shape.repair();
+    assert!(problems(&shape).is_empty());
+    let Shape::Polygon(polygon) = &shape else {
+        panic!("expected a polygon");
+    };
+    let rings = polygon.polygons();
+    assert_eq!(rings.len(), 1);
+    assert_eq!(
+        (rings[0].exterior.start, rings[0].interiors[0].start),
+        (0, 5)
+    );
+}
+
+#[test]
+fn lines() {
+    let (parts, points, mbr) = layout(&[&[(0., 0.), (1., 1.), (1., 1.)], &[(5., 5.), (5., 5.)]]);
+    let mut shape = Shape::PolyLine(PolyLine { mbr, parts, points });
+
+    let issues = shape.validate(1);
+    assert_eq!(
+        issues,
+        [
+            Issue {
+                record_number: 1,
+                part: 0,
+                vertex: 2,
+                problem: Problem::DuplicatePoint,
+            },
+            Issue {
+                record_number: 1,
+                part: 1,
+                vertex: 1,
+                problem: Problem::DuplicatePoint,
+            },
+            Issue {
+                record_number: 1,
+                part: 1,
+                vertex: 0,
+                problem: Problem::TooFewPoints,
+            },
+        ]
+    );
+
+    shape.repair();
+    assert!(shape.validate(1).is_empty());
+    assert_eq!(shape.length(), 2f64.sqrt());
+
+    // Nothing left
+    let (parts, points, mbr) = layout(&[&[(3., 3.), (3., 3.)]]);
+    let mut shape = Shape::PolyLine(PolyLine { mbr, parts, points });
+    shape.repair();
+    assert!(matches!(shape, Shape::Null));
+}
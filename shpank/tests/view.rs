@@ -0,0 +1,137 @@
+mod common;
+
+use common::{shp_file, shx_file, RecordBuilder};
+use shpank::{
+    parse::{Error, Parser},
+    shape::{Shape, ShapeType},
+    view::ShpView,
+};
+
+fn records() -> Vec<Vec<u8>> {
+    vec![
+        RecordBuilder::new(ShapeType::Point)
+            .doubles(&[1., 2.])
+            .build(),
+        RecordBuilder::new(ShapeType::PolyLine)
+            .doubles(&[0., 0., 10., 10.])
+            .integer(2)
+            .integer(4)
+            .integer(0)
+            .integer(2)
+            .points(&[(0., 0.), (10., 0.), (10., 10.), (0., 10.)])
+            .build(),
+        RecordBuilder::new(ShapeType::Null).build(),
+    ]
+}
+
+#[test]
+fn walk_records() {
+    let bytes = shp_file(&records());
+    let view = ShpView::new(&bytes).unwrap();
+
+    let records: Vec<_> = view.records().collect::<Result<_, _>>().unwrap();
+    assert_eq!(records.len(), 3);
+
+    let offsets: Vec<_> = records.iter().map(|r| r.offset()).collect();
+    assert_eq!(offsets, [100, 128, 252]);
+
+    let point = records[0].mbr().unwrap().unwrap();
+    assert_eq!((point.x, point.y), (1.0..1.0, 2.0..2.0));
+
+    let line = records[1].mbr().unwrap().unwrap();
+    assert_eq!((line.x, line.y), (0.0..10.0, 0.0..10.0));
+
+    assert!(records[2].mbr().unwrap().is_none());
+}
+
+#[test]
+fn lazy_points() {
+    let bytes = shp_file(&records());
+    let view = ShpView::new(&bytes).unwrap();
+    let line = view.records().nth(1).unwrap().unwrap();
+
+    assert_eq!(line.parts().unwrap().iter().collect::<Vec<_>>(), [0, 2]);
+
+    let points = line.points().unwrap();
+    assert_eq!(points.len(), 4);
+    let p = points.get(2).unwrap();
+    assert_eq!((p.x, p.y), (10., 10.));
+    assert!(points.get(4).is_none());
+    assert!(points.get(usize::MAX).is_none());
+    assert!(line.parts().unwrap().get(usize::MAX).is_none());
+
+    let Shape::PolyLine(owned) = line.shape().unwrap() else {
+        panic!("expected a polyline");
+    };
+    assert!(owned
+        .points
+        .iter()
+        .zip(points.iter())
+        .all(|(a, b)| (a.x, a.y) == (b.x, b.y)));
+}
+
+#[test]
+fn random_access_via_index() {
+    let records = records();
+    let bytes = shp_file(&records);
+    let shx = Parser::parse_shx_buffer(&shx_file(&records)).unwrap();
+    let view = ShpView::new(&bytes).unwrap();
+
+    let last = view.record_at(&shx.records[2]).unwrap();
+    assert!(matches!(last.shape_type().unwrap(), ShapeType::Null));
+
+    let mut wrong = shx.records[0];
+    wrong.content_length.0 += 2;
+    assert!(matches!(
+        view.record_at(&wrong),
+        Err(Error::RecordLengthMismatch { offset: 100, .. })
+    ));
+}
+
+#[test]
+fn bad_point_count() {
+    let bytes = shp_file(&[RecordBuilder::new(ShapeType::MultiPoint)
+        .doubles(&[0., 0., 0., 0.])
+        .integer(3)
+        .points(&[(0., 0.)])
+        .build()]);
+    let view = ShpView::new(&bytes).unwrap();
+    let record = view.records().next().unwrap().unwrap();
+
+    // The bounding box is still readable
+    assert!(record.mbr().unwrap().is_some());
+    assert!(matches!(
+        record.points(),
+        Err(Error::BadCount {
+            offset: 144,
+            count: 3
+        })
+    ));
+}
+
+#[test]
+fn short_content_offset() {
+    // Only the shape type, so the point is missing
+    let bytes = shp_file(&[RecordBuilder::new(ShapeType::Point).build()]);
+    let view = ShpView::new(&bytes).unwrap();
+    let record = view.records().next().unwrap().unwrap();
+
+    assert!(matches!(
+        record.mbr(),
+        Err(Error::Truncated {
+            offset: 112,
+            needed: 8
+        })
+    ));
+}
+
+#[test]
+fn stops_after_truncation() {
+    let mut bytes = shp_file(&records());
+    bytes.truncate(bytes.len() - 20);
+    let view = ShpView::new(&bytes).unwrap();
+
+    let results: Vec<_> = view.records().collect();
+    assert_eq!(results.len(), 2);
+    assert!(matches!(results[1], Err(Error::Truncated { .. })));
+}
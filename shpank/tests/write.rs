@@ -0,0 +1,373 @@
+mod common;
+
+use std::io::Cursor;
+
+use common::{points, DbfBuilder};
+use shpank::{
+    dbase::{
+        DbaseDate, DbaseFile, DbaseHeader, DbaseRecord, DbaseValue, FieldDescriptor, FieldType,
+    },
+    parse::{Error, Parser},
+    shape::{
+        Measures, MinimumBoundingRectangle, Point, PolyLine, Polygon, PolylineZ, Shape, ShapeType,
+    },
+    write::{DbaseWriter, ShpWriter},
+};
+
+fn write(shape_type: ShapeType, shapes: &[Shape]) -> (Vec<u8>, Vec<u8>) {
+    let mut writer = ShpWriter::new(Cursor::new(vec![]), Cursor::new(vec![]), shape_type).unwrap();
+    for shape in shapes {
+        writer.write_shape(shape).unwrap();
+    }
+
+    let (shp, shx) = writer.finish().unwrap();
+    (shp.into_inner(), shx.into_inner())
+}
+
+/// Bounding boxes are recomputed, so the stored one doesn't matter.
+fn stale_mbr() -> MinimumBoundingRectangle {
+    MinimumBoundingRectangle {
+        x: 0.0..0.0,
+        y: 0.0..0.0,
+    }
+}
+
+#[test]
+fn roundtrip() {
+    let square = Shape::Polygon(Polygon {
+        mbr: stale_mbr(),
+        parts: vec![0],
+        points: points(&[(1., 1.), (1., 2.), (2., 2.), (2., 1.), (1., 1.)]),
+    });
+    let triangle = Shape::Polygon(Polygon {
+        mbr: stale_mbr(),
+        parts: vec![0],
+        points: points(&[(-3., 5.), (0., 6.), (-1., 4.), (-3., 5.)]),
+    });
+
+    let (shp, shx) = write(ShapeType::Polygon, &[square, Shape::Null, triangle]);
+
+    let parsed = Parser::parse_shp_buffer(&shp).unwrap();
+    assert_eq!(parsed.header.shape_type, ShapeType::Polygon);
+    assert_eq!(parsed.header.file_length.num_bytes(), shp.len());
+    assert_eq!(parsed.header.mbr.x, -3.0..2.0);
+    assert_eq!(parsed.header.mbr.y, 1.0..6.0);
+    assert_eq!(parsed.records.len(), 3);
+
+    let Shape::Polygon(square) = &parsed.records[0].shape else {
+        panic!("expected a polygon");
+    };
+    assert_eq!(square.mbr.x, 1.0..2.0);
+    assert_eq!(square.points.len(), 5);
+    assert!(matches!(parsed.records[1].shape, Shape::Null));
+
+    let index = Parser::parse_shx_buffer(&shx).unwrap();
+    assert_eq!(index.header.file_length.num_bytes(), shx.len());
+    assert_eq!(index.header.mbr.x, -3.0..2.0);
+
+    let offsets: Vec<_> = index.records.iter().map(|r| r.offset.num_bytes()).collect();
+    assert_eq!(offsets, [100, 236, 248]);
+}
+
+#[test]
+fn z_and_m_ranges() {
+    let line = Shape::PolylineZ(PolylineZ {
+        mbr: stale_mbr(),
+        parts: vec![0],
+        points: points(&[(0., 0.), (1., 0.), (2., 0.)]),
+        z_range: 0.0..0.0,
+        z: vec![10., -5., 3.],
+        m: Some(Measures {
+            range: None,
+            values: vec![Some(2.), None, Some(7.)],
+        }),
+    });
+
+    let (shp, _) = write(ShapeType::PolylineZ, &[line]);
+    let parsed = Parser::parse_shp_buffer(&shp).unwrap();
+
+    assert_eq!(parsed.header.z_range, -5.0..10.0);
+    assert_eq!(parsed.header.m_range, 2.0..7.0);
+
+    let Shape::PolylineZ(line) = &parsed.records[0].shape else {
+        panic!("expected a polyline");
+    };
+    assert_eq!(line.z_range, -5.0..10.0);
+
+    let m = line.m.as_ref().unwrap();
+    assert_eq!(m.range, Some(2.0..7.0));
+    assert_eq!(m.values, [Some(2.), None, Some(7.)]);
+}
+
+#[test]
+fn shape_type_mismatch() {
+    let mut writer =
+        ShpWriter::new(Cursor::new(vec![]), Cursor::new(vec![]), ShapeType::Polygon).unwrap();
+
+    assert!(matches!(
+        writer.write_shape(&Shape::Point(Point { x: 0., y: 0. })),
+        Err(Error::ShapeTypeMismatch {
+            expected: ShapeType::Polygon,
+            actual: ShapeType::Point
+        })
+    ));
+    assert_eq!(writer.num_records(), 0);
+}
+
+#[test]
+fn dbf_roundtrip_is_byte_identical() {
+    let bytes = DbfBuilder::new()
+        .language_driver(0x57)
+        .field("name", FieldType::Character, 10, 0)
+        .field("maxspeed", FieldType::Numeric, 5, 0)
+        .field("area", FieldType::Numeric, 8, 2)
+        .field("opened", FieldType::Date, 8, 0)
+        .field("oneway", FieldType::Logical, 1, 0)
+        .record(&[b"Troms\xF8", b"   80", b"  123.45", b"19700101", b"T"])
+        .record_with_flag(
+            b'*',
+            &[b"Bod\xF8", b"  -30", b"    0.50", b"20240229", b"F"],
+        )
+        .record(&[b"", b"", b"", b"", b"?"])
+        // Blank in other ways: blank logical, overflowed numbers and no date
+        .record(&[b"", b"*****", b"********", b"00000000", b" "])
+        // Written by others: left aligned with a sign, fewer decimals, lowercase logical
+        .record(&[b"Narvik", b"+80", b"     1.5", b"20000101", b"y"])
+        .build();
+
+    let parsed = Parser::parse_dbf_buffer(&bytes).unwrap();
+    assert_eq!(parsed.records[0].entries[0].as_str(), Some("Tromsø"));
+    assert!(parsed.records[3].entries[1..]
+        .iter()
+        .all(DbaseValue::is_null));
+
+    assert_eq!(rewrite(&parsed.header, &parsed.records), bytes);
+
+    // Changed values are written the usual way
+    let mut records = parsed.records.clone();
+    records[4].entries[1] = DbaseValue::Integer(90);
+    let rewritten = rewrite(&parsed.header, &records);
+    let maxspeed = rewritten.len() - 1 - 32 + 10;
+    assert_eq!(&rewritten[maxspeed..maxspeed + 5], b"   90");
+    assert_eq!(&rewritten[maxspeed + 5..maxspeed + 13], b"     1.5");
+}
+
+fn rewrite(header: &DbaseHeader, records: &[DbaseRecord]) -> Vec<u8> {
+    let mut writer = DbaseWriter::with_header(Cursor::new(vec![]), header).unwrap();
+    for record in records {
+        writer.write_record(record).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+#[test]
+fn dbf_roundtrip_keeps_backlink() {
+    let mut bytes = DbfBuilder::new()
+        .visual_foxpro()
+        .field("name", FieldType::Character, 10, 0)
+        .record(&[b"Lofoten"])
+        .build();
+    // The backlink starts after the terminator
+    let (backlink, container) = (32 + 32 + 1, b"..\\nordland.dbc");
+    bytes[backlink..backlink + container.len()].copy_from_slice(container);
+
+    let parsed = Parser::parse_dbf_buffer(&bytes).unwrap();
+    assert_eq!(parsed.header.backlink.len(), 263);
+
+    assert_eq!(rewrite(&parsed.header, &parsed.records), bytes);
+}
+
+#[test]
+fn dbf_roundtrip_with_resolved_memos() {
+    let dir = std::env::temp_dir().join(format!("shpank-write-memo-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let bytes = DbfBuilder::new()
+        .field("name", FieldType::Character, 8, 0)
+        .field("notes", FieldType::Memo, 10, 0)
+        .record(&[b"Urnes", b"         1"])
+        .record(&[b"Borgund", b"          "])
+        .build();
+    let mut dbt = b"Eldste stavkirke\x1A\x1A".to_vec();
+    dbt.splice(0..0, [0; 512]);
+
+    let dbf_path = dir.join("churches.dbf");
+    std::fs::write(&dbf_path, &bytes).unwrap();
+    std::fs::write(dir.join("churches.dbt"), &dbt).unwrap();
+
+    let parsed = Parser::parse_dbf_file(&dbf_path).unwrap();
+    assert_eq!(
+        parsed.records[0].entries[1],
+        DbaseValue::Text("Eldste stavkirke".into())
+    );
+
+    // Still refers to block 1
+    assert_eq!(rewrite(&parsed.header, &parsed.records), bytes);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+fn field(name: &str, type_: FieldType, field_length: usize, decimal_count: u8) -> FieldDescriptor {
+    FieldDescriptor {
+        name: name.into(),
+        type_,
+        field_length,
+        decimal_count,
+        flags: 0,
+        work_area: 0,
+        autoincrement_next: 0,
+        autoincrement_step: 0,
+        indexed: false,
+    }
+}
+
+#[test]
+fn dbf_schema() {
+    let fields = vec![
+        field("name", FieldType::Character, 6, 0),
+        FieldDescriptor {
+            work_area: 3,
+            ..field("lanes", FieldType::Numeric, 2, 0)
+        },
+    ];
+    let date = DbaseDate {
+        year: 2024,
+        month: 6,
+        day: 30,
+    };
+    let mut writer = DbaseWriter::new(Cursor::new(vec![]), fields)
+        .unwrap()
+        .with_date(date);
+
+    // Text is cut short, but not in the middle of a character
+    writer
+        .write_values(&[DbaseValue::Text("Tromsø".into()), DbaseValue::Integer(4)])
+        .unwrap();
+
+    assert!(matches!(
+        writer.write_values(&[DbaseValue::Null, DbaseValue::Integer(100)]),
+        Err(Error::ValueDoesNotFit { field, .. }) if field == "lanes"
+    ));
+    assert!(matches!(
+        writer.write_values(&[DbaseValue::Integer(1), DbaseValue::Null]),
+        Err(Error::ValueDoesNotFit { field, .. }) if field == "name"
+    ));
+    assert_eq!(writer.num_records(), 1);
+
+    let bytes = writer.finish().unwrap().into_inner();
+    let parsed = Parser::parse_dbf_buffer(&bytes).unwrap();
+
+    assert_eq!(
+        (parsed.header.yy, parsed.header.mm, parsed.header.dd),
+        (124, 6, 30)
+    );
+    assert_eq!(parsed.header.num_records, 1);
+    assert_eq!(bytes.len(), parsed.header.dbase_num_bytes_total());
+    // Byte 20 of the second field descriptor
+    assert_eq!(bytes[32 + 32 + 20], 3);
+    assert_eq!(parsed.header.fields[1].work_area, 3);
+    assert_eq!(
+        parsed.records[0].entries,
+        [DbaseValue::Text("Troms".into()), DbaseValue::Integer(4)]
+    );
+}
+
+#[test]
+fn dbf_unsupported_field() {
+    // dBASE 7 only
+    let fields = vec![field("population", FieldType::OrderedDouble, 8, 0)];
+    assert!(matches!(
+        DbaseWriter::new(Cursor::new(vec![]), fields),
+        Err(Error::UnsupportedFieldType(FieldType::OrderedDouble))
+    ));
+
+    let fields = vec![field("population", FieldType::Integer, 8, 0)];
+    assert!(matches!(
+        DbaseWriter::new(Cursor::new(vec![]), fields),
+        Err(Error::BadFieldDescriptor { .. })
+    ));
+}
+
+#[test]
+fn dbf_visual_foxpro() {
+    let mut date_time = 2460000i32.to_le_bytes().to_vec();
+    date_time.extend(3_661_000u32.to_le_bytes());
+
+    let bytes = DbfBuilder::new()
+        .visual_foxpro()
+        .field("population", FieldType::Integer, 4, 0)
+        .field("length", FieldType::Double, 8, 0)
+        .field("price", FieldType::Currency, 8, 4)
+        .field("updated", FieldType::DateTime, 8, 0)
+        .record(&[
+            &(-42i32).to_le_bytes(),
+            &1.25f64.to_le_bytes(),
+            &123_4567i64.to_le_bytes(),
+            &date_time,
+        ])
+        .record(&[&[0; 4], &[0; 8], &[0; 8], &[0; 8]])
+        .build();
+
+    let parsed = Parser::parse_dbf_buffer(&bytes).unwrap();
+    assert_eq!(rewrite(&parsed.header, &parsed.records), bytes);
+
+    // The same values written from scratch
+    let mut writer = DbaseWriter::new(Cursor::new(vec![]), parsed.header.fields.clone()).unwrap();
+    for record in &parsed.records {
+        writer.write_values(&record.entries).unwrap();
+    }
+    let written = Parser::parse_dbf_buffer(&writer.finish().unwrap().into_inner()).unwrap();
+
+    assert!(written.header.is_visual_foxpro());
+    let entries =
+        |dbf: &DbaseFile| -> Vec<_> { dbf.records.iter().map(|r| r.entries.clone()).collect() };
+    assert_eq!(entries(&written), entries(&parsed));
+}
+
+#[test]
+fn bad_shapes() {
+    let line = |parts: Vec<i32>| {
+        Shape::PolyLine(PolyLine {
+            mbr: stale_mbr(),
+            parts,
+            points: points(&[(0., 0.), (1., 0.), (2., 0.)]),
+        })
+    };
+    let line_z = Shape::PolylineZ(PolylineZ {
+        mbr: stale_mbr(),
+        parts: vec![0],
+        points: points(&[(0., 0.), (1., 0.)]),
+        z_range: 0.0..0.0,
+        z: vec![10.],
+        m: None,
+    });
+
+    let mut writer = ShpWriter::new(
+        Cursor::new(vec![]),
+        Cursor::new(vec![]),
+        ShapeType::PolyLine,
+    )
+    .unwrap();
+    for parts in [vec![0, 4], vec![-1], vec![0, 2, 1]] {
+        assert!(matches!(
+            writer.write_shape(&line(parts)),
+            Err(Error::BadShape {
+                shape_type: ShapeType::PolyLine,
+                ..
+            })
+        ));
+    }
+    assert_eq!(writer.num_records(), 0);
+
+    let mut writer = ShpWriter::new(
+        Cursor::new(vec![]),
+        Cursor::new(vec![]),
+        ShapeType::PolylineZ,
+    )
+    .unwrap();
+    assert!(matches!(
+        writer.write_shape(&line_z),
+        Err(Error::BadShape { reason, .. }) if reason == "1 Z values for 2 points"
+    ));
+    assert_eq!(writer.num_records(), 0);
+}